@@ -5,27 +5,33 @@ pub struct Camera {
     pub right_x: i32,
     pub top_y: i32,
     pub bottom_y: i32,
+    map_width: i32,
+    map_height: i32,
 }
 
 const CAMERA_WIDTH: i32 = 48;
 const CAMERA_HEIGHT: i32 = 44;
 
 impl Camera {
-    pub fn new(player_position: Point) -> Self {
-        // View area is 48x44, thus player is at {24,22}
-        Self {
-            left_x: player_position.x - CAMERA_WIDTH / 2, // DISPLAY_WIDTH / 2,
-            right_x: player_position.x + CAMERA_WIDTH / 2, // DISPLAY_WIDTH / 2,
-            top_y: player_position.y - CAMERA_HEIGHT / 2, //DISPLAY_HEIGHT / 2,
-            bottom_y: player_position.y + CAMERA_HEIGHT / 2, //DISPLAY_HEIGHT / 2,
-        }
+    pub fn new(player_position: Point, map: &Map) -> Self {
+        let mut camera = Self {
+            left_x: 0,
+            right_x: 0,
+            top_y: 0,
+            bottom_y: 0,
+            map_width: map.width as i32,
+            map_height: map.height as i32,
+        };
+        camera.on_player_move(player_position);
+        camera
     }
 
     pub fn on_player_move(&mut self, player_position: Point) {
-        self.left_x = player_position.x - CAMERA_WIDTH / 2; //DISPLAY_WIDTH / 2;
-        self.right_x = player_position.x + CAMERA_WIDTH / 2; //DISPLAY_WIDTH / 2;
-        self.top_y = player_position.y - CAMERA_HEIGHT / 2; //DISPLAY_HEIGHT / 2;
-        self.bottom_y = player_position.y + CAMERA_HEIGHT / 2; //DISPLAY_HEIGHT / 2;
+        // View area is 48x44, so the player is normally at {24,22}
+        self.left_x = view_start(player_position.x, self.map_width, CAMERA_WIDTH);
+        self.right_x = self.left_x + CAMERA_WIDTH;
+        self.top_y = view_start(player_position.y, self.map_height, CAMERA_HEIGHT);
+        self.bottom_y = self.top_y + CAMERA_HEIGHT;
     }
 
     pub fn center_point(&self) -> Point {
@@ -35,3 +41,13 @@ impl Camera {
         )
     }
 }
+
+// Maps that fit in the view along an axis stay centred on it rather than
+// scrolling with the player.
+fn view_start(player: i32, map_size: i32, view_size: i32) -> i32 {
+    if map_size <= view_size {
+        (map_size - view_size) / 2
+    } else {
+        player - view_size / 2
+    }
+}
@@ -218,6 +218,17 @@ pub struct Door {
     pub open: bool,
 }
 
+// Looks like wall until found; then the tile opens up to `opens_to`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SecretPassage {
+    pub opens_to: TileType,
+}
+
+// Turns rock into this tile: used up as an item's effect, swung at a wall
+// while equipped, or burrowed through by a mob.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Digs(pub TileType);
+
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AlwaysVisible;
 
@@ -342,7 +353,7 @@ pub struct LootTable(pub String);
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct OtherLevelPosition {
     pub position: Point,
-    pub depth: i32,
+    pub level: LevelId,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -404,10 +415,19 @@ pub struct TownPortal;
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct TeleportTo {
     pub position: Point,
-    pub depth: i32,
+    pub level: LevelId,
     pub player_only: bool,
 }
 
+// Drops whoever steps on it to the level below.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trapdoor;
+
+// Fell from the level above, and lands somewhere on this one when it is next
+// visited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fallen;
+
 #[derive(Debug, Copy, Clone)]
 pub struct WantsToMove {
     pub destination: Point,
@@ -416,7 +436,7 @@ pub struct WantsToMove {
 #[derive(Debug, Copy, Clone)]
 pub struct ApplyTeleport {
     pub destination: Point,
-    pub depth: i32,
+    pub level: LevelId,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
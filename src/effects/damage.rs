@@ -1,6 +1,13 @@
 use crate::prelude::*;
 
-pub fn inflict_damage(ecs: &mut SubWorld, damage: &EffectSpawner, _map: &Map, target: Entity) {
+pub fn inflict_damage(
+    ecs: &mut SubWorld,
+    damage: &EffectSpawner,
+    target: Entity,
+    game_log: &mut GameLog,
+    events: &mut GameEvents,
+    effect_queue: &mut EffectQueue,
+) {
     let attacker_name = damage.creator.map(|c| name_for(&c, ecs).0);
     let target_name = name_for(&target, ecs).0;
     let player_entity = <Entity>::query()
@@ -30,26 +37,30 @@ pub fn inflict_damage(ecs: &mut SubWorld, damage: &EffectSpawner, _map: &Map, ta
                             .append("for")
                             .damage(amount)
                             .append("hp.")
-                            .log();
+                            .log(game_log);
                     } else {
                         crate::gamelog::Logger::new()
                             .npc_name(&target_name)
                             .append("is hit for")
                             .damage(amount)
                             .append("hp.")
-                            .log();
+                            .log(game_log);
                     }
 
                     stats.hit_points.current -= amount;
                     if stats.hit_points.current < 1 {
-                        add_effect(
+                        effect_queue.add_effect(
                             damage.creator,
                             EffectType::EntityDeath,
                             Targets::Single { target },
                         );
                     }
-                    add_effect(None, EffectType::Bloodstain, Targets::Single { target });
-                    add_effect(
+                    effect_queue.add_effect(
+                        None,
+                        EffectType::Bloodstain,
+                        Targets::Single { target },
+                    );
+                    effect_queue.add_effect(
                         None,
                         EffectType::Particle {
                             glyph: to_cp437('‼'),
@@ -60,10 +71,10 @@ pub fn inflict_damage(ecs: &mut SubWorld, damage: &EffectSpawner, _map: &Map, ta
                     );
 
                     if target_is_player {
-                        crate::gamelog::record_event("Damage Taken", amount);
+                        events.record_event("Damage Taken", amount);
                     }
                     if attacker_is_player {
-                        crate::gamelog::record_event("Damage Inflicted", amount);
+                        events.record_event("Damage Inflicted", amount);
                     }
                 }
             }
@@ -77,13 +88,18 @@ pub fn bloodstain(map: &mut Map, indices: Vec<usize>) {
     }
 }
 
-pub fn heal_damage(ecs: &mut SubWorld, heal: &EffectSpawner, target: Entity) {
+pub fn heal_damage(
+    ecs: &mut SubWorld,
+    heal: &EffectSpawner,
+    target: Entity,
+    effect_queue: &mut EffectQueue,
+) {
     let mut entry = ecs.entry_mut(target).unwrap();
     if let Ok(stats) = entry.get_component_mut::<Pools>() {
         if let EffectType::Healing { amount } = heal.effect_type {
             stats.hit_points.current =
                 i32::min(stats.hit_points.max, stats.hit_points.current + amount);
-            add_effect(
+            effect_queue.add_effect(
                 None,
                 EffectType::Particle {
                     glyph: to_cp437('‼'),
@@ -96,12 +112,17 @@ pub fn heal_damage(ecs: &mut SubWorld, heal: &EffectSpawner, target: Entity) {
     }
 }
 
-pub fn restore_mana(ecs: &mut SubWorld, mana: &EffectSpawner, target: Entity) {
+pub fn restore_mana(
+    ecs: &mut SubWorld,
+    mana: &EffectSpawner,
+    target: Entity,
+    effect_queue: &mut EffectQueue,
+) {
     let mut entry = ecs.entry_mut(target).unwrap();
     if let Ok(stats) = entry.get_component_mut::<Pools>() {
         if let EffectType::Mana { amount } = mana.effect_type {
             stats.mana.current = i32::min(stats.mana.max, stats.mana.current + amount);
-            add_effect(
+            effect_queue.add_effect(
                 None,
                 EffectType::Particle {
                     glyph: to_cp437('‼'),
@@ -195,13 +216,13 @@ pub fn damage_over_time(
     }
 }
 
-pub fn death(ecs: &mut SubWorld, effect: &EffectSpawner, target: Entity, map: &Map) {
+pub fn death(ecs: &mut SubWorld, effect: &EffectSpawner, target: Entity, ctx: &mut EffectContext) {
     let mut xp_gain = 0;
     let mut gold_gain = 0.0f32;
 
-    if let Some(idxes) = entity_position(ecs, target, map) {
+    if let Some(idxes) = entity_position(ecs, target, ctx.map) {
         for idx in idxes {
-            crate::spatial::remove_entity(target, idx);
+            ctx.spatial.remove_entity(target, idx);
         }
     }
 
@@ -233,38 +254,37 @@ pub fn death(ecs: &mut SubWorld, effect: &EffectSpawner, target: Entity, map: &M
                                 .color(MAGENTA)
                                 .append("Congratulations, you are now level")
                                 .append(format!("{}", stats.level))
-                                .log();
+                                .log(ctx.game_log);
 
                             // Improve a random attribute
-                            let mut rng = RandomNumberGenerator::new();
-                            match rng.roll_dice(1, 4) {
+                            match ctx.rng.roll_dice(1, 4) {
                                 1 => {
                                     attrs.might.base += 1;
                                     crate::gamelog::Logger::new()
                                         .color(GREEN)
                                         .append("You feel stronger!")
-                                        .log();
+                                        .log(ctx.game_log);
                                 }
                                 2 => {
                                     attrs.fitness.base += 1;
                                     crate::gamelog::Logger::new()
                                         .color(GREEN)
                                         .append("You feel healthier!")
-                                        .log();
+                                        .log(ctx.game_log);
                                 }
                                 3 => {
                                     attrs.quickness.base += 1;
                                     crate::gamelog::Logger::new()
                                         .color(GREEN)
                                         .append("You feel quicker!")
-                                        .log();
+                                        .log(ctx.game_log);
                                 }
                                 _ => {
                                     attrs.intelligence.base += 1;
                                     crate::gamelog::Logger::new()
                                         .color(GREEN)
                                         .append("You feel smarter!")
-                                        .log();
+                                        .log(ctx.game_log);
                                 }
                             }
 
@@ -286,7 +306,7 @@ pub fn death(ecs: &mut SubWorld, effect: &EffectSpawner, target: Entity, map: &M
 
                             for i in 0..10 {
                                 if pos.y - i > 1 {
-                                    add_effect(
+                                    ctx.effect_queue.add_effect(
                                         None,
                                         EffectType::Particle {
                                             glyph: to_cp437('░'),
@@ -294,7 +314,9 @@ pub fn death(ecs: &mut SubWorld, effect: &EffectSpawner, target: Entity, map: &M
                                             lifespan: 400.0,
                                         },
                                         Targets::Tile {
-                                            tile_idx: map.point2d_to_index(*pos - Point::new(0, i)),
+                                            tile_idx: ctx
+                                                .map
+                                                .point2d_to_index(*pos - Point::new(0, i)),
                                         },
                                     );
                                 }
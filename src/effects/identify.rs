@@ -4,6 +4,7 @@ pub fn identify_entity(
     ecs: &SubWorld,
     effect: &EffectSpawner,
     target: Entity,
+    raws: &RawMaster,
     dm: &mut MasterDungeonMap,
     commands: &mut CommandBuffer,
 ) {
@@ -26,7 +27,7 @@ pub fn identify_entity(
 
     if let Ok(entry) = ecs.entry_ref(target) {
         if let Ok(name) = entry.get_component::<Name>() {
-            if !dm.identified_items.contains(&name.0) && is_tag_magic(&name.0) {
+            if !dm.identified_items.contains(&name.0) && is_tag_magic(raws, &name.0) {
                 dm.identified_items.insert(name.0.clone());
             }
             commands.remove_component::<ObfuscatedName>(target);
@@ -1,8 +1,5 @@
 use crate::prelude::*;
-use std::{
-    collections::{HashSet, VecDeque},
-    sync::Mutex,
-};
+use std::collections::VecDeque;
 
 mod damage;
 mod hunger;
@@ -10,13 +7,11 @@ mod identify;
 mod movement;
 mod particles;
 mod targeting;
+mod terrain;
 mod triggers;
 
 pub use targeting::*;
-
-lazy_static! {
-    pub static ref EFFECT_QUEUE: Mutex<VecDeque<EffectSpawner>> = Mutex::new(VecDeque::new());
-}
+pub use terrain::can_dig;
 
 pub enum EffectType {
     Damage {
@@ -58,7 +53,7 @@ pub enum EffectType {
     },
     TeleportTo {
         pos: Point,
-        depth: i32,
+        level: LevelId,
         player_only: bool,
     },
     AttributeEffect {
@@ -76,6 +71,10 @@ pub enum EffectType {
     DamageOverTime {
         damage: i32,
     },
+    Dig {
+        tile: TileType,
+    },
+    Fall,
 }
 
 #[derive(Clone)]
@@ -93,136 +92,70 @@ pub struct EffectSpawner {
     pub targets: Targets,
 }
 
-pub fn add_effect(creator: Option<Entity>, effect_type: EffectType, targets: Targets) {
-    EFFECT_QUEUE.lock().unwrap().push_back(EffectSpawner {
-        creator,
-        effect_type,
-        targets,
-    });
+#[derive(Default)]
+pub struct EffectQueue(VecDeque<EffectSpawner>);
+
+impl EffectQueue {
+    pub fn add_effect(
+        &mut self,
+        creator: Option<Entity>,
+        effect_type: EffectType,
+        targets: Targets,
+    ) {
+        self.0.push_back(EffectSpawner {
+            creator,
+            effect_type,
+            targets,
+        });
+    }
 }
 
-pub fn run_effects_queue(
-    ecs: &mut SubWorld,
-    map: &mut Map,
-    particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    dm: &mut MasterDungeonMap,
-    commands: &mut CommandBuffer,
-) {
-    loop {
-        let effect: Option<EffectSpawner> = EFFECT_QUEUE.lock().unwrap().pop_front();
-        if let Some(effect) = effect {
-            target_applicator(
-                ecs,
-                &effect,
-                map,
-                particle_builder,
-                turn_state,
-                dm,
-                commands,
-            );
-        } else {
-            break;
-        }
+// Everything outside the world that applying an effect can touch.
+pub struct EffectContext<'a> {
+    pub map: &'a mut Map,
+    pub particle_builder: &'a mut ParticleBuilder,
+    pub turn_state: &'a mut TurnState,
+    pub dm: &'a mut MasterDungeonMap,
+    pub rng: &'a mut RandomNumberGenerator,
+    pub raws: &'a RawMaster,
+    pub spatial: &'a mut SpatialMap,
+    pub game_log: &'a mut GameLog,
+    pub events: &'a mut GameEvents,
+    pub effect_queue: &'a mut EffectQueue,
+    pub commands: &'a mut CommandBuffer,
+}
+
+pub fn run_effects_queue(ecs: &mut SubWorld, ctx: &mut EffectContext) {
+    while let Some(effect) = ctx.effect_queue.0.pop_front() {
+        target_applicator(ecs, &effect, ctx);
     }
 }
 
-fn target_applicator(
-    ecs: &mut SubWorld,
-    effect: &EffectSpawner,
-    map: &mut Map,
-    particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    dm: &mut MasterDungeonMap,
-    commands: &mut CommandBuffer,
-) {
+fn target_applicator(ecs: &mut SubWorld, effect: &EffectSpawner, ctx: &mut EffectContext) {
     if let EffectType::ItemUse { item } = effect.effect_type {
-        triggers::item_trigger(
-            effect.creator,
-            item,
-            &effect.targets,
-            ecs,
-            particle_builder,
-            turn_state,
-            map,
-            commands,
-        );
+        triggers::item_trigger(effect.creator, item, &effect.targets, ecs, ctx);
         return;
     }
 
     if let EffectType::CastSpell { spell } = effect.effect_type {
-        triggers::spell_trigger(
-            effect.creator,
-            spell,
-            &effect.targets,
-            ecs,
-            particle_builder,
-            turn_state,
-            map,
-            commands,
-        );
+        triggers::spell_trigger(effect.creator, spell, &effect.targets, ecs, ctx);
         return;
     }
 
     if let EffectType::TriggerFire { trigger } = effect.effect_type {
-        triggers::trigger(
-            effect.creator,
-            trigger,
-            &effect.targets,
-            ecs,
-            particle_builder,
-            turn_state,
-            map,
-            commands,
-        );
+        triggers::trigger(effect.creator, trigger, &effect.targets, ecs, ctx);
         return;
     }
 
     match &effect.targets {
-        Targets::Tile { tile_idx } => affect_tile(
-            ecs,
-            effect,
-            *tile_idx,
-            map,
-            particle_builder,
-            turn_state,
-            dm,
-            commands,
-        ),
-        Targets::Tiles { tiles } => tiles.iter().for_each(|tile_idx| {
-            affect_tile(
-                ecs,
-                effect,
-                *tile_idx,
-                map,
-                particle_builder,
-                turn_state,
-                dm,
-                commands,
-            )
-        }),
-        Targets::Single { target } => affect_entity(
-            ecs,
-            effect,
-            *target,
-            map,
-            particle_builder,
-            turn_state,
-            dm,
-            commands,
-        ),
-        Targets::Area { targets } => targets.iter().for_each(|target| {
-            affect_entity(
-                ecs,
-                effect,
-                *target,
-                map,
-                particle_builder,
-                turn_state,
-                dm,
-                commands,
-            )
-        }),
+        Targets::Tile { tile_idx } => affect_tile(ecs, effect, *tile_idx, ctx),
+        Targets::Tiles { tiles } => tiles
+            .iter()
+            .for_each(|tile_idx| affect_tile(ecs, effect, *tile_idx, ctx)),
+        Targets::Single { target } => affect_entity(ecs, effect, *target, ctx),
+        Targets::Area { targets } => targets
+            .iter()
+            .for_each(|target| affect_entity(ecs, effect, *target, ctx)),
     }
 }
 
@@ -238,6 +171,7 @@ fn tile_effect_hits_entities(effect: &EffectType) -> bool {
         EffectType::LearnSpell { .. } => true,
         EffectType::Slow { .. } => true,
         EffectType::DamageOverTime { .. } => true,
+        EffectType::Fall => true,
         _ => false,
     }
 }
@@ -246,15 +180,11 @@ fn affect_tile(
     ecs: &mut SubWorld,
     effect: &EffectSpawner,
     tile_idx: usize,
-    map: &mut Map,
-    particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    dm: &mut MasterDungeonMap,
-    commands: &mut CommandBuffer,
+    ctx: &mut EffectContext,
 ) {
     if tile_effect_hits_entities(&effect.effect_type) {
-        let pos = map.index_to_point2d(tile_idx);
-        let content: HashSet<Entity> = <(Entity, &Point, Option<&TileSize>)>::query()
+        let pos = ctx.map.index_to_point2d(tile_idx);
+        let content: Vec<Entity> = <(Entity, &Point, Option<&TileSize>)>::query()
             .iter(ecs)
             .filter(|(_, p, s)| {
                 if let Some(size) = s {
@@ -265,28 +195,20 @@ fn affect_tile(
             })
             .map(|(e, _, _)| *e)
             .collect();
-        content.iter().for_each(|entity| {
-            affect_entity(
-                ecs,
-                effect,
-                *entity,
-                map,
-                particle_builder,
-                turn_state,
-                dm,
-                commands,
-            )
-        });
+        content
+            .iter()
+            .for_each(|entity| affect_entity(ecs, effect, *entity, ctx));
     }
 
     match &effect.effect_type {
-        EffectType::Bloodstain => damage::bloodstain(map, vec![tile_idx]),
+        EffectType::Bloodstain => damage::bloodstain(ctx.map, vec![tile_idx]),
         EffectType::Particle { .. } => {
-            particles::particle_to_tile(ecs, vec![tile_idx], effect, map, particle_builder)
+            particles::particle_to_tile(ecs, vec![tile_idx], effect, ctx.map, ctx.particle_builder)
         }
         EffectType::ParticleProjectile { .. } => {
-            particles::projectile(ecs, tile_idx, effect, map, particle_builder)
+            particles::projectile(ecs, tile_idx, effect, ctx.map, ctx.particle_builder)
         }
+        EffectType::Dig { .. } => terrain::dig(ecs, effect, tile_idx, ctx),
         _ => {}
     }
 }
@@ -295,41 +217,49 @@ fn affect_entity(
     ecs: &mut SubWorld,
     effect: &EffectSpawner,
     target: Entity,
-    map: &mut Map,
-    particle_builder: &mut ParticleBuilder,
-    _turn_state: &mut TurnState,
-    dm: &mut MasterDungeonMap,
-    commands: &mut CommandBuffer,
+    ctx: &mut EffectContext,
 ) {
     match &effect.effect_type {
-        EffectType::Damage { .. } => damage::inflict_damage(ecs, effect, map, target),
-        EffectType::EntityDeath => damage::death(ecs, effect, target, map),
+        EffectType::Damage { .. } => damage::inflict_damage(
+            ecs,
+            effect,
+            target,
+            ctx.game_log,
+            ctx.events,
+            ctx.effect_queue,
+        ),
+        EffectType::EntityDeath => damage::death(ecs, effect, target, ctx),
         EffectType::Bloodstain => {
-            if let Some(pos) = entity_position(ecs, target, map) {
-                damage::bloodstain(map, pos)
+            if let Some(pos) = entity_position(ecs, target, ctx.map) {
+                damage::bloodstain(ctx.map, pos)
             }
         }
         EffectType::Particle { .. } => {
-            if let Some(pos) = entity_position(ecs, target, map) {
-                particles::particle_to_tile(ecs, pos, effect, map, particle_builder);
+            if let Some(pos) = entity_position(ecs, target, ctx.map) {
+                particles::particle_to_tile(ecs, pos, effect, ctx.map, ctx.particle_builder);
             }
         }
-        EffectType::Identify => identify::identify_entity(ecs, effect, target, dm, commands),
+        EffectType::Identify => {
+            identify::identify_entity(ecs, effect, target, ctx.raws, ctx.dm, ctx.commands)
+        }
         EffectType::WellFed => hunger::well_fed(ecs, target),
-        EffectType::Healing { .. } => damage::heal_damage(ecs, effect, target),
-        EffectType::Mana { .. } => damage::restore_mana(ecs, effect, target),
-        EffectType::Confusion { .. } => damage::add_confusion(ecs, effect, target, commands),
-        EffectType::TeleportTo { .. } => movement::apply_teleport(ecs, effect, target, commands),
+        EffectType::Healing { .. } => damage::heal_damage(ecs, effect, target, ctx.effect_queue),
+        EffectType::Mana { .. } => damage::restore_mana(ecs, effect, target, ctx.effect_queue),
+        EffectType::Confusion { .. } => damage::add_confusion(ecs, effect, target, ctx.commands),
+        EffectType::TeleportTo { .. } => {
+            movement::apply_teleport(ecs, effect, target, ctx.commands)
+        }
         EffectType::AttributeEffect { .. } => {
-            damage::attribute_effect(ecs, effect, target, commands)
+            damage::attribute_effect(ecs, effect, target, ctx.commands)
         }
         EffectType::LearnSpell { name, spell } => {
-            triggers::learn_spell(ecs, effect, name.to_string(), *spell, commands)
+            triggers::learn_spell(ecs, effect, name.to_string(), *spell, ctx.commands)
         }
-        EffectType::Slow { .. } => damage::slow(ecs, effect, target, commands),
+        EffectType::Slow { .. } => damage::slow(ecs, effect, target, ctx.commands),
         EffectType::DamageOverTime { .. } => {
-            damage::damage_over_time(ecs, effect, target, commands)
+            damage::damage_over_time(ecs, effect, target, ctx.commands)
         }
+        EffectType::Fall => movement::fall(ecs, target, ctx),
         _ => {}
     }
 }
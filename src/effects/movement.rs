@@ -1,5 +1,7 @@
 use crate::prelude::*;
 
+const FALL_DAMAGE_DIE: i32 = 6;
+
 pub fn apply_teleport(
     ecs: &mut SubWorld,
     destination: &EffectSpawner,
@@ -13,7 +15,7 @@ pub fn apply_teleport(
         .unwrap();
     if let EffectType::TeleportTo {
         pos,
-        depth,
+        level,
         player_only,
     } = &destination.effect_type
     {
@@ -22,9 +24,80 @@ pub fn apply_teleport(
                 target,
                 ApplyTeleport {
                     destination: *pos,
-                    depth: *depth,
+                    level: *level,
                 },
             )
         }
     }
 }
+
+// Drops a creature through the floor. The player goes straight down after it;
+// anything else waits on the level below until the player gets there.
+pub fn fall(ecs: &mut SubWorld, target: Entity, ctx: &mut EffectContext) {
+    let (pos, is_player) = match ecs.entry_ref(target) {
+        Ok(entry) if entry.get_component::<Pools>().is_ok() => match entry.get_component::<Point>()
+        {
+            Ok(pos) => (*pos, entry.get_component::<Player>().is_ok()),
+            Err(_) => return,
+        },
+        _ => return,
+    };
+    // The bottom of a branch has nothing underneath it.
+    let below = match ctx.dm.level_below(ctx.map.level_id(), ctx.raws) {
+        Some(below) => below,
+        None => {
+            crate::gamelog::Logger::new()
+                .npc_name(&name_for(&target, ecs).0)
+                .append("stumbles, but there is only solid rock below.")
+                .log(ctx.game_log);
+            return;
+        }
+    };
+
+    let amount = ctx.rng.roll_dice(1, FALL_DAMAGE_DIE);
+    ctx.effect_queue.add_effect(
+        None,
+        EffectType::Damage { amount },
+        Targets::Single { target },
+    );
+    // A monster the fall would kill dies where it stands, so its body is
+    // still somewhere to be buried.
+    let fatal = ecs
+        .entry_ref(target)
+        .ok()
+        .and_then(|entry| {
+            entry
+                .get_component::<Pools>()
+                .ok()
+                .map(|pools| !pools.god_mode && amount >= pools.hit_points.current)
+        })
+        .unwrap_or(false);
+    if fatal && !is_player {
+        crate::gamelog::Logger::new()
+            .npc_name(&name_for(&target, ecs).0)
+            .append("is broken by the fall!")
+            .log(ctx.game_log);
+        return;
+    }
+
+    crate::gamelog::Logger::new()
+        .npc_name(&name_for(&target, ecs).0)
+        .append("falls to the level below!")
+        .log(ctx.game_log);
+
+    ctx.commands.add_component(target, Fallen);
+    if is_player {
+        *ctx.turn_state = TurnState::ChangeLevel { level: below };
+    } else {
+        ctx.spatial
+            .remove_entity(target, ctx.map.point2d_to_index(pos));
+        ctx.commands.remove_component::<Point>(target);
+        ctx.commands.add_component(
+            target,
+            OtherLevelPosition {
+                position: pos,
+                level: below,
+            },
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use crate::prelude::*;
+
+// Rock that isn't part of the level's outer edge.
+pub fn can_dig(map: &Map, idx: usize) -> bool {
+    if idx >= map.tiles.len() {
+        return false;
+    }
+    let pos = map.index_to_point2d(idx);
+    map.tiles[idx].is_diggable()
+        && pos.x > 0
+        && pos.y > 0
+        && pos.x < map.width as i32 - 1
+        && pos.y < map.height as i32 - 1
+}
+
+// Opens up a wall. Everything that caches what is passable or visible is told,
+// and the dungeon's stored copy of the level is updated so the hole is still
+// there when the player comes back.
+pub fn dig(ecs: &mut SubWorld, effect: &EffectSpawner, tile_idx: usize, ctx: &mut EffectContext) {
+    let tile = match effect.effect_type {
+        EffectType::Dig { tile } => tile,
+        _ => return,
+    };
+    if !can_dig(ctx.map, tile_idx) {
+        return;
+    }
+
+    ctx.map.tiles[tile_idx] = tile;
+    ctx.map.populate_blocked();
+    ctx.spatial.populate_blocked_from_map(ctx.map);
+
+    // A secret door or rubble in the wall goes with it.
+    let pos = ctx.map.index_to_point2d(tile_idx);
+    <(Entity, &Point)>::query()
+        .filter(component::<SecretPassage>())
+        .iter(ecs)
+        .filter(|(_, p)| **p == pos)
+        .for_each(|(e, _)| ctx.commands.remove(*e));
+
+    <&mut FieldOfView>::query()
+        .iter_mut(ecs)
+        .for_each(|fov| fov.is_dirty = true);
+
+    ctx.dm.store_map(ctx.map);
+}
@@ -5,10 +5,7 @@ pub fn item_trigger(
     item: Entity,
     targets: &Targets,
     ecs: &mut SubWorld,
-    particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    map: &Map,
-    commands: &mut CommandBuffer,
+    ctx: &mut EffectContext,
 ) {
     let mut entry = ecs.entry_mut(item).unwrap();
     if let Ok(c) = entry.get_component_mut::<Consumable>() {
@@ -17,7 +14,7 @@ pub fn item_trigger(
             crate::gamelog::Logger::new()
                 .append(&name.0)
                 .append("is out of charges!")
-                .log();
+                .log(ctx.game_log);
             return;
         } else {
             c.charges -= 1;
@@ -26,23 +23,14 @@ pub fn item_trigger(
     std::mem::drop(entry);
 
     // use the item via generic system
-    let did_something = event_trigger(
-        creator,
-        item,
-        targets,
-        ecs,
-        particle_builder,
-        turn_state,
-        map,
-        commands,
-    );
+    let did_something = event_trigger(creator, item, targets, ecs, ctx);
 
     // If it was a consumable, then it gets deleted.
     if did_something {
         if let Ok(entry) = ecs.entry_ref(item) {
             if let Ok(consumable) = entry.get_component::<Consumable>() {
                 if consumable.max_charges == 0 {
-                    commands.remove(item);
+                    ctx.commands.remove(item);
                 }
             }
         }
@@ -54,10 +42,7 @@ pub fn spell_trigger(
     spell: Entity,
     targets: &Targets,
     ecs: &mut SubWorld,
-    particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    map: &Map,
-    commands: &mut CommandBuffer,
+    ctx: &mut EffectContext,
 ) {
     let template = ecs
         .entry_ref(spell)
@@ -98,11 +83,11 @@ pub fn spell_trigger(
                 if targets_self {
                     targeting = if let Some(aoe) = aoe {
                         Targets::Tiles {
-                            tiles: aoe_tiles(map, pos, aoe.0),
+                            tiles: aoe_tiles(ctx.map, pos, aoe.0),
                         }
                     } else {
                         Targets::Tile {
-                            tile_idx: map.point2d_to_index(pos),
+                            tile_idx: ctx.map.point2d_to_index(pos),
                         }
                     };
                 }
@@ -111,26 +96,19 @@ pub fn spell_trigger(
     }
 
     if cast_ok {
-        event_trigger(
-            creator,
-            spell,
-            &targeting,
-            ecs,
-            particle_builder,
-            turn_state,
-            map,
-            commands,
-        );
+        event_trigger(creator, spell, &targeting, ecs, ctx);
     }
 
-    if self_destruct && creator.is_some() {
-        // remove all hit points
-        let mut entry = ecs.entry_mut(creator.unwrap()).unwrap();
-        if let Ok(stats) = entry.get_component_mut::<Pools>() {
-            stats.hit_points.current = 0;
+    if self_destruct {
+        if let Some(creator) = creator {
+            // remove all hit points
+            let mut entry = ecs.entry_mut(creator).unwrap();
+            if let Ok(stats) = entry.get_component_mut::<Pools>() {
+                stats.hit_points.current = 0;
+            }
+            // don't trigger on-death if it self-destructed
+            ctx.commands.remove_component::<OnDeath>(creator);
         }
-        // don't trigger on-death if it self-destructed
-        commands.remove_component::<OnDeath>(creator.unwrap());
     }
 }
 
@@ -139,53 +117,58 @@ fn event_trigger(
     item: Entity,
     targets: &Targets,
     ecs: &mut SubWorld,
-    _particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    map: &Map,
-    _commands: &mut CommandBuffer,
+    ctx: &mut EffectContext,
 ) -> bool {
     let entry = ecs.entry_ref(item).unwrap();
     let mut did_something = false;
 
     // Providing food
     if entry.get_component::<ProvidesFood>().is_ok() {
-        add_effect(creator, EffectType::WellFed, targets.clone());
+        ctx.effect_queue
+            .add_effect(creator, EffectType::WellFed, targets.clone());
         did_something = true;
         if let Ok(name) = entry.get_component::<Name>() {
             crate::gamelog::Logger::new()
                 .append("You eat the")
                 .append(&name.0)
-                .log();
+                .log(ctx.game_log);
         }
     }
 
     // Magic mapper
     if entry.get_component::<ProvidesDungeonMap>().is_ok() {
-        *turn_state = TurnState::RevealMap { row: 0 };
+        *ctx.turn_state = TurnState::RevealMap { row: 0 };
         did_something = true;
         crate::gamelog::Logger::new()
             .append("The map is revealed to you!")
-            .log();
+            .log(ctx.game_log);
     }
 
     // Town Portal
     if entry.get_component::<TownPortal>().is_ok() {
-        if map.depth == 0 {
+        if ctx.map.depth == 0 {
             crate::gamelog::Logger::new()
                 .append("You are already in town, so the scroll does nothing.")
-                .log();
+                .log(ctx.game_log);
         } else {
             crate::gamelog::Logger::new()
                 .append("You are teleported back to town!")
-                .log();
-            *turn_state = TurnState::TownPortal;
+                .log(ctx.game_log);
+            *ctx.turn_state = TurnState::TownPortal;
             did_something = true;
         }
     }
 
+    // Trapdoor
+    if entry.get_component::<Trapdoor>().is_ok() {
+        ctx.effect_queue
+            .add_effect(creator, EffectType::Fall, targets.clone());
+        did_something = true;
+    }
+
     // Healing
     if let Ok(healing) = entry.get_component::<ProvidesHealing>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::Healing {
                 amount: healing.amount,
@@ -197,7 +180,7 @@ fn event_trigger(
 
     // Damage
     if let Ok(damage) = entry.get_component::<Damage>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::Damage { amount: damage.0 },
             targets.clone(),
@@ -208,7 +191,7 @@ fn event_trigger(
     // Confusion
     if entry.get_component::<Confusion>().is_ok() {
         if let Ok(duration) = entry.get_component::<Duration>() {
-            add_effect(
+            ctx.effect_queue.add_effect(
                 creator,
                 EffectType::Confusion { turns: duration.0 },
                 targets.clone(),
@@ -219,11 +202,11 @@ fn event_trigger(
 
     // Teleport
     if let Ok(teleport) = entry.get_component::<TeleportTo>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::TeleportTo {
                 pos: teleport.position,
-                depth: teleport.depth,
+                level: teleport.level,
                 player_only: teleport.player_only,
             },
             targets.clone(),
@@ -233,20 +216,20 @@ fn event_trigger(
 
     // Remove Curse
     if entry.get_component::<ProvidesRemoveCurse>().is_ok() {
-        *turn_state = TurnState::ShowingRemoveCurse;
+        *ctx.turn_state = TurnState::ShowingRemoveCurse;
         did_something = true;
     }
 
     // Identify Scroll
     if entry.get_component::<ProvidesIdentify>().is_ok() {
-        *turn_state = TurnState::ShowingIdentify;
+        *ctx.turn_state = TurnState::ShowingIdentify;
         did_something = true;
     }
 
     // Attribute Modifiers
     if let Ok(attr) = entry.get_component::<AttributeBonus>() {
         if let Ok(name) = entry.get_component::<Name>() {
-            add_effect(
+            ctx.effect_queue.add_effect(
                 creator,
                 EffectType::AttributeEffect {
                     bonus: attr.clone(),
@@ -261,7 +244,7 @@ fn event_trigger(
 
     // Restore Mana
     if let Ok(mana) = entry.get_component::<ProvidesMana>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::Mana { amount: mana.0 },
             targets.clone(),
@@ -272,7 +255,7 @@ fn event_trigger(
     // Teach spell
     if let Ok(spell) = entry.get_component::<TeachSpell>() {
         let name = &spell.0;
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::LearnSpell {
                 name: name.clone(),
@@ -285,7 +268,7 @@ fn event_trigger(
 
     // Slow / Haste
     if let Ok(slow) = entry.get_component::<Slow>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::Slow {
                 initiative_penalty: slow.initiative_penalty,
@@ -297,7 +280,7 @@ fn event_trigger(
 
     // Ongoing Damage
     if let Ok(ongoing) = entry.get_component::<DamageOverTime>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::DamageOverTime {
                 damage: ongoing.damage,
@@ -307,9 +290,16 @@ fn event_trigger(
         did_something = true;
     }
 
+    // Digging
+    if let Ok(digs) = entry.get_component::<Digs>() {
+        ctx.effect_queue
+            .add_effect(creator, EffectType::Dig { tile: digs.0 }, targets.clone());
+        did_something = true;
+    }
+
     // Simple particle spawn
     if let Ok(part) = entry.get_component::<SpawnParticleBurst>() {
-        add_effect(
+        ctx.effect_queue.add_effect(
             creator,
             EffectType::Particle {
                 glyph: part.glyph,
@@ -322,23 +312,35 @@ fn event_trigger(
 
     // Line particle spawn
     if let Ok(part) = entry.get_component::<SpawnParticleLine>() {
-        if let Some(start_pos) = find_item_position(ecs, item, creator, map) {
+        if let Some(start_pos) = find_item_position(ecs, item, creator, ctx.map) {
             match targets {
                 Targets::Tile { tile_idx } => {
-                    spawn_line_particles(ecs, start_pos, *tile_idx, part, map)
+                    spawn_line_particles(start_pos, *tile_idx, part, ctx.map, ctx.effect_queue)
                 }
                 Targets::Tiles { tiles } => tiles.iter().for_each(|tile_idx| {
-                    spawn_line_particles(ecs, start_pos, *tile_idx, part, map)
+                    spawn_line_particles(start_pos, *tile_idx, part, ctx.map, ctx.effect_queue)
                 }),
                 Targets::Single { target } => {
-                    if let Some(end_pos) = entity_position(ecs, *target, map) {
-                        spawn_line_particles(ecs, start_pos, end_pos[0], part, map);
+                    if let Some(end_pos) = entity_position(ecs, *target, ctx.map) {
+                        spawn_line_particles(
+                            start_pos,
+                            end_pos[0],
+                            part,
+                            ctx.map,
+                            ctx.effect_queue,
+                        );
                     }
                 }
                 Targets::Area { targets } => {
                     targets.iter().for_each(|target| {
-                        if let Some(end_pos) = entity_position(ecs, *target, map) {
-                            spawn_line_particles(ecs, start_pos, end_pos[0], part, map);
+                        if let Some(end_pos) = entity_position(ecs, *target, ctx.map) {
+                            spawn_line_particles(
+                                start_pos,
+                                end_pos[0],
+                                part,
+                                ctx.map,
+                                ctx.effect_queue,
+                            );
                         }
                     });
                 }
@@ -354,24 +356,12 @@ pub fn trigger(
     trigger: Entity,
     targets: &Targets,
     ecs: &mut SubWorld,
-    particle_builder: &mut ParticleBuilder,
-    turn_state: &mut TurnState,
-    map: &Map,
-    commands: &mut CommandBuffer,
+    ctx: &mut EffectContext,
 ) {
-    commands.remove_component::<Hidden>(trigger);
+    ctx.commands.remove_component::<Hidden>(trigger);
 
     // Use via the generic item system
-    let did_something = event_trigger(
-        creator,
-        trigger,
-        targets,
-        ecs,
-        particle_builder,
-        turn_state,
-        map,
-        commands,
-    );
+    let did_something = event_trigger(creator, trigger, targets, ecs, ctx);
 
     if did_something
         && ecs
@@ -380,7 +370,7 @@ pub fn trigger(
             .get_component::<SingleActivation>()
             .is_ok()
     {
-        commands.remove(trigger);
+        ctx.commands.remove(trigger);
     }
 }
 
@@ -417,18 +407,18 @@ pub fn learn_spell(
 }
 
 fn spawn_line_particles(
-    _ecs: &SubWorld,
     start: usize,
     end: usize,
     part: &SpawnParticleLine,
     map: &Map,
+    effect_queue: &mut EffectQueue,
 ) {
     let start_pt = map.index_to_point2d(start);
     let end_pt = map.index_to_point2d(end);
     let line = line2d_bresenham(start_pt, end_pt);
     for pt in line.iter() {
         let idx = map.point2d_to_index(*pt);
-        add_effect(
+        effect_queue.add_effect(
             None,
             EffectType::Particle {
                 glyph: part.glyph,
@@ -0,0 +1,70 @@
+use crate::prelude::*;
+
+// Each consumer of randomness gets its own stream derived from the master
+// seed, so that (for example) rolling extra combat dice never changes the
+// layout of a level that hasn't been generated yet.
+const STREAM_GAME: u64 = 0;
+const STREAM_IDENTIFICATION: u64 = 1;
+const STREAM_LEVEL_BASE: u64 = 0x1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSeed(pub u64);
+
+impl GameSeed {
+    pub fn random() -> Self {
+        Self(RandomNumberGenerator::new().next_u64())
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        match text.trim().parse::<u64>() {
+            Ok(seed) => Self(seed),
+            // So that a word seed maps to the same game on every build.
+            Err(_) => Self(fnv1a(text.trim())),
+        }
+    }
+
+    pub fn game_rng(&self) -> RandomNumberGenerator {
+        self.derive(STREAM_GAME)
+    }
+
+    pub fn identification_rng(&self) -> RandomNumberGenerator {
+        self.derive(STREAM_IDENTIFICATION)
+    }
+
+    pub fn level_rng(&self, depth: i32) -> RandomNumberGenerator {
+        self.derive(STREAM_LEVEL_BASE.wrapping_add(depth as i64 as u64))
+    }
+
+    // Side branches are keyed by name, so adding one to the raws doesn't
+    // change the levels of any other.
+    pub fn branch_level_rng(&self, branch: &str, depth: i32) -> RandomNumberGenerator {
+        if branch == MAIN_BRANCH {
+            self.level_rng(depth)
+        } else {
+            self.derive(fnv1a(branch).wrapping_add(depth as i64 as u64))
+        }
+    }
+
+    fn derive(&self, stream: u64) -> RandomNumberGenerator {
+        // SplitMix64 finalizer; spreads nearby seeds and stream ids apart.
+        let mut z = self
+            .0
+            .wrapping_add(stream.wrapping_mul(0x9e3779b97f4a7c15))
+            .wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        RandomNumberGenerator::seeded(z ^ (z >> 31))
+    }
+}
+
+impl std::fmt::Display for GameSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -1,4 +1,4 @@
-use super::{append_entry, LogFragment};
+use super::{GameLog, LogFragment};
 use crate::prelude::*;
 
 pub struct Logger {
@@ -6,6 +6,12 @@ pub struct Logger {
     fragments: Vec<LogFragment>,
 }
 
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Logger {
     pub fn new() -> Self {
         Logger {
@@ -54,7 +60,7 @@ impl Logger {
         self
     }
 
-    pub fn log(self) {
-        append_entry(self.fragments)
+    pub fn log(self, game_log: &mut GameLog) {
+        game_log.append_entry(self.fragments)
     }
 }
@@ -1,42 +1,29 @@
 use crate::prelude::*;
-use std::sync::Mutex;
 
-lazy_static! {
-    static ref LOG: Mutex<Vec<Vec<LogFragment>>> = Mutex::new(Vec::new());
-}
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GameLog(Vec<Vec<LogFragment>>);
 
-#[allow(dead_code)]
-pub fn append_fragment(fragment: LogFragment) {
-    LOG.lock().unwrap().push(vec![fragment]);
-}
+impl GameLog {
+    #[allow(dead_code)]
+    pub fn append_fragment(&mut self, fragment: LogFragment) {
+        self.0.push(vec![fragment]);
+    }
 
-pub fn append_entry(fragments: Vec<LogFragment>) {
-    LOG.lock().unwrap().push(fragments);
-}
-
-pub fn clear_log() {
-    LOG.lock().unwrap().clear();
-}
+    pub fn append_entry(&mut self, fragments: Vec<LogFragment>) {
+        self.0.push(fragments);
+    }
 
-pub fn log_display() -> TextBuilder {
-    let mut buf = TextBuilder::empty();
+    pub fn log_display(&self) -> TextBuilder {
+        let mut buf = TextBuilder::empty();
 
-    LOG.lock().unwrap().iter().rev().take(12).for_each(|log| {
-        log.iter().for_each(|frag| {
-            buf.fg(frag.color);
-            buf.line_wrap(&frag.text);
+        self.0.iter().rev().take(12).for_each(|log| {
+            log.iter().for_each(|frag| {
+                buf.fg(frag.color);
+                buf.line_wrap(&frag.text);
+            });
+            buf.ln();
         });
-        buf.ln();
-    });
-
-    buf
-}
-
-pub fn clone_log() -> Vec<Vec<LogFragment>> {
-    LOG.lock().unwrap().clone()
-}
 
-pub fn restore_log(log: &mut Vec<Vec<LogFragment>>) {
-    LOG.lock().unwrap().clear();
-    LOG.lock().unwrap().append(log);
+        buf
+    }
 }
@@ -5,8 +5,7 @@ mod events;
 mod logstore;
 pub use builder::*;
 pub use events::*;
-use logstore::*;
-pub use logstore::{clear_log, clone_log, log_display, restore_log};
+pub use logstore::*;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LogFragment {
@@ -0,0 +1,161 @@
+use crate::{prelude::*, replay::Replay, KeyState, State};
+
+// Headless games pretend every frame takes this long, so timers (particles,
+// the map-building visualizer) advance the same way on every machine.
+const FRAME_TIME_MS: f32 = 33.0;
+
+// Guards against a state machine that never settles back to waiting for input.
+const MAX_FRAMES_PER_INPUT: usize = 10_000;
+
+pub struct HeadlessGame {
+    state: State,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadlessReport {
+    pub seed: GameSeed,
+    pub turns: i32,
+    pub depth: i32,
+    pub level: i32,
+    pub hit_points: i32,
+    pub died: bool,
+}
+
+impl HeadlessGame {
+    pub fn new(seed: GameSeed) -> Self {
+        Self::starting(
+            seed,
+            TurnState::NewGame {
+                slot: SaveSlot(0),
+                mode: GameMode::Permadeath,
+            },
+        )
+    }
+
+    // Starts from the given new game state, e.g. a replay's.
+    pub fn starting(seed: GameSeed, new_game: TurnState) -> Self {
+        let mut state = State::new(Some(seed), RenderMode::Headless);
+        state.resources.insert(new_game);
+        let mut game = Self { state };
+        game.run_until_input();
+        game
+    }
+
+    pub fn turn_state(&self) -> TurnState {
+        *self.state.resources.get::<TurnState>().unwrap()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.state.ecs
+    }
+
+    pub fn resources(&self) -> &Resources {
+        &self.state.resources
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.state.ecs
+    }
+
+    pub fn resources_mut(&mut self) -> &mut Resources {
+        &mut self.state.resources
+    }
+
+    // Runs exactly one frame with the given input.
+    pub fn step(&mut self, input: KeyState) -> TurnState {
+        self.state.run_frame(input, FRAME_TIME_MS);
+        // Nothing will ever render the queued draw batches.
+        clear_command_buffer().expect("Unable to clear draw buffer");
+        self.turn_state()
+    }
+
+    // Presses a key, then keeps stepping until the game wants input again.
+    pub fn send(&mut self, input: KeyState) -> TurnState {
+        self.step(input);
+        self.run_until_input()
+    }
+
+    pub fn run_until_input(&mut self) -> TurnState {
+        for _ in 0..MAX_FRAMES_PER_INPUT {
+            if self.state.accepts_input() {
+                return self.turn_state();
+            }
+            self.step(KeyState::none());
+        }
+        panic!(
+            "Headless game stuck in {:?} after {} frames",
+            self.turn_state(),
+            MAX_FRAMES_PER_INPUT
+        );
+    }
+
+    pub fn report(&self) -> HeadlessReport {
+        let (level, hit_points) = <&Pools>::query()
+            .filter(component::<Player>())
+            .iter(self.world())
+            .next()
+            .map(|p| (p.level, p.hit_points.current))
+            .unwrap_or((0, 0));
+        HeadlessReport {
+            seed: *self.resources().get::<GameSeed>().unwrap(),
+            turns: self
+                .resources()
+                .get::<GameEvents>()
+                .unwrap()
+                .get_event_count("Turn"),
+            depth: self.resources().get::<Map>().unwrap().depth,
+            level,
+            hit_points,
+            died: self.turn_state() == TurnState::GameOver,
+        }
+    }
+}
+
+// A trivial bot: wanders at random (waiting now and then) for the given
+// number of inputs, or until it dies. Useful as a smoke test on CI.
+pub fn random_walk(seed: GameSeed, turns: i32) -> HeadlessReport {
+    const KEYS: [VirtualKeyCode; 9] = [
+        VirtualKeyCode::Left,
+        VirtualKeyCode::Right,
+        VirtualKeyCode::Up,
+        VirtualKeyCode::Down,
+        VirtualKeyCode::Q,
+        VirtualKeyCode::E,
+        VirtualKeyCode::Z,
+        VirtualKeyCode::C,
+        VirtualKeyCode::Period,
+    ];
+
+    let mut game = HeadlessGame::new(seed);
+    let mut rng = RandomNumberGenerator::seeded(seed.0);
+    for _ in 0..turns {
+        match game.turn_state() {
+            TurnState::GameOver => break,
+            TurnState::AwaitingInput => {
+                let key = *rng.random_slice_entry(&KEYS).unwrap();
+                game.send(KeyState::with_key(key));
+            }
+            // Walked into a shopkeeper or similar; back out of the menu.
+            _ => {
+                game.send(KeyState::with_key(VirtualKeyCode::Escape));
+            }
+        }
+    }
+    game.report()
+}
+
+// Re-feeds a recorded game, stopping where the recorded player died for good
+// or left for the main menu. Explore mode carries on from the last save.
+pub fn play_replay(mut replay: Replay) -> HeadlessReport {
+    let mut game = HeadlessGame::starting(replay.seed, replay.new_game());
+    while let Some(input) = replay.next_input() {
+        match game.turn_state() {
+            TurnState::GameOver if replay.mode == GameMode::Permadeath => break,
+            TurnState::MainMenu { selection: _ } => break,
+            _ => {
+                game.send(input);
+            }
+        }
+    }
+    game.report()
+}
@@ -0,0 +1,984 @@
+pub mod camera;
+pub mod components;
+pub mod effects;
+pub mod game_seed;
+pub mod gamelog;
+pub mod gamesystem;
+pub mod headless;
+pub mod map;
+pub mod map_analysis;
+pub mod map_builder;
+pub mod map_dump;
+pub mod mapgen_visualizer;
+pub mod menu;
+pub mod random_table;
+pub mod raws;
+pub mod replay;
+pub mod rex_assets;
+pub mod savegame;
+pub mod spatial;
+pub mod spawner;
+pub mod systems;
+pub mod tiled;
+pub mod turn_state;
+
+#[allow(dead_code)]
+pub mod prelude {
+    pub use bracket_lib::prelude::*;
+    pub use legion::serialize::*;
+    pub use legion::systems::CommandBuffer;
+    pub use legion::world::SubWorld;
+    pub use legion::*;
+    pub use serde::*;
+
+    pub const SCREEN_WIDTH: i32 = 80;
+    pub const SCREEN_HEIGHT: i32 = 60;
+    pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
+    pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
+
+    pub const SHOW_MAPGEN_VISUALIZER: bool = false;
+    pub const SHOW_BOUNDARIES: bool = false;
+
+    pub const FINAL_LEVEL: u32 = 2;
+
+    pub use crate::camera::*;
+    pub use crate::components::*;
+    pub use crate::effects::*;
+    pub use crate::game_seed::*;
+    pub use crate::gamelog::*;
+    pub use crate::gamesystem::*;
+    pub use crate::map::*;
+    pub use crate::map_builder::*;
+    pub use crate::mapgen_visualizer::*;
+    pub use crate::menu::*;
+    pub use crate::random_table::*;
+    pub use crate::raws::*;
+    pub use crate::rex_assets::*;
+    pub use crate::savegame::*;
+    pub use crate::spatial::*;
+    pub use crate::spawner::*;
+    pub use crate::systems::*;
+    pub use crate::turn_state::*;
+}
+
+use legion::serialize::UnknownType;
+use prelude::*;
+use replay::{Replay, ReplayRecorder, REPLAY_PATH, REPLAY_STEP_MS};
+
+#[derive(Clone)]
+pub struct KeyState {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub mouse_pos: Point,
+    pub mouse_clicked: bool,
+    pub key: Option<VirtualKeyCode>,
+}
+
+impl KeyState {
+    fn new(ctx: &BTerm) -> Self {
+        Self {
+            shift: ctx.shift,
+            control: ctx.control,
+            alt: ctx.alt,
+            mouse_pos: Point::from_tuple(ctx.mouse_pos()),
+            mouse_clicked: ctx.left_click,
+            key: ctx.key,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            shift: false,
+            control: false,
+            alt: false,
+            mouse_pos: Point::zero(),
+            mouse_clicked: false,
+            key: None,
+        }
+    }
+
+    pub fn with_key(key: VirtualKeyCode) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::none()
+        }
+    }
+}
+
+pub struct State {
+    ecs: World,
+    resources: Resources,
+    input_systems: Schedule,
+    tick_systems: Schedule,
+    ranged_systems: Schedule,
+    menu_systems: Schedule,
+    popup_menu_systems: Schedule,
+
+    mapgen: MapgenVisualizer,
+
+    requested_seed: Option<GameSeed>,
+    render_mode: RenderMode,
+
+    recorder: Option<ReplayRecorder>,
+    replay: Option<Replay>,
+    replay_timer: f32,
+    // Replays and headless games keep their save here rather than in the
+    // slot, so Explore mode can still return to it.
+    held_save: Option<String>,
+}
+
+impl State {
+    pub fn new(requested_seed: Option<GameSeed>, render_mode: RenderMode) -> Self {
+        let ecs = World::default();
+        let mut resources = Resources::default();
+
+        resources.insert(TurnState::MainMenu {
+            selection: MainMenuSelection::NewGame,
+        });
+        resources.insert(RexAssets::new());
+        resources.insert(load_raws());
+        resources.insert(MasterDungeonMap::default());
+        resources.insert(GameLog::default());
+        resources.insert(GameEvents::default());
+        resources.insert(EffectQueue::default());
+        resources.insert(SpatialMap::default());
+
+        Self {
+            ecs,
+            resources,
+            input_systems: build_input_scheduler(render_mode),
+            tick_systems: build_ticking_scheduler(render_mode),
+            ranged_systems: build_ranged_scheduler(render_mode),
+            menu_systems: build_menu_scheduler(),
+            popup_menu_systems: build_popup_scheduler(render_mode),
+            mapgen: MapgenVisualizer::new(SHOW_MAPGEN_VISUALIZER),
+            requested_seed,
+            render_mode,
+            recorder: None,
+            replay: None,
+            replay_timer: 0.0,
+            held_save: None,
+        }
+    }
+
+    pub fn with_replay(replay: Replay) -> Self {
+        let mut state = Self::new(Some(replay.seed), RenderMode::Window);
+        state.resources.insert(replay.new_game());
+        state.replay = Some(replay);
+        state
+    }
+
+    // Headless runs and replays never touch the player's save or replay files.
+    fn is_live_game(&self) -> bool {
+        self.render_mode == RenderMode::Window && self.replay.is_none()
+    }
+
+    fn accepts_input(&self) -> bool {
+        match *self.resources.get::<TurnState>().unwrap() {
+            // The visualizer's controls are recorded too, and a replay feeds
+            // them back to it.
+            TurnState::MapBuilding { .. } => true,
+            turn_state => turn_state.awaits_input(),
+        }
+    }
+
+    fn make_new_game(&mut self, slot: SaveSlot, mode: GameMode) {
+        // The raws outlive any one game.
+        let raws = self
+            .resources
+            .remove::<RawMaster>()
+            .unwrap_or_else(load_raws);
+        self.ecs = World::default();
+        self.resources = Resources::default();
+        self.resources.insert(RexAssets::new());
+        self.resources.insert(slot);
+        self.resources.insert(mode);
+        self.resources.insert(EffectQueue::default());
+        self.resources.insert(SpatialMap::default());
+
+        let seed = self.requested_seed.unwrap_or_else(GameSeed::random);
+        self.resources.insert(seed);
+        self.resources
+            .insert(MasterDungeonMap::new(&raws, &mut seed.identification_rng()));
+        self.resources.insert(raws);
+
+        let mut rng = seed.level_rng(0);
+        self.conjure_map(&mut rng, LevelId::town(), LevelId::town());
+
+        self.resources.insert(seed.game_rng());
+        self.resources.insert(TurnState::AwaitingInput);
+
+        let mut game_log = GameLog::default();
+        gamelog::Logger::new()
+            .append("Welcome to")
+            .color(CYAN)
+            .append("Rusty Roguelike")
+            .log(&mut game_log);
+        gamelog::Logger::new()
+            .color(GRAY)
+            .append(format!("Game seed: {}", seed))
+            .log(&mut game_log);
+        self.resources.insert(game_log);
+        self.resources.insert(GameEvents::default());
+
+        self.show_map_build();
+
+        let saved = match mode {
+            // Permadeath: DELETE THE SAVED GAME IN THIS SLOT!
+            GameMode::Permadeath => self.delete_save(slot),
+            // Make sure there is always a save to return to.
+            GameMode::Explore => self.write_save(),
+        };
+        if let Err(e) = saved {
+            self.resources.insert(e);
+            self.resources
+                .insert(TurnState::ShowingSaveError { in_game: true });
+        }
+
+        if self.is_live_game() {
+            self.recorder = match ReplayRecorder::create(REPLAY_PATH, seed, slot, mode) {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    log(format!("WARNING: unable to record replay: {}", e));
+                    None
+                }
+            };
+        }
+    }
+
+    fn conjure_map(&mut self, rng: &mut RandomNumberGenerator, level: LevelId, from: LevelId) {
+        let map_building_info = map::level_transition(
+            &mut self.ecs,
+            &mut self.resources,
+            rng,
+            level,
+            from,
+            self.mapgen.enabled,
+        );
+        if let Some(history) = map_building_info {
+            self.mapgen.start(history);
+        } else {
+            self.mapgen.start(Vec::new());
+        }
+        // A new level can still have had things fall into it.
+        let mut cb = CommandBuffer::new(&self.ecs);
+        thaw_level_entities(&self.ecs, level, &mut cb);
+        cb.flush(&mut self.ecs, &mut self.resources);
+        map::land_fallen_entities(&mut self.ecs, &mut self.resources, rng);
+    }
+
+    // Shows how a newly built level came to be, if its history was recorded.
+    fn show_map_build(&mut self) {
+        if self.mapgen.has_history() {
+            let map = self.resources.get::<Map>().unwrap().clone();
+            self.mapgen.set_real_map(map);
+            self.resources.insert(TurnState::MapBuilding { step: 0 });
+        }
+    }
+
+    fn switch_level(&mut self, level: LevelId) {
+        let current_map = self.resources.get::<Map>().unwrap().clone();
+        let from = current_map.level_id();
+
+        // Save the full current state of the map in the master
+        let mut dungeon_master = self.resources.get_mut::<MasterDungeonMap>().unwrap();
+        dungeon_master.store_map(&current_map);
+        std::mem::drop(dungeon_master);
+
+        let mut cb = CommandBuffer::new(&self.ecs);
+        freeze_level_entities(&self.ecs, from, &mut cb);
+        cb.flush(&mut self.ecs, &mut self.resources);
+
+        let seed = *self.resources.get::<GameSeed>().unwrap();
+        let branch = self
+            .resources
+            .get::<MasterDungeonMap>()
+            .unwrap()
+            .branch_name(level.branch)
+            .to_string();
+        let mut rng = seed.branch_level_rng(&branch, level.depth);
+        self.conjure_map(&mut rng, level, from);
+
+        self.resources.insert(TurnState::AwaitingInput);
+
+        <&mut FieldOfView>::query()
+            .filter(!component::<OtherLevelPosition>())
+            .iter_mut(&mut self.ecs)
+            .for_each(|fov| fov.is_dirty = true);
+
+        self.show_map_build();
+
+        gamelog::Logger::new()
+            .append("You descend to the next level.")
+            .log(&mut self.resources.get_mut::<GameLog>().unwrap());
+    }
+
+    fn game_over(&mut self, key: Option<VirtualKeyCode>) -> bool {
+        let mut batch = DrawBatch::new();
+        batch.target(2);
+
+        let white = ColorPair::new(WHITE, BLACK);
+        let yellow = ColorPair::new(YELLOW, BLACK);
+        let red = ColorPair::new(RED, BLACK);
+        let magenta = ColorPair::new(MAGENTA, BLACK);
+
+        batch.print_color_centered(15, "Your journey has ended!", yellow);
+
+        batch.print_color_centered(17, "One day, we'll tell you all about how you did.", white);
+        batch.print_color_centered(18, "That day, sadly, is not in this chapter...", white);
+
+        let events = self.resources.get::<GameEvents>().unwrap().clone();
+        batch.print_color_centered(
+            19,
+            format!("You lived for {} turns.", events.get_event_count("Turn")),
+            white,
+        );
+        batch.print_color_centered(
+            20,
+            format!(
+                "You suffered {} points of damage.",
+                events.get_event_count("Damage Taken")
+            ),
+            red,
+        );
+        batch.print_color_centered(
+            21,
+            format!(
+                "You inflicted {} points of damage.",
+                events.get_event_count("Damage Inflicted")
+            ),
+            red,
+        );
+
+        if let Some(seed) = self.resources.get::<GameSeed>() {
+            batch.print_color_centered(22, format!("Game seed: {}", *seed), white);
+        }
+
+        // Explore mode: death returns you to the last save.
+        let last_save = match (
+            self.resources.get::<GameMode>().as_deref(),
+            self.resources.get::<SaveSlot>().as_deref(),
+        ) {
+            (Some(GameMode::Explore), Some(slot)) if self.has_save(*slot) => Some(*slot),
+            _ => None,
+        };
+
+        if last_save.is_some() {
+            batch.print_color_centered(23, "Press any key to return to your last save.", magenta);
+        } else {
+            batch.print_color_centered(23, "Press any key to return to the menu.", magenta);
+        }
+
+        batch.submit(6000).expect("Batch error");
+
+        if key.is_some() {
+            if let Some(slot) = last_save {
+                self.resources.insert(TurnState::LoadGame { slot });
+            } else {
+                self.resources.insert(TurnState::MainMenu {
+                    selection: MainMenuSelection::NewGame,
+                });
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn configure_registry(&self, registry: &mut Registry<String>) {
+        registry.register::<Point>("position".to_string());
+        registry.register::<Render>("render".to_string());
+        registry.register::<Player>("player".to_string());
+        registry.register::<Name>("name".to_string());
+        registry.register::<Item>("item".to_string());
+        registry.register::<AmuletOfYala>("amulet_of_yala".to_string());
+        registry.register::<FieldOfView>("fov".to_string());
+        registry.register::<ProvidesHealing>("provides_healing".to_string());
+        registry.register::<ProvidesDungeonMap>("provides_map".to_string());
+        registry.register::<Carried>("carried_by".to_string());
+        registry.register::<Damage>("damage".to_string());
+        registry.register::<WeaponAttribute>("wattr".to_string());
+        registry.register::<Weapon>("melee_weapon".to_string());
+        registry.register::<Wearable>("wearable".to_string());
+        registry.register::<BlocksTile>("blocks_tile".to_string());
+        registry.register::<Consumable>("consumable".to_string());
+        registry.register::<Ranged>("ranged".to_string());
+        registry.register::<AreaOfEffect>("aoe".to_string());
+        registry.register::<Confusion>("confusion".to_string());
+        registry.register::<MapTheme>("theme".to_string());
+        registry.register::<SerializeMe>("serialize".to_string());
+        registry.register::<EquipmentSlot>("slot".to_string());
+        registry.register::<Equippable>("equippable".to_string());
+        registry.register::<Equipped>("equipped".to_string());
+        registry.register::<ParticleLifetime>("particle_lifetime".to_string());
+        registry.register::<ParticleAnimation>("particle_animation".to_string());
+        registry.register::<HungerState>("hunger_state".to_string());
+        registry.register::<HungerClock>("hunger_clock".to_string());
+        registry.register::<ProvidesFood>("provides_food".to_string());
+        registry.register::<Hidden>("hidden".to_string());
+        registry.register::<EntryTrigger>("entry_trigger".to_string());
+        registry.register::<SingleActivation>("one_shot".to_string());
+        registry.register::<BlocksVisibility>("blocks_visibility".to_string());
+        registry.register::<Door>("door".to_string());
+        registry.register::<SecretPassage>("secret_passage".to_string());
+        registry.register::<Digs>("digs".to_string());
+        registry.register::<AlwaysVisible>("always_visible".to_string());
+        registry.register::<Quips>("quips".to_string());
+        registry.register::<Attribute>("attr".to_string());
+        registry.register::<Attributes>("attrs".to_string());
+        registry.register::<Skill>("skill".to_string());
+        registry.register::<Skills>("skills".to_string());
+        registry.register::<Pool>("pool".to_string());
+        registry.register::<Pools>("pools".to_string());
+        registry.register::<NaturalAttack>("nattack".to_string());
+        registry.register::<NaturalAttackDefense>("natkdef".to_string());
+        registry.register::<LootTable>("loot_tbl".to_string());
+        registry.register::<OtherLevelPosition>("olpos".to_string());
+        registry.register::<LightSource>("light_source".to_string());
+        registry.register::<Initiative>("initiative".to_string());
+        registry.register::<MyTurn>("my_turn".to_string());
+        registry.register::<Faction>("faction".to_string());
+        registry.register::<Movement>("movement".to_string());
+        registry.register::<MoveMode>("move_mode".to_string());
+        registry.register::<Chasing>("chasing".to_string());
+        registry.register::<Vendor>("vendor".to_string());
+        registry.register::<TownPortal>("town_portal".to_string());
+        registry.register::<Trapdoor>("trapdoor".to_string());
+        registry.register::<Fallen>("fallen".to_string());
+        registry.register::<MagicItemClass>("magic_item_class".to_string());
+        registry.register::<MagicItem>("magic_item".to_string());
+        registry.register::<ObfuscatedName>("obf_name".to_string());
+        registry.register::<IdentifiedItem>("identified_item".to_string());
+        registry.register::<CursedItem>("cursed".to_string());
+        registry.register::<ProvidesRemoveCurse>("removes_curse".to_string());
+        registry.register::<ProvidesIdentify>("identifies".to_string());
+        registry.register::<AttributeBonus>("attr_bonus".to_string());
+        registry.register::<StatusEffect>("status_effect".to_string());
+        registry.register::<Duration>("duration".to_string());
+        registry.register::<KnownSpell>("spell".to_string());
+        registry.register::<KnownSpells>("known_spells".to_string());
+        registry.register::<SpellTemplate>("spell_template".to_string());
+        registry.register::<ProvidesMana>("provides_mana".to_string());
+        registry.register::<TeachSpell>("teach_spell".to_string());
+        registry.register::<Slow>("slow".to_string());
+        registry.register::<DamageOverTime>("dot".to_string());
+        registry.register::<SpecialAbility>("sp_ability".to_string());
+        registry.register::<SpecialAbilities>("sp_abilities".to_string());
+        registry.register::<TileSize>("tile_size".to_string());
+        registry.register::<OnDeath>("on_death".to_string());
+        registry.register::<AlwaysTargetsSelf>("targets_self".to_string());
+        registry.register::<Target>("target".to_string());
+        registry.register::<WantsToShoot>("wants_shoot".to_string());
+        registry.on_unknown(UnknownType::Ignore);
+    }
+
+    fn save_game(&mut self) {
+        if let Err(e) = self.write_save() {
+            self.resources.insert(e);
+            self.resources
+                .insert(TurnState::ShowingSaveError { in_game: true });
+            return;
+        }
+
+        // Show the main menu.
+        self.resources.insert(TurnState::MainMenu {
+            selection: MainMenuSelection::LoadGame,
+        });
+    }
+
+    fn write_save(&mut self) -> Result<(), SaveError> {
+        let mut registry = Registry::<String>::default();
+        self.configure_registry(&mut registry);
+
+        let entity_serializer = Canon::default();
+        let world = serde_json::to_value(self.ecs.as_serializable(
+            component::<SerializeMe>(),
+            &registry,
+            &entity_serializer,
+        ));
+
+        let world = world.map_err(|e| SaveError(format!("Unable to encode the save: {}", e)))?;
+        let level = <&Pools>::query()
+            .filter(component::<Player>())
+            .iter(&self.ecs)
+            .next()
+            .map_or(1, |p| p.level);
+        let resources = SavedResources {
+            map: self.resources.get::<Map>().unwrap().clone(),
+            dungeon: self.resources.get::<MasterDungeonMap>().unwrap().clone(),
+            log: self.resources.get::<GameLog>().unwrap().clone(),
+            events: self.resources.get::<GameEvents>().unwrap().clone(),
+            seed: *self.resources.get::<GameSeed>().unwrap(),
+            rng: self
+                .resources
+                .get::<RandomNumberGenerator>()
+                .unwrap()
+                .clone(),
+            mode: *self.resources.get::<GameMode>().unwrap(),
+        };
+        let summary = SaveSummary::new(
+            level,
+            resources.map.depth,
+            resources.events.get_event_count("Turn"),
+            resources.mode,
+        );
+        let text = savegame::encode_save(summary, &resources, world)?;
+        if self.is_live_game() {
+            let slot = *self.resources.get::<SaveSlot>().unwrap();
+            savegame::write_save(&slot.path(), &text)
+        } else {
+            self.held_save = Some(text);
+            Ok(())
+        }
+    }
+
+    fn has_save(&self, slot: SaveSlot) -> bool {
+        if self.is_live_game() {
+            slot.exists()
+        } else {
+            self.held_save.is_some()
+        }
+    }
+
+    fn delete_save(&mut self, slot: SaveSlot) -> Result<(), SaveError> {
+        if self.is_live_game() {
+            slot.delete()
+        } else {
+            self.held_save = None;
+            Ok(())
+        }
+    }
+
+    fn load_game(&mut self, slot: SaveSlot) {
+        if let Err(e) = self.read_save(slot) {
+            self.resources.insert(e);
+            self.resources
+                .insert(TurnState::ShowingSaveError { in_game: false });
+            return;
+        }
+
+        gamelog::Logger::new()
+            .append("Loaded game.")
+            .log(&mut self.resources.get_mut::<GameLog>().unwrap());
+
+        self.resources.insert(TurnState::Ticking);
+        self.resources.insert(RexAssets::new());
+
+        // make all FOVs dirty
+        <&mut FieldOfView>::query().for_each_mut(&mut self.ecs, |fov| {
+            fov.is_dirty = true;
+        });
+
+        // Permadeath: DELETE THE SAVED GAME!
+        if *self.resources.get::<GameMode>().unwrap() == GameMode::Permadeath {
+            if let Err(e) = self.delete_save(slot) {
+                self.resources.insert(e);
+                self.resources
+                    .insert(TurnState::ShowingSaveError { in_game: true });
+            }
+        }
+    }
+
+    // Leaves the current game untouched unless the whole save could be read.
+    fn read_save(&mut self, slot: SaveSlot) -> Result<(), SaveError> {
+        use serde::de::DeserializeSeed;
+
+        let mut registry = Registry::new();
+        self.configure_registry(&mut registry);
+        // Migrations have run, so anything still unknown can't be loaded.
+        registry.on_unknown(UnknownType::Error);
+        let entity_serializer = Canon::default();
+
+        let (saved, json) = if self.is_live_game() {
+            savegame::read_save(&slot.path())?
+        } else {
+            let text = self
+                .held_save
+                .as_deref()
+                .ok_or_else(|| SaveError("There is no save to return to.".to_string()))?;
+            savegame::decode_save("the held save", text)?
+        };
+        let ecs: World = registry
+            .as_deserialize(&entity_serializer)
+            .deserialize(json)
+            .map_err(|e| SaveError(format!("Unable to restore the saved world: {}", e)))?;
+
+        // build the camera, centered on the player
+        let player_pos = <&Point>::query()
+            .filter(component::<Player>())
+            .iter(&ecs)
+            .next()
+            .ok_or_else(|| SaveError("The save has no player.".to_string()))?;
+
+        let mut resources = Resources::default();
+        resources.insert(slot);
+        resources.insert(Camera::new(*player_pos, &saved.map));
+        let mut spatial = SpatialMap::default();
+        spatial.set_size(saved.map.tiles.len());
+        resources.insert(spatial);
+        resources.insert(EffectQueue::default());
+        let raws = self
+            .resources
+            .remove::<RawMaster>()
+            .unwrap_or_else(load_raws);
+        // Older saves predate any branches the raws now define.
+        let mut dungeon = saved.dungeon;
+        dungeon.add_branches(&raws);
+        resources.insert(saved.map);
+        resources.insert(dungeon);
+        resources.insert(saved.log);
+        resources.insert(saved.events);
+        resources.insert(saved.seed);
+        resources.insert(saved.rng);
+        resources.insert(saved.mode);
+        resources.insert(raws);
+
+        self.ecs = ecs;
+        self.resources = resources;
+        Ok(())
+    }
+
+    fn save_error(&mut self, in_game: bool, key: Option<VirtualKeyCode>) -> bool {
+        let mut batch = DrawBatch::new();
+        batch.target(2);
+
+        let white = ColorPair::new(WHITE, BLACK);
+        let yellow = ColorPair::new(YELLOW, BLACK);
+        let magenta = ColorPair::new(MAGENTA, BLACK);
+
+        if in_game {
+            batch.print_color_centered(15, "Your game could not be saved.", yellow);
+        } else {
+            batch.print_color_centered(15, "Your saved game could not be loaded.", yellow);
+        }
+
+        let message = self
+            .resources
+            .get::<SaveError>()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+        let mut y = 17;
+        for line in wrap_text(&message, SCREEN_WIDTH as usize - 10) {
+            batch.print_color_centered(y, line, white);
+            y += 1;
+        }
+
+        batch.print_color_centered(y + 1, "Press any key to continue.", magenta);
+
+        batch.submit(6000).expect("Batch error");
+
+        if key.is_some() {
+            if in_game {
+                self.resources.insert(TurnState::AwaitingInput);
+            } else {
+                self.resources.insert(TurnState::MainMenu {
+                    selection: MainMenuSelection::NewGame,
+                });
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn reveal_map(&mut self, row: i32) {
+        let height: usize;
+        {
+            let mut map = self.resources.get_mut::<Map>().unwrap();
+            for x in 0..map.width {
+                let idx = map.point2d_to_index(Point::new(x, row as usize));
+                map.revealed_tiles[idx] = true;
+            }
+            height = map.height;
+        }
+        if row as usize == height - 1 {
+            self.resources.insert(TurnState::Ticking);
+        } else {
+            self.resources.insert(TurnState::RevealMap { row: row + 1 })
+        }
+        map_reveal_scheduler(self.render_mode).execute(&mut self.ecs, &mut self.resources);
+    }
+
+    // Re-reads spawns.json and the mods directory mid-game, updating what has
+    // already been spawned to match.
+    fn reload_raws(&mut self) {
+        // Broken raws would leave the running game half updated, so they are
+        // refused and the ones already loaded stay in use.
+        let decoder = read_raws();
+        let problems = validate_raws(&decoder);
+        if !problems.is_empty() {
+            for problem in problems.iter() {
+                log(format!("WARNING: {}", problem));
+            }
+            gamelog::Logger::new()
+                .color(RED)
+                .append(format!(
+                    "Raws not reloaded: {} problem(s) found.",
+                    problems.len()
+                ))
+                .log(&mut self.resources.get_mut::<GameLog>().unwrap());
+            self.resources.insert(TurnState::AwaitingInput);
+            return;
+        }
+
+        let mut new_raws = RawMaster::empty();
+        new_raws.load(decoder);
+        let old_raws = self.resources.remove::<RawMaster>().unwrap();
+        let updated = refresh_spawned_entities(&mut self.ecs, &old_raws, &new_raws);
+
+        let spells: Vec<Entity> = <Entity>::query()
+            .filter(component::<SpellTemplate>())
+            .iter(&self.ecs)
+            .copied()
+            .collect();
+        spells.iter().for_each(|spell| {
+            self.ecs.remove(*spell);
+        });
+        let mut cb = CommandBuffer::new(&self.ecs);
+        spawn_all_spells(&new_raws, &mut cb);
+        cb.flush(&mut self.ecs, &mut self.resources);
+
+        // Newly added magic items need a masked name.
+        {
+            let mut rng = self.resources.get_mut::<RandomNumberGenerator>().unwrap();
+            let mut dm = self.resources.get_mut::<MasterDungeonMap>().unwrap();
+            dm.assign_names(&new_raws, &mut rng);
+            dm.add_branches(&new_raws);
+        }
+        self.resources.insert(new_raws);
+
+        gamelog::Logger::new()
+            .color(GRAY)
+            .append(format!("Reloaded raws: {} entities updated.", updated))
+            .log(&mut self.resources.get_mut::<GameLog>().unwrap());
+        self.resources.insert(TurnState::AwaitingInput);
+    }
+
+    fn visualize_map_build(
+        &mut self,
+        step: usize,
+        frame_time_ms: f32,
+        key: Option<VirtualKeyCode>,
+    ) {
+        let next = match self.mapgen.snapshot(step) {
+            Some(snapshot) => {
+                self.resources.insert(snapshot.map.clone());
+                map_reveal_scheduler(self.render_mode).execute(&mut self.ecs, &mut self.resources);
+                if let Some(camera) = self.resources.get::<Camera>() {
+                    self.mapgen.render(step, &camera);
+                }
+                self.mapgen.next_step(step, frame_time_ms, key)
+            }
+            None => None,
+        };
+
+        match next {
+            Some(step) => self.resources.insert(TurnState::MapBuilding { step }),
+            None => {
+                self.resources.insert(self.mapgen.finish());
+                self.resources.insert(TurnState::AwaitingInput);
+            }
+        }
+    }
+
+    fn toggle_mapgen_visualizer(&mut self) {
+        self.mapgen.enabled = !self.mapgen.enabled;
+        let status = if self.mapgen.enabled { "on" } else { "off" };
+        gamelog::Logger::new()
+            .color(GRAY)
+            .append(format!("Map generation visualizer {}.", status))
+            .log(&mut self.resources.get_mut::<GameLog>().unwrap());
+        self.resources.insert(TurnState::AwaitingInput);
+    }
+
+    // Runs one frame of the game with the given input, without touching the
+    // window. Returns true if the key press was consumed.
+    fn run_frame(&mut self, key_state: KeyState, frame_time_ms: f32) -> bool {
+        // Record before running the frame, so a crash report's replay includes
+        // the input that caused it.
+        if self.accepts_input() {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.record(&key_state);
+            }
+        }
+
+        let key = key_state.key;
+        let mut key_consumed = false;
+        self.resources.insert(key_state);
+        self.resources.insert(frame_time_ms);
+        self.resources.insert(ParticleBuilder::new());
+
+        let current_state = *self.resources.get::<TurnState>().unwrap();
+        match current_state {
+            TurnState::AwaitingInput => {
+                self.input_systems
+                    .execute(&mut self.ecs, &mut self.resources);
+                if *self.resources.get::<TurnState>().unwrap() != current_state {
+                    // if we changed state, clear keyboard input
+                    key_consumed = true;
+                    // we also survived another turn
+                    self.resources
+                        .get_mut::<GameEvents>()
+                        .unwrap()
+                        .record_event("Turn", 1);
+                }
+            }
+            TurnState::Ticking => {
+                while *self.resources.get::<TurnState>().unwrap() == TurnState::Ticking {
+                    self.tick_systems
+                        .execute(&mut self.ecs, &mut self.resources);
+                }
+                if *self.resources.get::<TurnState>().unwrap() == TurnState::AwaitingInput {
+                    update_targeting_scheduler().execute(&mut self.ecs, &mut self.resources);
+                }
+            }
+            TurnState::ShowingInventory
+            | TurnState::ShowingDropItems
+            | TurnState::ShowingRemoveCurse
+            | TurnState::ShowingIdentify => self
+                .popup_menu_systems
+                .execute(&mut self.ecs, &mut self.resources),
+            TurnState::RangedTargeting { range: _, item: _ } => self
+                .ranged_systems
+                .execute(&mut self.ecs, &mut self.resources),
+            TurnState::MainMenu { selection: _ }
+            | TurnState::ShowingGameModes
+            | TurnState::ShowingSaveSlots { purpose: _ } => {
+                // Reading every slot's summary each frame would be wasteful.
+                if !self.resources.contains::<SaveSlots>() {
+                    self.resources.insert(list_save_slots());
+                }
+                self.menu_systems
+                    .execute(&mut self.ecs, &mut self.resources)
+            }
+            TurnState::NewGame { slot, mode } => self.make_new_game(slot, mode),
+            TurnState::SaveGame => self.save_game(),
+            TurnState::LoadGame { slot } => self.load_game(slot),
+            TurnState::NextLevel => {
+                let level = self.resources.get::<Map>().unwrap().level_id();
+                let below = self
+                    .resources
+                    .get::<MasterDungeonMap>()
+                    .unwrap()
+                    .level_below(level, &self.resources.get::<RawMaster>().unwrap());
+                match below {
+                    Some(below) => self.switch_level(below),
+                    None => self.resources.insert(TurnState::AwaitingInput),
+                }
+            }
+            TurnState::ChangeLevel { level } => self.switch_level(level),
+            TurnState::GameOver => key_consumed = self.game_over(key),
+            TurnState::ShowingSaveError { in_game } => key_consumed = self.save_error(in_game, key),
+            TurnState::RevealMap { row } => self.reveal_map(row),
+            TurnState::MapBuilding { step } => self.visualize_map_build(step, frame_time_ms, key),
+            TurnState::ShowCheatMenu => build_cheat_menu_scheduler(self.render_mode)
+                .execute(&mut self.ecs, &mut self.resources),
+            TurnState::ReloadRaws => self.reload_raws(),
+            TurnState::ToggleMapgenVisualizer => self.toggle_mapgen_visualizer(),
+            TurnState::ShowingVendor { vendor: _, mode: _ } => self
+                .input_systems
+                .execute(&mut self.ecs, &mut self.resources),
+            TurnState::TownPortal => {
+                spawn_town_portal(&mut self.ecs, &mut self.resources);
+                self.switch_level(LevelId::town());
+            }
+            TurnState::LevelTeleport { destination, level } => {
+                self.switch_level(level);
+                <&mut Point>::query()
+                    .filter(component::<Player>())
+                    .iter_mut(&mut self.ecs)
+                    .for_each(|pt| *pt = destination);
+                self.resources
+                    .get_mut::<Camera>()
+                    .unwrap()
+                    .on_player_move(destination);
+            }
+        }
+
+        let new_state = *self.resources.get::<TurnState>().unwrap();
+        if !new_state.in_main_menu() {
+            self.resources.remove::<SaveSlots>();
+        }
+
+        // A recording covers a single game, from its start until the menu (or
+        // until an explore game reloads).
+        if new_state.in_main_menu() || matches!(new_state, TurnState::LoadGame { .. }) {
+            self.recorder = None;
+        }
+
+        key_consumed
+    }
+
+    // Feeds the next recorded input whenever the game is ready for one, paced
+    // so that a replay can be watched.
+    fn next_replay_input(&mut self, frame_time_ms: f32) -> KeyState {
+        if !self.accepts_input() {
+            return KeyState::none();
+        }
+        self.replay_timer += frame_time_ms;
+        if self.replay_timer < REPLAY_STEP_MS {
+            return KeyState::none();
+        }
+        self.replay_timer = 0.0;
+
+        let input = self.replay.as_mut().and_then(|r| r.next_input());
+        input.unwrap_or_else(|| {
+            self.replay = None;
+            gamelog::Logger::new()
+                .color(GRAY)
+                .append("Replay finished.")
+                .log(&mut self.resources.get_mut::<GameLog>().unwrap());
+            KeyState::none()
+        })
+    }
+}
+
+#[allow(dead_code)]
+impl GameState for State {
+    #[allow(dead_code)]
+    fn tick(&mut self, ctx: &mut BTerm) {
+        ctx.set_active_console(0);
+        ctx.cls();
+        ctx.set_active_console(1);
+        ctx.cls();
+        ctx.set_active_console(2);
+        ctx.cls();
+        ctx.set_active_console(3);
+        ctx.cls();
+
+        ctx.set_active_console(0);
+
+        if self.resources.get::<TurnState>().unwrap().in_main_menu() {
+            // Alas, there's no draw batch command to render a sprite.
+            let assets = self.resources.get::<RexAssets>().unwrap();
+            ctx.render_xp_sprite(&assets.menu, 0, 0);
+        }
+
+        let key_state = if self.replay.is_some() {
+            self.next_replay_input(ctx.frame_time_ms)
+        } else {
+            KeyState::new(ctx)
+        };
+        if self.run_frame(key_state, ctx.frame_time_ms) {
+            ctx.key = None;
+        }
+        // println!("Tick took {} seconds", tm.elapsed().as_secs_f32());
+
+        render_draw_buffer(ctx).expect("Render error");
+    }
+}
+
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + word.len() < width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
@@ -1,608 +1,213 @@
-mod camera;
-mod components;
-mod effects;
-mod gamelog;
-mod gamesystem;
-mod map;
-mod map_builder;
-mod menu;
-mod random_table;
-mod raws;
-mod rex_assets;
-mod spatial;
-mod spawner;
-mod systems;
-mod turn_state;
-
-#[allow(dead_code)]
-mod prelude {
-    pub use bracket_lib::prelude::*;
-    pub use legion::serialize::*;
-    pub use legion::systems::CommandBuffer;
-    pub use legion::world::SubWorld;
-    pub use legion::*;
-    pub use serde::*;
-
-    pub const SCREEN_WIDTH: i32 = 80;
-    pub const SCREEN_HEIGHT: i32 = 60;
-    pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
-    pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
-
-    pub const SHOW_MAPGEN_VISUALIZER: bool = false;
-    pub const SHOW_BOUNDARIES: bool = false;
-
-    pub const FINAL_LEVEL: u32 = 2;
-
-    pub use crate::camera::*;
-    pub use crate::components::*;
-    pub use crate::effects::*;
-    pub use crate::gamelog::*;
-    pub use crate::gamesystem::*;
-    pub use crate::map::*;
-    pub use crate::map_builder::*;
-    pub use crate::menu::*;
-    pub use crate::random_table::*;
-    pub use crate::raws::*;
-    pub use crate::rex_assets::*;
-    pub use crate::spawner::*;
-    pub use crate::systems::*;
-    pub use crate::turn_state::*;
-}
-
-use legion::serialize::UnknownType::Ignore;
-use prelude::*;
-use std::collections::HashMap;
-use std::fs;
-use std::fs::File;
+use rrlt::map_analysis::analyze_levels;
+use rrlt::map_dump::{dump_level, generate_level, DumpFormat};
+use rrlt::prelude::*;
+use rrlt::replay::Replay;
+use rrlt::{headless, State};
 use std::path::Path;
 
-#[macro_use]
-extern crate lazy_static;
-
-pub struct KeyState {
-    pub shift: bool,
-    pub control: bool,
-    pub alt: bool,
-    pub mouse_pos: Point,
-    pub mouse_clicked: bool,
-    pub key: Option<VirtualKeyCode>,
+// Accepts `--seed <number or text>` so a run can be reproduced.
+fn requested_seed() -> Option<GameSeed> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .map(|s| GameSeed::from_text(s))
 }
 
-impl KeyState {
-    fn new(ctx: &BTerm) -> Self {
-        Self {
-            shift: ctx.shift,
-            control: ctx.control,
-            alt: ctx.alt,
-            mouse_pos: Point::from_tuple(ctx.mouse_pos()),
-            mouse_clicked: ctx.left_click,
-            key: ctx.key,
+// Accepts `--replay <file>` to play back a recorded game.
+fn requested_replay() -> Option<Replay> {
+    let args: Vec<String> = std::env::args().collect();
+    let path = args
+        .iter()
+        .position(|a| a == "--replay")
+        .and_then(|i| args.get(i + 1))?;
+    match Replay::load(path) {
+        Ok(replay) => Some(replay),
+        Err(e) => {
+            eprintln!("Unable to load replay {}", e);
+            std::process::exit(1);
         }
     }
 }
 
-struct State {
-    ecs: World,
-    resources: Resources,
-    input_systems: Schedule,
-    tick_systems: Schedule,
-    ranged_systems: Schedule,
-    menu_systems: Schedule,
-    popup_menu_systems: Schedule,
-
-    map_history: Vec<Map>,
-    real_map: Map,
-    mapgen_timer: f32,
-}
-
-impl State {
-    fn new() -> Self {
-        let ecs = World::default();
-        let mut resources = Resources::default();
-
-        resources.insert(TurnState::MainMenu {
-            selection: MainMenuSelection::NewGame,
-        });
-        resources.insert(RexAssets::new());
-        resources.insert(MasterDungeonMap::new());
-
-        Self {
-            ecs,
-            resources,
-            input_systems: build_input_scheduler(),
-            tick_systems: build_ticking_scheduler(),
-            ranged_systems: build_ranged_scheduler(),
-            menu_systems: build_menu_scheduler(),
-            popup_menu_systems: build_popup_scheduler(),
-            map_history: Vec::default(),
-            real_map: Map::default(),
-            mapgen_timer: 0.0,
-        }
+// Checks the raws (with any mods) and exits non-zero if there are problems.
+fn validate_raws() -> ! {
+    let problems = rrlt::prelude::validate_raws(&read_raws());
+    for problem in problems.iter() {
+        println!("{}", problem);
     }
+    println!("{} problem(s) found", problems.len());
+    std::process::exit(if problems.is_empty() { 0 } else { 1 });
+}
 
-    fn make_new_game(&mut self) {
-        self.ecs = World::default();
-        self.resources = Resources::default();
-        self.resources.insert(RexAssets::new());
-        self.resources.insert(MasterDungeonMap::new());
-
-        let mut rng = RandomNumberGenerator::new();
-        self.conjure_map(&mut rng, 0, 0);
-
-        self.resources.insert(rng);
-        self.resources.insert(TurnState::AwaitingInput);
-
-        gamelog::clear_log();
-        gamelog::Logger::new()
-            .append("Welcome to")
-            .color(CYAN)
-            .append("Rusty Roguelike")
-            .log();
-
-        gamelog::clear_events();
-
-        if SHOW_MAPGEN_VISUALIZER {
-            self.real_map = self.resources.get::<Map>().unwrap().clone();
-            self.resources.insert(TurnState::MapBuilding { step: 0 });
+// Writes the level for `--depth` (and `--seed`, and `--branch` for a side
+// branch) to `--out` without playing, as ASCII, REXPaint (`--format xp`) or
+// a Tiled map (`--format tiled`); `--history` adds every build step.
+// `--width` and `--height` size levels that don't set their own size.
+fn generate_map(args: &[String]) -> ! {
+    let value = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let number = |flag: &str, default: usize| {
+        value(flag)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(default)
+    };
+
+    let seed = requested_seed().unwrap_or_else(GameSeed::random);
+    let depth = number("--depth", 1) as i32;
+    let with_history = args.iter().any(|a| a == "--history");
+    let format = match value("--format").map(|f| DumpFormat::from_name(f)) {
+        None => DumpFormat::Ascii,
+        Some(Some(format)) => format,
+        Some(None) => {
+            eprintln!("Unknown map format; use ascii, xp or tiled");
+            std::process::exit(2);
         }
-
-        // Permadeath: DELETE ANY SAVED GAMES!
-        if Path::new("./savegame.json").exists() {
-            fs::remove_file("./savegame.json").expect("Save deletion failed");
+    };
+    let out = value("--out").map_or("./mapgen", |o| o.as_str());
+
+    let raws = load_raws();
+    let branch = value("--branch").map_or(MAIN_BRANCH, |b| b.as_str());
+    if branch != MAIN_BRANCH {
+        match raws.branch(branch) {
+            None => {
+                eprintln!("Unknown branch: {}", branch);
+                std::process::exit(2);
+            }
+            Some(b) if depth < b.first_depth() || depth > b.last_depth() => {
+                eprintln!(
+                    "{} only has depths {} to {}",
+                    branch,
+                    b.first_depth(),
+                    b.last_depth()
+                );
+                std::process::exit(2);
+            }
+            Some(_) => {}
         }
     }
-
-    fn conjure_map(&mut self, rng: &mut RandomNumberGenerator, new_depth: i32, offset: i32) {
-        if SHOW_MAPGEN_VISUALIZER {
-            self.mapgen_timer = 0.0;
-            self.map_history.clear();
-        }
-
-        let map_building_info =
-            map::level_transition(&mut self.ecs, &mut self.resources, rng, new_depth, offset);
-        if let Some(history) = map_building_info {
-            if SHOW_MAPGEN_VISUALIZER {
-                self.map_history = history;
+    let level = generate_level(
+        seed,
+        branch,
+        depth,
+        number("--width", DEFAULT_MAP_WIDTH),
+        number("--height", DEFAULT_MAP_HEIGHT),
+        &raws,
+        with_history,
+    );
+    match dump_level(
+        &level,
+        branch,
+        &raws,
+        seed,
+        format,
+        Path::new(out),
+        with_history,
+    ) {
+        Ok(files) => {
+            for file in files.iter() {
+                println!("{}", file.display());
             }
-        } else {
-            let mut cb = CommandBuffer::new(&self.ecs);
-            thaw_level_entities(&self.ecs, new_depth, &mut cb);
-            cb.flush(&mut self.ecs, &mut self.resources);
+            std::process::exit(0);
         }
-    }
-
-    fn switch_level(&mut self, offset: i32) {
-        let current_map = self.resources.get::<Map>().unwrap().clone();
-        let map_level = current_map.depth;
-        let new_depth = map_level + offset;
-
-        // Save the full current state of the map in the master
-        let mut dungeon_master = self.resources.get_mut::<MasterDungeonMap>().unwrap();
-        dungeon_master.store_map(&current_map);
-        std::mem::drop(dungeon_master);
-
-        let mut cb = CommandBuffer::new(&mut self.ecs);
-        freeze_level_entities(&self.ecs, map_level, &mut cb);
-        cb.flush(&mut self.ecs, &mut self.resources);
-
-        let mut rng = RandomNumberGenerator::new();
-        self.conjure_map(&mut rng, new_depth, offset);
-
-        self.resources.insert(rng);
-        self.resources.insert(TurnState::AwaitingInput);
-
-        <&mut FieldOfView>::query()
-            .filter(!component::<OtherLevelPosition>())
-            .iter_mut(&mut self.ecs)
-            .for_each(|fov| fov.is_dirty = true);
-
-        if SHOW_MAPGEN_VISUALIZER {
-            self.real_map = self.resources.get::<Map>().unwrap().clone();
-            self.resources.insert(TurnState::MapBuilding { step: 0 });
+        Err(e) => {
+            eprintln!("Unable to write map: {}", e);
+            std::process::exit(1);
         }
-
-        gamelog::Logger::new()
-            .append("You descend to the next level.")
-            .log();
     }
+}
 
-    fn game_over(&mut self, ctx: &mut BTerm) {
-        let mut batch = DrawBatch::new();
-        batch.target(2);
-
-        let white = ColorPair::new(WHITE, BLACK);
-        let yellow = ColorPair::new(YELLOW, BLACK);
-        let red = ColorPair::new(RED, BLACK);
-        let magenta = ColorPair::new(MAGENTA, BLACK);
-
-        batch.print_color_centered(15, "Your journey has ended!", yellow);
-
-        batch.print_color_centered(17, "One day, we'll tell you all about how you did.", white);
-        batch.print_color_centered(18, "That day, sadly, is not in this chapter...", white);
-
-        batch.print_color_centered(
-            19,
-            &format!("You lived for {} turns.", gamelog::get_event_count("Turn")),
-            white,
-        );
-        batch.print_color_centered(
-            20,
-            &format!(
-                "You suffered {} points of damage.",
-                gamelog::get_event_count("Damage Taken")
-            ),
-            red,
-        );
-        batch.print_color_centered(
-            21,
-            &format!(
-                "You inflicted {} points of damage.",
-                gamelog::get_event_count("Damage Inflicted")
-            ),
-            red,
-        );
-
-        batch.print_color_centered(23, "Press any key to return to the menu.", magenta);
-
-        batch.submit(6000).expect("Batch error");
-
-        if ctx.key.is_some() {
-            ctx.key = None;
-            self.resources.insert(TurnState::MainMenu {
-                selection: MainMenuSelection::NewGame,
-            });
+// Builds `--runs` levels (from `--seed` onwards) with `--builder` and reports
+// quality metrics and suspicious seeds for each builder chain.
+fn analyze_maps(args: &[String]) -> ! {
+    let value = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let number = |flag: &str, default: u64| {
+        value(flag)
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(default)
+    };
+
+    let raws = load_raws();
+    let builder = value("--builder").map(|b| b.as_str());
+    if let Some(name) = builder {
+        if raws.level_chain(name).is_none() && raws.builder_fragment(name).is_none() {
+            eprintln!(
+                "Unknown builder {}; use the name of a level or builder fragment in the raws",
+                name
+            );
+            std::process::exit(2);
         }
     }
-
-    fn configure_registry(&self, registry: &mut Registry<String>) {
-        registry.register::<Point>("position".to_string());
-        registry.register::<Render>("render".to_string());
-        registry.register::<Player>("player".to_string());
-        registry.register::<Name>("name".to_string());
-        registry.register::<Item>("item".to_string());
-        registry.register::<AmuletOfYala>("amulet_of_yala".to_string());
-        registry.register::<FieldOfView>("fov".to_string());
-        registry.register::<ProvidesHealing>("provides_healing".to_string());
-        registry.register::<ProvidesDungeonMap>("provides_map".to_string());
-        registry.register::<Carried>("carried_by".to_string());
-        registry.register::<Damage>("damage".to_string());
-        registry.register::<WeaponAttribute>("wattr".to_string());
-        registry.register::<Weapon>("melee_weapon".to_string());
-        registry.register::<Wearable>("wearable".to_string());
-        registry.register::<BlocksTile>("blocks_tile".to_string());
-        registry.register::<Consumable>("consumable".to_string());
-        registry.register::<Ranged>("ranged".to_string());
-        registry.register::<AreaOfEffect>("aoe".to_string());
-        registry.register::<Confusion>("confusion".to_string());
-        registry.register::<Map>("map".to_string());
-        registry.register::<MapTheme>("theme".to_string());
-        registry.register::<SerializeMe>("serialize".to_string());
-        registry.register::<EquipmentSlot>("slot".to_string());
-        registry.register::<Equippable>("equippable".to_string());
-        registry.register::<Equipped>("equipped".to_string());
-        registry.register::<ParticleLifetime>("particle_lifetime".to_string());
-        registry.register::<ParticleAnimation>("particle_animation".to_string());
-        registry.register::<HungerState>("hunger_state".to_string());
-        registry.register::<HungerClock>("hunger_clock".to_string());
-        registry.register::<ProvidesFood>("provides_food".to_string());
-        registry.register::<Hidden>("hidden".to_string());
-        registry.register::<EntryTrigger>("entry_trigger".to_string());
-        registry.register::<SingleActivation>("one_shot".to_string());
-        registry.register::<BlocksVisibility>("blocks_visibility".to_string());
-        registry.register::<Door>("door".to_string());
-        registry.register::<AlwaysVisible>("always_visible".to_string());
-        registry.register::<Quips>("quips".to_string());
-        registry.register::<Attribute>("attr".to_string());
-        registry.register::<Attributes>("attrs".to_string());
-        registry.register::<Skill>("skill".to_string());
-        registry.register::<Skills>("skills".to_string());
-        registry.register::<Pool>("pool".to_string());
-        registry.register::<Pools>("pools".to_string());
-        registry.register::<NaturalAttack>("nattack".to_string());
-        registry.register::<NaturalAttackDefense>("natkdef".to_string());
-        registry.register::<LootTable>("loot_tbl".to_string());
-        registry.register::<OtherLevelPosition>("olpos".to_string());
-        registry.register::<LightSource>("light_source".to_string());
-        registry.register::<Initiative>("initiative".to_string());
-        registry.register::<MyTurn>("my_turn".to_string());
-        registry.register::<Faction>("faction".to_string());
-        registry.register::<Movement>("movement".to_string());
-        registry.register::<MoveMode>("move_mode".to_string());
-        registry.register::<Chasing>("chasing".to_string());
-        registry.register::<Vendor>("vendor".to_string());
-        registry.register::<TownPortal>("town_portal".to_string());
-        registry.register::<MagicItemClass>("magic_item_class".to_string());
-        registry.register::<MagicItem>("magic_item".to_string());
-        registry.register::<ObfuscatedName>("obf_name".to_string());
-        registry.register::<IdentifiedItem>("identified_item".to_string());
-        registry.register::<MasterDungeonMap>("dungeon_master".to_string());
-        registry.register::<CursedItem>("cursed".to_string());
-        registry.register::<ProvidesRemoveCurse>("removes_curse".to_string());
-        registry.register::<ProvidesIdentify>("identifies".to_string());
-        registry.register::<AttributeBonus>("attr_bonus".to_string());
-        registry.register::<StatusEffect>("status_effect".to_string());
-        registry.register::<Duration>("duration".to_string());
-        registry.register::<KnownSpell>("spell".to_string());
-        registry.register::<KnownSpells>("known_spells".to_string());
-        registry.register::<SpellTemplate>("spell_template".to_string());
-        registry.register::<ProvidesMana>("provides_mana".to_string());
-        registry.register::<TeachSpell>("teach_spell".to_string());
-        registry.register::<Slow>("slow".to_string());
-        registry.register::<DamageOverTime>("dot".to_string());
-        registry.register::<SpecialAbility>("sp_ability".to_string());
-        registry.register::<SpecialAbilities>("sp_abilities".to_string());
-        registry.register::<TileSize>("tile_size".to_string());
-        registry.register::<OnDeath>("on_death".to_string());
-        registry.register::<AlwaysTargetsSelf>("targets_self".to_string());
-        registry.register::<Target>("target".to_string());
-        registry.register::<WantsToShoot>("wants_shoot".to_string());
-        registry.register::<LogFragment>("log_fragment".to_string());
-        registry.on_unknown(Ignore);
-    }
-
-    fn save_game(&mut self) {
-        let mut registry = Registry::<String>::default();
-        self.configure_registry(&mut registry);
-
-        // Temporarily add the map etc. to get them included
-        let map_entity = self
-            .ecs
-            .push((self.resources.get::<Map>().unwrap().clone(), SerializeMe));
-        let dm_entity = self.ecs.push((
-            self.resources.get::<MasterDungeonMap>().unwrap().clone(),
-            SerializeMe,
-        ));
-        let log_entity = self.ecs.push((gamelog::clone_log(), SerializeMe));
-        let events_entity = self.ecs.push((gamelog::clone_events(), SerializeMe));
-
-        let writer = File::create("./savegame.json").unwrap();
-        let entity_serializer = Canon::default();
-        serde_json::to_writer(
-            writer,
-            &self
-                .ecs
-                .as_serializable(component::<SerializeMe>(), &registry, &entity_serializer),
-        )
-        .expect("Error saving game");
-
-        // remove the extras now
-        self.ecs.remove(map_entity);
-        self.ecs.remove(dm_entity);
-        self.ecs.remove(log_entity);
-        self.ecs.remove(events_entity);
-
-        // Show the main menu.
-        self.resources.insert(TurnState::MainMenu {
-            selection: MainMenuSelection::LoadGame,
-        });
+    let first_seed = requested_seed().map_or(1, |s| s.0);
+    let runs = number("--runs", 1000);
+    let depth = number("--depth", 12) as i32;
+
+    let reports = analyze_levels(
+        builder,
+        depth,
+        number("--width", DEFAULT_MAP_WIDTH as u64) as usize,
+        number("--height", DEFAULT_MAP_HEIGHT as u64) as usize,
+        first_seed,
+        runs,
+        &raws,
+    );
+    println!(
+        "{} levels at depth {} from seed {}, {} builder chains",
+        runs,
+        depth,
+        first_seed,
+        reports.len()
+    );
+    for report in reports.iter() {
+        println!();
+        print!("{}", report);
     }
+    std::process::exit(0);
+}
 
-    fn load_game(&mut self) {
-        use serde::de::DeserializeSeed;
-
-        let mut registry = Registry::new();
-        self.configure_registry(&mut registry);
-        let entity_serializer = Canon::default();
-
-        let text = fs::read_to_string("./savegame.json").unwrap();
-        let json = serde_json::from_str::<serde_json::Value>(text.as_str()).unwrap();
-        self.ecs = registry
-            .as_deserialize(&entity_serializer)
-            .deserialize(json)
-            .unwrap();
-        self.resources = Resources::default();
-
-        // extract the map etc.
-        let mut to_remove: Vec<Entity> = Vec::new();
-        {
-            let (map, map_entity) = <(&Map, Entity)>::query()
-                .iter(&mut self.ecs)
-                .nth(0)
-                .unwrap();
-            self.resources.insert(map.clone());
-            spatial::set_size(map.width * map.height);
-            to_remove.push(*map_entity);
-
-            let (dm, dm_entity) = <(&MasterDungeonMap, Entity)>::query()
-                .iter(&mut self.ecs)
-                .nth(0)
-                .unwrap();
-            self.resources.insert(dm.clone());
-            to_remove.push(*dm_entity);
-
-            let (log, log_entity) = <(&Vec<Vec<LogFragment>>, Entity)>::query()
-                .iter(&mut self.ecs)
-                .nth(0)
-                .unwrap();
-            gamelog::restore_log(&mut log.clone());
-            to_remove.push(*log_entity);
-
-            let (events, events_entity) = <(&HashMap<String, i32>, Entity)>::query()
-                .iter(&mut self.ecs)
-                .nth(0)
-                .unwrap();
-            gamelog::load_events(events.clone());
-            to_remove.push(*events_entity);
-        }
-
-        for entity in to_remove {
-            self.ecs.remove(entity);
-        }
-
-        // build the camera, centered on the player
-        let player_pos = <&Point>::query()
-            .filter(component::<Player>())
-            .iter(&mut self.ecs)
-            .nth(0)
-            .unwrap();
-
-        gamelog::Logger::new().append("Loaded game.").log();
-
-        let rng = RandomNumberGenerator::new();
-
-        self.resources.insert(rng);
-        self.resources.insert(Camera::new(*player_pos));
-        self.resources.insert(TurnState::Ticking);
-        self.resources.insert(RexAssets::new());
-
-        // make all FOVs dirty
-        <&mut FieldOfView>::query().for_each_mut(&mut self.ecs, |mut fov| {
-            fov.is_dirty = true;
-        });
-
-        // Permadeath: DELETE THE SAVED GAME!
-        fs::remove_file("./savegame.json").expect("Save deletion failed");
+fn main() -> BError {
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--validate-raws") {
+        validate_raws();
     }
-
-    fn reveal_map(&mut self, row: i32) {
-        let height: usize;
-        {
-            let mut map = self.resources.get_mut::<Map>().unwrap();
-            for x in 0..map.width {
-                let idx = map.point2d_to_index(Point::new(x, row as usize));
-                map.revealed_tiles[idx] = true;
-            }
-            height = map.height;
-        }
-        if row as usize == height - 1 {
-            self.resources.insert(TurnState::Ticking);
-        } else {
-            self.resources.insert(TurnState::RevealMap { row: row + 1 })
-        }
-        map_reveal_scheduler().execute(&mut self.ecs, &mut self.resources);
+    if args.iter().any(|a| a == "--mapgen") {
+        generate_map(&args);
     }
-
-    fn visualize_map_build(&mut self, step: usize, ctx: &BTerm) {
-        let mut continue_build = false;
-
-        if SHOW_MAPGEN_VISUALIZER {
-            self.mapgen_timer += ctx.frame_time_ms;
-
-            if step < self.map_history.len() {
-                self.resources.insert(self.map_history[step].clone());
-                continue_build = true;
-            } else if ctx.key.is_none() {
-                // wait for user to press a key
-                continue_build = true;
-            }
-        }
-
-        if continue_build {
-            map_reveal_scheduler().execute(&mut self.ecs, &mut self.resources);
-
-            self.mapgen_timer += ctx.frame_time_ms;
-            if self.mapgen_timer < 300.0 {
-                return;
-            }
-            self.mapgen_timer = 0.0;
-            self.resources
-                .insert(TurnState::MapBuilding { step: step + 1 });
-        } else {
-            self.map_history.clear();
-            self.resources.insert(self.real_map.clone());
-            self.resources.insert(TurnState::AwaitingInput);
-        }
+    if args.iter().any(|a| a == "--analyze-maps") {
+        analyze_maps(&args);
     }
-}
-
-#[allow(dead_code)]
-impl GameState for State {
-    #[allow(dead_code)]
-    fn tick(&mut self, ctx: &mut BTerm) {
-        ctx.set_active_console(0);
-        ctx.cls();
-        ctx.set_active_console(1);
-        ctx.cls();
-        ctx.set_active_console(2);
-        ctx.cls();
-        ctx.set_active_console(3);
-        ctx.cls();
-
-        ctx.set_active_console(0);
-        self.resources.insert(KeyState::new(ctx));
-        self.resources.insert(ctx.frame_time_ms);
-        self.resources.insert(ParticleBuilder::new());
 
-        // ctx.key = None;
-
-        let current_state = self.resources.get::<TurnState>().unwrap().clone();
-        match current_state {
-            TurnState::AwaitingInput => {
-                self.input_systems
-                    .execute(&mut self.ecs, &mut self.resources);
-                if self.resources.get::<TurnState>().unwrap().clone() != current_state {
-                    // if we changed state, clear keyboard input
-                    ctx.key = None;
-                    // we also survived another turn
-                    gamelog::record_event("Turn", 1);
-                }
-            }
-            TurnState::Ticking => {
-                while self.resources.get::<TurnState>().unwrap().clone() == TurnState::Ticking {
-                    self.tick_systems
-                        .execute(&mut self.ecs, &mut self.resources);
-                }
-                if self.resources.get::<TurnState>().unwrap().clone() == TurnState::AwaitingInput {
-                    update_targeting_scheduler().execute(&mut self.ecs, &mut self.resources);
-                }
-            }
-            TurnState::ShowingInventory
-            | TurnState::ShowingDropItems
-            | TurnState::ShowingRemoveCurse
-            | TurnState::ShowingIdentify => self
-                .popup_menu_systems
-                .execute(&mut self.ecs, &mut self.resources),
-            TurnState::RangedTargeting { range: _, item: _ } => self
-                .ranged_systems
-                .execute(&mut self.ecs, &mut self.resources),
-            TurnState::MainMenu { selection: _ } => {
-                {
-                    // Alas, there's no draw batch command to render a sprite.
-                    let assets = self.resources.get::<RexAssets>().unwrap();
-                    ctx.render_xp_sprite(&assets.menu, 0, 0);
-                }
-                self.menu_systems
-                    .execute(&mut self.ecs, &mut self.resources)
-            }
-            TurnState::NewGame => self.make_new_game(),
-            TurnState::SaveGame => self.save_game(),
-            TurnState::LoadGame => self.load_game(),
-            TurnState::NextLevel => self.switch_level(1),
-            TurnState::PreviousLevel => self.switch_level(-1),
-            TurnState::GameOver => self.game_over(ctx),
-            TurnState::RevealMap { row } => self.reveal_map(row),
-            TurnState::MapBuilding { step } => self.visualize_map_build(step, ctx),
-            TurnState::ShowCheatMenu => {
-                build_cheat_menu_scheduler().execute(&mut self.ecs, &mut self.resources)
-            }
-            TurnState::ShowingVendor { vendor: _, mode: _ } => self
-                .input_systems
-                .execute(&mut self.ecs, &mut self.resources),
-            TurnState::TownPortal => {
-                spawn_town_portal(&mut self.ecs, &mut self.resources);
-                let map_depth = self.resources.get::<Map>().unwrap().depth;
-                self.switch_level(-map_depth);
-            }
-            TurnState::LevelTeleport { destination, depth } => {
-                let map_depth = self.resources.get::<Map>().unwrap().depth;
-                self.switch_level(depth - map_depth);
-                <&mut Point>::query()
-                    .filter(component::<Player>())
-                    .iter_mut(&mut self.ecs)
-                    .for_each(|pt| *pt = destination);
-                self.resources
-                    .get_mut::<Camera>()
-                    .unwrap()
-                    .on_player_move(destination);
-            }
-        }
-        // println!("Tick took {} seconds", tm.elapsed().as_secs_f32());
-
-        render_draw_buffer(ctx).expect("Render error");
+    let replay = requested_replay();
+    if args.iter().any(|a| a == "--headless") {
+        let turns = args
+            .iter()
+            .position(|a| a == "--turns")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|t| t.parse::<i32>().ok())
+            .unwrap_or(1000);
+        let report = match replay {
+            Some(replay) => headless::play_replay(replay),
+            None => headless::random_walk(requested_seed().unwrap_or_else(GameSeed::random), turns),
+        };
+        println!(
+            "seed {} | {} turns | depth {} | level {} | hp {}{}",
+            report.seed,
+            report.turns,
+            report.depth,
+            report.level,
+            report.hit_points,
+            if report.died { " | died" } else { "" }
+        );
+        return Ok(());
     }
-}
 
-fn main() -> BError {
     let context = BTermBuilder::new()
         .with_title("Roguelike Tutorial")
         .with_fps_cap(30.0)
@@ -629,6 +234,9 @@ fn main() -> BError {
     //     .with_simple_console(DISPLAY_WIDTH, DISPLAY_HEIGHT, "terminal8x8.png")
     //     .build()?;
 
-    load_raws();
-    main_loop(context, State::new())
+    let state = match replay {
+        Some(replay) => State::with_replay(replay),
+        None => State::new(requested_seed(), RenderMode::Window),
+    };
+    main_loop(context, state)
 }
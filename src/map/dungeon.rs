@@ -1,9 +1,47 @@
 use crate::prelude::*;
 use std::collections::{HashMap, HashSet};
 
+// Branches are numbered as the game first meets them in the raws; the main
+// dungeon is always 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BranchId(pub usize);
+
+pub const MAIN_BRANCH_ID: BranchId = BranchId(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LevelId {
+    pub branch: BranchId,
+    pub depth: i32,
+}
+
+impl LevelId {
+    pub fn new(branch: BranchId, depth: i32) -> Self {
+        Self { branch, depth }
+    }
+
+    pub fn main(depth: i32) -> Self {
+        Self::new(MAIN_BRANCH_ID, depth)
+    }
+
+    pub fn town() -> Self {
+        Self::main(0)
+    }
+
+    pub fn below(&self) -> Self {
+        Self::new(self.branch, self.depth + 1)
+    }
+
+    pub fn above(&self) -> Self {
+        Self::new(self.branch, self.depth - 1)
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct MasterDungeonMap {
-    maps: HashMap<i32, Map>,
+    maps: Vec<Map>,
+    // The names of the side branches; BranchId(n) is branches[n - 1].
+    #[serde(default)]
+    branches: Vec<String>,
     available_scroll_names: Vec<String>,
     available_potion_types: Vec<String>,
     available_wand_types: Vec<String>,
@@ -15,44 +53,103 @@ pub struct MasterDungeonMap {
 }
 
 impl MasterDungeonMap {
-    pub fn new() -> MasterDungeonMap {
+    pub fn new(raws: &RawMaster, rng: &mut RandomNumberGenerator) -> MasterDungeonMap {
         let mut dm = MasterDungeonMap::default();
 
         dm.build_name_tables();
+        dm.assign_names(raws, rng);
+        dm.add_branches(raws);
+        dm
+    }
 
-        let mut rng = RandomNumberGenerator::new();
-        for scroll_tag in get_scroll_tags().iter() {
-            let idx = rng.random_slice_index(&dm.available_scroll_names).unwrap();
-            let masked_name = dm.available_scroll_names.remove(idx);
-            dm.scroll_mappings.insert(
-                scroll_tag.to_string(),
-                format!("Scroll titled {}", masked_name),
-            );
+    // Numbers any branches in the raws that this game hasn't seen yet.
+    pub fn add_branches(&mut self, raws: &RawMaster) {
+        for branch in raws.branches() {
+            if !self.branches.contains(&branch.name) {
+                self.branches.push(branch.name.clone());
+            }
         }
+    }
 
-        for potion_tag in get_potion_tags().iter() {
-            let idx = rng.random_slice_index(&dm.available_potion_types).unwrap();
-            let masked_name = dm.available_potion_types.remove(idx);
-            dm.potion_mappings
-                .insert(potion_tag.to_string(), format!("{} potion", masked_name));
+    pub fn branch_id(&self, name: &str) -> Option<BranchId> {
+        if name == MAIN_BRANCH {
+            return Some(MAIN_BRANCH_ID);
         }
+        self.branches
+            .iter()
+            .position(|b| b == name)
+            .map(|idx| BranchId(idx + 1))
+    }
 
-        for wand_tag in get_wand_tags().iter() {
-            let idx = rng.random_slice_index(&dm.available_wand_types).unwrap();
-            let masked_name = dm.available_wand_types.remove(idx);
-            dm.wand_mappings
-                .insert(wand_tag.to_string(), format!("{} wand", masked_name));
+    pub fn branch_name(&self, id: BranchId) -> &str {
+        match id.0 {
+            0 => MAIN_BRANCH,
+            n => self.branches.get(n - 1).map_or(MAIN_BRANCH, |b| b.as_str()),
         }
+    }
 
-        dm
+    // The level under this one; the bottom of a side branch has nothing below it.
+    pub fn level_below(&self, level: LevelId, raws: &RawMaster) -> Option<LevelId> {
+        let below = level.below();
+        let dead_end = raws
+            .branch(self.branch_name(below.branch))
+            .is_some_and(|b| below.depth > b.last_depth());
+        if dead_end {
+            None
+        } else {
+            Some(below)
+        }
+    }
+
+    // Gives a masked name to every scroll, potion and wand tag that doesn't
+    // have one yet; existing names are kept so reloaded raws don't reshuffle.
+    pub fn assign_names(&mut self, raws: &RawMaster, rng: &mut RandomNumberGenerator) {
+        for scroll_tag in get_scroll_tags(raws).iter() {
+            if self.scroll_mappings.contains_key(scroll_tag) {
+                continue;
+            }
+            if let Some(idx) = rng.random_slice_index(&self.available_scroll_names) {
+                let masked_name = self.available_scroll_names.remove(idx);
+                self.scroll_mappings.insert(
+                    scroll_tag.to_string(),
+                    format!("Scroll titled {}", masked_name),
+                );
+            }
+        }
+
+        for potion_tag in get_potion_tags(raws).iter() {
+            if self.potion_mappings.contains_key(potion_tag) {
+                continue;
+            }
+            if let Some(idx) = rng.random_slice_index(&self.available_potion_types) {
+                let masked_name = self.available_potion_types.remove(idx);
+                self.potion_mappings
+                    .insert(potion_tag.to_string(), format!("{} potion", masked_name));
+            }
+        }
+
+        for wand_tag in get_wand_tags(raws).iter() {
+            if self.wand_mappings.contains_key(wand_tag) {
+                continue;
+            }
+            if let Some(idx) = rng.random_slice_index(&self.available_wand_types) {
+                let masked_name = self.available_wand_types.remove(idx);
+                self.wand_mappings
+                    .insert(wand_tag.to_string(), format!("{} wand", masked_name));
+            }
+        }
     }
 
     pub fn store_map(&mut self, map: &Map) {
-        self.maps.insert(map.depth, map.clone());
+        let level = map.level_id();
+        match self.maps.iter_mut().find(|m| m.level_id() == level) {
+            Some(stored) => *stored = map.clone(),
+            None => self.maps.push(map.clone()),
+        }
     }
 
-    pub fn get_map(&self, depth: i32) -> Option<Map> {
-        self.maps.get(&depth).map(|m| m.clone())
+    pub fn get_map(&self, level: LevelId) -> Option<Map> {
+        self.maps.iter().find(|m| m.level_id() == level).cloned()
     }
 
     fn build_name_tables(&mut self) {
@@ -151,7 +248,7 @@ impl MasterDungeonMap {
     }
 }
 
-pub fn freeze_level_entities(ecs: &World, depth: i32, commands: &mut CommandBuffer) {
+pub fn freeze_level_entities(ecs: &World, level: LevelId, commands: &mut CommandBuffer) {
     <(Entity, &Point)>::query()
         .filter(!component::<Player>() & !component::<OtherLevelPosition>())
         .for_each(ecs, |(entity, pos)| {
@@ -159,17 +256,17 @@ pub fn freeze_level_entities(ecs: &World, depth: i32, commands: &mut CommandBuff
                 *entity,
                 OtherLevelPosition {
                     position: pos.clone(),
-                    depth,
+                    level,
                 },
             );
             commands.remove_component::<Point>(*entity);
         });
 }
 
-pub fn thaw_level_entities(ecs: &World, depth: i32, commands: &mut CommandBuffer) {
+pub fn thaw_level_entities(ecs: &World, level: LevelId, commands: &mut CommandBuffer) {
     <(Entity, &OtherLevelPosition)>::query()
         .iter(ecs)
-        .filter(|(_, opos)| opos.depth == depth)
+        .filter(|(_, opos)| opos.level == level)
         .for_each(|(entity, opos)| {
             commands.add_component(*entity, opos.position.clone());
             commands.remove_component::<OtherLevelPosition>(*entity);
@@ -1,12 +1,12 @@
 use crate::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub mod dungeon;
 pub use dungeon::*;
 pub mod transitions;
 pub use transitions::*;
 
-#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Hash, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Hash, Eq)]
 pub enum TileType {
     Wall,
     Stalactite,
@@ -21,6 +21,8 @@ pub enum TileType {
     Bridge,
     Gravel,
     UpStairs,
+    Chasm,
+    Pit,
 }
 
 impl TileType {
@@ -34,7 +36,8 @@ impl TileType {
             | TileType::WoodFloor
             | TileType::Bridge
             | TileType::UpStairs
-            | TileType::Gravel => true,
+            | TileType::Gravel
+            | TileType::Pit => true,
             _ => false,
         }
     }
@@ -46,21 +49,42 @@ impl TileType {
         }
     }
 
+    pub fn is_diggable(&self) -> bool {
+        matches!(
+            self,
+            TileType::Wall | TileType::Stalactite | TileType::Stalagmite
+        )
+    }
+
     pub fn cost(&self) -> f32 {
         match self {
             TileType::Road => 0.8,
             TileType::Grass => 1.1,
             TileType::ShallowWater => 1.2,
+            // Walkable, but only as a last resort.
+            TileType::Pit => 10.0,
             _ => 1.0,
         }
     }
 }
 
+// A room given a purpose while the level was built. The player is told what
+// it is the first time they walk in.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpecialRoom {
+    pub kind: String,
+    pub message: String,
+    pub area: Rect,
+    pub entered: bool,
+}
+
 #[derive(Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Map {
     pub width: usize,
     pub height: usize,
     pub depth: i32,
+    #[serde(default)]
+    pub branch: BranchId,
     pub name: String,
     pub theme: MapTheme,
     pub outdoors: bool,
@@ -70,7 +94,19 @@ pub struct Map {
     pub revealed_tiles: Vec<bool>,
     pub bloodstains: HashSet<usize>,
     pub view_blocked: HashSet<usize>,
+    // Impassable tiles as of the last populate_blocked; pathfinding goes by
+    // this rather than the tiles themselves.
+    #[serde(skip)]
+    pub blocked: Vec<bool>,
     pub visible_tiles: Vec<bool>, // tiles that are always fully visible
+    // Stairs that lead somewhere other than the next level up or down.
+    #[serde(default)]
+    pub exits: HashMap<usize, LevelId>,
+    #[serde(default)]
+    pub special_rooms: Vec<SpecialRoom>,
+    // Tiles that can only be seen from right next to them.
+    #[serde(default)]
+    pub dark_tiles: HashSet<usize>,
 
     pub debug_pathing: bool,
 }
@@ -78,11 +114,11 @@ pub struct Map {
 impl Map {
     pub fn new<S: ToString>(depth: i32, width: usize, height: usize, name: S) -> Self {
         let num_tiles = width * height;
-        crate::spatial::set_size(num_tiles);
         Self {
             width,
             height,
             depth,
+            branch: MAIN_BRANCH_ID,
             name: name.to_string(),
             theme: MapTheme::Dungeon,
             outdoors: true,
@@ -91,20 +127,39 @@ impl Map {
             revealed_tiles: vec![false; num_tiles],
             bloodstains: HashSet::new(),
             view_blocked: HashSet::new(),
+            blocked: vec![false; num_tiles],
             visible_tiles: vec![false; num_tiles],
+            exits: HashMap::new(),
+            special_rooms: Vec::new(),
+            dark_tiles: HashSet::new(),
             debug_pathing: false,
         }
     }
 
     pub fn clone_empty(map: &Map) -> Self {
-        let num_tiles = map.width * map.height;
-        crate::spatial::set_size(num_tiles);
         let mut new_map = Map::new(map.depth, map.width, map.height, &map.name);
+        new_map.branch = map.branch;
         new_map.theme = map.theme.clone();
         new_map.outdoors = map.outdoors;
         new_map
     }
 
+    pub fn level_id(&self) -> LevelId {
+        LevelId::new(self.branch, self.depth)
+    }
+
+    // Where the stairs on this tile lead, if there are any.
+    pub fn exit_from(&self, idx: usize) -> Option<LevelId> {
+        if let Some(level) = self.exits.get(&idx) {
+            return Some(*level);
+        }
+        match self.tiles[idx] {
+            TileType::DownStairs => Some(self.level_id().below()),
+            TileType::UpStairs => Some(self.level_id().above()),
+            _ => None,
+        }
+    }
+
     pub fn fill(&mut self, tile: TileType) {
         self.tiles.iter_mut().for_each(|t| *t = tile);
     }
@@ -113,8 +168,8 @@ impl Map {
         point.x >= 0 && point.x < self.width as i32 && point.y >= 0 && point.y < self.height as i32
     }
 
-    pub fn can_enter_tile(&self, point: Point) -> bool {
-        self.in_bounds(point) && (crate::spatial::is_blocked(self.idx_for_pos(&point)) == false)
+    pub fn can_enter_tile(&self, point: Point, spatial: &SpatialMap) -> bool {
+        self.in_bounds(point) && !spatial.is_blocked(self.idx_for_pos(&point))
     }
 
     pub fn try_idx(&self, point: Point) -> Option<usize> {
@@ -126,14 +181,14 @@ impl Map {
     }
 
     pub fn populate_blocked(&mut self) {
-        crate::spatial::populate_blocked_from_map(self)
+        self.blocked = self.tiles.iter().map(|t| !t.is_walkable()).collect();
     }
 
     fn valid_exit(&self, loc: Point, delta: Point) -> Option<usize> {
         let destination = loc + delta;
         if self.in_bounds(destination) {
             let idx = self.idx_for_pos(&destination);
-            if !crate::spatial::is_blocked_by_tile(idx) {
+            if !self.blocked.get(idx).copied().unwrap_or(false) {
                 Some(idx)
             } else {
                 None
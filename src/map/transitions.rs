@@ -1,23 +1,31 @@
 use crate::prelude::*;
+use std::collections::HashSet;
 
 pub fn level_transition(
     ecs: &mut World,
     resources: &mut Resources,
     rng: &mut RandomNumberGenerator,
-    new_depth: i32,
-    offset: i32,
-) -> Option<Vec<Map>> {
+    level: LevelId,
+    from: LevelId,
+    record_history: bool,
+) -> Option<Vec<MapSnapshot>> {
     // Obtain the master dungeon map from the resources.
     let dungeon_master = resources.get_or_default::<MasterDungeonMap>();
 
     // Do we already have a map?
-    if dungeon_master.get_map(new_depth).is_some() {
+    if dungeon_master.get_map(level).is_some() {
         std::mem::drop(dungeon_master);
-        transition_to_existing_map(ecs, resources, new_depth, offset);
+        transition_to_existing_map(ecs, resources, level, from);
         None
     } else {
         std::mem::drop(dungeon_master);
-        Some(transition_to_new_map(ecs, resources, rng, new_depth))
+        Some(transition_to_new_map(
+            ecs,
+            resources,
+            rng,
+            level,
+            record_history,
+        ))
     }
 }
 
@@ -25,21 +33,26 @@ fn transition_to_new_map(
     ecs: &mut World,
     resources: &mut Resources,
     rng: &mut RandomNumberGenerator,
-    new_depth: i32,
-) -> Vec<Map> {
-    let mut builder = level_builder(new_depth, 80, 50, rng);
-    builder.build_map(rng);
-
+    level: LevelId,
+    record_history: bool,
+) -> Vec<MapSnapshot> {
+    let raws = resources.get::<RawMaster>().unwrap();
     let dm = resources.get::<MasterDungeonMap>().unwrap();
-    builder.spawn_entities(ecs, &dm);
+    let mut builder = build_level(
+        level,
+        DEFAULT_MAP_WIDTH,
+        DEFAULT_MAP_HEIGHT,
+        rng,
+        &raws,
+        &dm,
+        record_history,
+    );
+
+    builder.spawn_entities(ecs, &raws, &dm, rng);
     std::mem::drop(dm);
+    std::mem::drop(raws);
 
     if let Some(pos) = &builder.build_data.starting_position {
-        if new_depth != 0 {
-            let up_idx = builder.build_data.map.point2d_to_index(*pos);
-            builder.build_data.map.tiles[up_idx] = TileType::UpStairs;
-        }
-
         // Update player if they exist.
         let mut found_user = false;
         <(&mut Point, &mut FieldOfView)>::query()
@@ -52,16 +65,20 @@ fn transition_to_new_map(
 
         if !found_user {
             // Need to spawn the player
+            let raws = resources.get::<RawMaster>().unwrap();
             let dm = resources.get::<MasterDungeonMap>().unwrap();
-            spawn_player(ecs, &dm, *pos);
+            spawn_player(ecs, &raws, &dm, rng, *pos);
         }
 
         // Update the camera
-        resources.insert(Camera::new(*pos));
+        resources.insert(Camera::new(*pos, &builder.build_data.map));
     }
 
-    // Put the map into resources *after* we add the up-stairs
+    // Put the map into resources
     resources.insert(builder.build_data.map.clone());
+    resources
+        .get_mut_or_default::<SpatialMap>()
+        .set_size(builder.build_data.map.tiles.len());
 
     // Store in the dungeon master
     let mut dungeon_master = resources.get_mut::<MasterDungeonMap>().unwrap();
@@ -73,34 +90,104 @@ fn transition_to_new_map(
 fn transition_to_existing_map(
     ecs: &mut World,
     resources: &mut Resources,
-    new_depth: i32,
-    offset: i32,
+    level: LevelId,
+    from: LevelId,
 ) {
     // We know it's here at this point.
     let dungeon_master = resources.get::<MasterDungeonMap>().unwrap();
-    let map = dungeon_master.get_map(new_depth).unwrap();
+    let map = dungeon_master.get_map(level).unwrap();
     std::mem::drop(dungeon_master);
 
     resources.insert(map.clone());
+    resources
+        .get_mut_or_default::<SpatialMap>()
+        .set_size(map.tiles.len());
 
-    // Find the appropriate stairs and place the player there.
-    let stair_type = if offset < 0 {
+    // Find the stairs leading back where we came from and place the player
+    // there, falling back to any stairs in the right direction.
+    let stair_type = if from.depth > level.depth {
         TileType::DownStairs
     } else {
         TileType::UpStairs
     };
+    let arrival = (0..map.tiles.len())
+        .find(|idx| map.exit_from(*idx) == Some(from))
+        .or_else(|| map.tiles.iter().position(|tt| *tt == stair_type));
+
+    if let Some(idx) = arrival {
+        let pos = map.index_to_point2d(idx);
+        <(&mut Point, &mut FieldOfView)>::query()
+            .filter(component::<Player>())
+            .for_each_mut(ecs, |(pt, fov)| {
+                *pt = pos;
+                fov.is_dirty = true;
+                resources.insert(Camera::new(pos, &map));
+            });
+    }
+}
+
+// Whatever fell from the level above lands on open ground somewhere on this
+// one, clear of the stairs and of anything already standing there.
+pub fn land_fallen_entities(
+    ecs: &mut World,
+    resources: &mut Resources,
+    rng: &mut RandomNumberGenerator,
+) {
+    let map = resources.get::<Map>().unwrap().clone();
+    let taken: HashSet<Point> = <&Point>::query()
+        .filter(component::<BlocksTile>() | component::<EntryTrigger>())
+        .iter(ecs)
+        .copied()
+        .collect();
+    let mut spots: Vec<usize> = (0..map.tiles.len())
+        .filter(|idx| {
+            map.tiles[*idx].is_walkable()
+                && map.tiles[*idx] != TileType::Pit
+                && map.exit_from(*idx).is_none()
+                && !taken.contains(&map.index_to_point2d(*idx))
+        })
+        .collect();
+    // If there is no open ground left, land on the way back up instead.
+    let fallback = map
+        .tiles
+        .iter()
+        .position(|t| *t == TileType::UpStairs)
+        .or_else(|| (0..map.tiles.len()).find(|idx| map.exit_from(*idx).is_some()))
+        .map(|idx| map.index_to_point2d(idx));
 
-    for (idx, tt) in map.tiles.iter().enumerate() {
-        if *tt == stair_type {
-            let pos = map.index_to_point2d(idx);
-            <(&mut Point, &mut FieldOfView)>::query()
-                .filter(component::<Player>())
-                .for_each_mut(ecs, |(pt, fov)| {
-                    *pt = pos;
-                    fov.is_dirty = true;
-                    resources.insert(Camera::new(pos));
-                });
-            break;
+    let mut landed = Vec::new();
+    let mut player_pos = None;
+    <(
+        Entity,
+        &mut Point,
+        Option<&mut FieldOfView>,
+        Option<&Player>,
+    )>::query()
+    .filter(component::<Fallen>())
+    .for_each_mut(ecs, |(entity, pos, fov, player)| {
+        match rng.random_slice_index(&spots) {
+            Some(i) => *pos = map.index_to_point2d(spots.remove(i)),
+            None => {
+                if let Some(fallback) = fallback {
+                    *pos = fallback;
+                }
+            }
         }
+        if let Some(fov) = fov {
+            fov.is_dirty = true;
+        }
+        if player.is_some() {
+            player_pos = Some(*pos);
+        }
+        landed.push(*entity);
+    });
+
+    for entity in landed {
+        if let Some(mut entry) = ecs.entry(entity) {
+            entry.remove_component::<Fallen>();
+        }
+    }
+    if let Some(pos) = player_pos {
+        resources.insert(Camera::new(pos, &map));
     }
 }
@@ -0,0 +1,254 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+// Levels with less walkable floor than this are reported.
+const MIN_FLOOR_RATIO: f32 = 0.15;
+// Floor ratios this many standard deviations from their chain's mean are
+// reported too, unless they are within a few percent of it anyway.
+const FLOOR_RATIO_SIGMAS: f32 = 2.0;
+const MIN_FLOOR_RATIO_DEVIATION: f32 = 0.05;
+const MAX_OUTLIERS_SHOWN: usize = 10;
+
+pub struct LevelMetrics {
+    pub seed: GameSeed,
+    pub tiles: usize,
+    pub floor_ratio: f32,
+    pub has_start: bool,
+    pub has_exit: bool,
+    // Walking distance from the start to the down stairs, if they connect.
+    pub path_length: Option<f32>,
+    pub unreachable_floor: usize,
+    pub rooms: usize,
+    pub corridors: usize,
+    pub spawns: usize,
+    pub doors: usize,
+}
+
+impl LevelMetrics {
+    fn measure(seed: GameSeed, build_data: &BuilderMap) -> LevelMetrics {
+        // Secret passages are only wall until they are found.
+        let mut map = build_data.map.clone();
+        for (pos, name) in build_data.spawn_list.iter() {
+            if SECRET_PASSAGES.contains(&name.as_str()) {
+                if let Some(idx) = map.try_idx(*pos) {
+                    map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+        map.populate_blocked();
+        let map = &map;
+        let walkable: Vec<usize> = (0..map.tiles.len())
+            .filter(|idx| map.tiles[*idx].is_walkable())
+            .collect();
+        let exit = map.tiles.iter().position(|t| *t == TileType::DownStairs);
+        let start = build_data.starting_position.and_then(|p| map.try_idx(p));
+
+        let (path_length, unreachable_floor) = match start {
+            Some(start) => {
+                let dijkstra = DijkstraMap::new(map.width, map.height, &[start], map, 10000.0);
+                let reached = |idx: usize| dijkstra.map[idx] < f32::MAX;
+                (
+                    exit.filter(|e| reached(*e)).map(|e| dijkstra.map[e]),
+                    walkable.iter().filter(|idx| !reached(**idx)).count(),
+                )
+            }
+            None => (None, walkable.len()),
+        };
+
+        LevelMetrics {
+            seed,
+            tiles: map.tiles.len(),
+            floor_ratio: walkable.len() as f32 / map.tiles.len() as f32,
+            has_start: start.is_some(),
+            has_exit: exit.is_some(),
+            path_length,
+            unreachable_floor,
+            rooms: build_data.rooms.as_ref().map_or(0, |r| r.len()),
+            corridors: build_data.corridors.as_ref().map_or(0, |c| c.len()),
+            spawns: build_data.spawn_list.len(),
+            doors: build_data
+                .spawn_list
+                .iter()
+                .filter(|(_, name)| name == "Door")
+                .count(),
+        }
+    }
+
+    fn floor_tiles(&self) -> f32 {
+        self.floor_ratio * self.tiles as f32
+    }
+
+    fn problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if !self.has_start {
+            problems.push("no starting position");
+        }
+        if !self.has_exit {
+            problems.push("no down stairs");
+        } else if self.has_start && self.path_length.is_none() {
+            problems.push("down stairs unreachable");
+        }
+        if self.floor_ratio < MIN_FLOOR_RATIO {
+            problems.push("mostly wall");
+        }
+        if self.unreachable_floor > 0 {
+            problems.push("isolated pockets");
+        }
+        problems
+    }
+}
+
+// Every level built by one combination of builders.
+pub struct ChainReport {
+    pub chain: String,
+    pub levels: Vec<LevelMetrics>,
+    pub panics: Vec<(GameSeed, String)>,
+}
+
+impl ChainReport {
+    pub fn runs(&self) -> usize {
+        self.levels.len() + self.panics.len()
+    }
+
+    fn mean<F: Fn(&LevelMetrics) -> f32>(&self, f: F) -> f32 {
+        if self.levels.is_empty() {
+            0.0
+        } else {
+            self.levels.iter().map(f).sum::<f32>() / self.levels.len() as f32
+        }
+    }
+
+    fn percent<F: Fn(&LevelMetrics) -> bool>(&self, f: F) -> f32 {
+        self.mean(|l| if f(l) { 100.0 } else { 0.0 })
+    }
+
+    // Seeds worth a look, with why.
+    pub fn outliers(&self) -> Vec<(GameSeed, String)> {
+        let mean = self.mean(|l| l.floor_ratio);
+        let sigma = self.mean(|l| (l.floor_ratio - mean).powi(2)).sqrt();
+
+        let mut outliers: Vec<(GameSeed, String)> = self
+            .panics
+            .iter()
+            .map(|(seed, message)| (*seed, format!("panicked: {}", message)))
+            .collect();
+        for level in self.levels.iter() {
+            let mut problems: Vec<String> =
+                level.problems().iter().map(|p| p.to_string()).collect();
+            let deviation = (level.floor_ratio - mean).abs();
+            if deviation > f32::max(FLOOR_RATIO_SIGMAS * sigma, MIN_FLOOR_RATIO_DEVIATION) {
+                problems.push(format!(
+                    "unusual floor ratio {:.0}%",
+                    level.floor_ratio * 100.0
+                ));
+            }
+            if !problems.is_empty() {
+                outliers.push((level.seed, problems.join(", ")));
+            }
+        }
+        outliers
+    }
+}
+
+impl fmt::Display for ChainReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.chain)?;
+        let paths: Vec<f32> = self.levels.iter().filter_map(|l| l.path_length).collect();
+        let min_floor = self
+            .levels
+            .iter()
+            .map(|l| l.floor_ratio)
+            .fold(f32::MAX, f32::min);
+        writeln!(
+            f,
+            "  runs {} | panics {} | floor {:.0}% (min {:.0}%) | exit reachable {:.0}% | path {:.1} | rooms {:.1} | corridors {:.1} | spawns {:.1}/100 floor | doors {:.1} | pockets {:.0}%",
+            self.runs(),
+            self.panics.len(),
+            self.mean(|l| l.floor_ratio) * 100.0,
+            if self.levels.is_empty() { 0.0 } else { min_floor * 100.0 },
+            self.percent(|l| l.path_length.is_some()),
+            if paths.is_empty() { 0.0 } else { paths.iter().sum::<f32>() / paths.len() as f32 },
+            self.mean(|l| l.rooms as f32),
+            self.mean(|l| l.corridors as f32),
+            self.mean(|l| 100.0 * l.spawns as f32 / l.floor_tiles().max(1.0)),
+            self.mean(|l| l.doors as f32),
+            self.percent(|l| l.unreachable_floor > 0),
+        )?;
+
+        let outliers = self.outliers();
+        for (seed, reason) in outliers.iter().take(MAX_OUTLIERS_SHOWN) {
+            writeln!(f, "  seed {}: {}", seed, reason)?;
+        }
+        if outliers.len() > MAX_OUTLIERS_SHOWN {
+            writeln!(f, "  ... and {} more", outliers.len() - MAX_OUTLIERS_SHOWN)?;
+        }
+        Ok(())
+    }
+}
+
+// Builds a level for each of `runs` consecutive seeds and groups the results
+// by builder chain, most common first. Without a level chain or fragment
+// name, each seed builds what the game would for that depth, so a reported
+// seed can be replayed with `--seed`.
+pub fn analyze_levels(
+    builder: Option<&str>,
+    depth: i32,
+    width: usize,
+    height: usize,
+    first_seed: u64,
+    runs: u64,
+    raws: &RawMaster,
+) -> Vec<ChainReport> {
+    let mut reports: HashMap<String, ChainReport> = HashMap::new();
+
+    // Panics are collected into the report rather than printed.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    for seed in (first_seed..first_seed.saturating_add(runs)).map(GameSeed) {
+        let mut rng = seed.level_rng(depth);
+        let chain = panic::catch_unwind(AssertUnwindSafe(|| match builder {
+            None => level_builder(MAIN_BRANCH, depth, width, height, &mut rng, raws),
+            Some(name) => named_builder(name, depth, width, height, &mut rng, raws)
+                .unwrap_or_else(|| panic!("No level chain or fragment named {}", name)),
+        }));
+        let (name, result) = match chain {
+            Ok(mut chain) => {
+                let built = panic::catch_unwind(AssertUnwindSafe(|| {
+                    chain.build_map(&mut rng, raws);
+                    LevelMetrics::measure(seed, &chain.build_data)
+                }));
+                (chain.describe(), built)
+            }
+            Err(e) => ("(chain construction)".to_string(), Err(e)),
+        };
+
+        let report = reports.entry(name.clone()).or_insert_with(|| ChainReport {
+            chain: name,
+            levels: Vec::new(),
+            panics: Vec::new(),
+        });
+        match result {
+            Ok(metrics) => report.levels.push(metrics),
+            Err(e) => report.panics.push((seed, panic_message(e))),
+        }
+    }
+
+    panic::set_hook(default_hook);
+
+    let mut reports: Vec<ChainReport> = reports.into_values().collect();
+    reports.sort_by(|a, b| b.runs().cmp(&a.runs()).then(a.chain.cmp(&b.chain)));
+    reports
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::prelude::*;
+
+// Links a freshly built level into the branches around it: the first level of
+// a side branch leads back up to its parent, the last has no way down, and
+// levels with side branches get extra stairs down into them.
+pub fn connect_branches(
+    build_data: &mut BuilderMap,
+    branch_name: &str,
+    raws: &RawMaster,
+    dm: &MasterDungeonMap,
+) {
+    let depth = build_data.map.depth;
+    let start_idx = match build_data.starting_position {
+        Some(start) => build_data.map.point2d_to_index(start),
+        None => return,
+    };
+
+    if let Some(branch) = raws.branch(branch_name) {
+        if depth == branch.first_depth() {
+            if let Some(parent) = dm.branch_id(branch.parent()) {
+                build_data
+                    .map
+                    .exits
+                    .insert(start_idx, LevelId::new(parent, branch.entrance_depth));
+            }
+        }
+        if depth >= branch.last_depth() {
+            for tile in build_data.map.tiles.iter_mut() {
+                if *tile == TileType::DownStairs {
+                    *tile = TileType::Floor;
+                }
+            }
+        }
+    }
+
+    for side_branch in raws.branches_from(branch_name, depth) {
+        let id = match dm.branch_id(&side_branch.name) {
+            Some(id) => id,
+            None => continue,
+        };
+        build_data.map.populate_blocked();
+        match entrance_site(build_data, start_idx) {
+            Some(idx) => {
+                build_data.map.tiles[idx] = TileType::DownStairs;
+                build_data
+                    .map
+                    .exits
+                    .insert(idx, LevelId::new(id, side_branch.first_depth()));
+            }
+            None => log(format!(
+                "WARNING: no room for the entrance to {} at depth {}",
+                side_branch.name, depth
+            )),
+        }
+    }
+}
+
+// The reachable floor tile furthest from both the start and any existing
+// stairs, with nothing spawned on it. No dice are rolled, so adding a branch
+// doesn't change anything else about the level.
+fn entrance_site(build_data: &BuilderMap, start_idx: usize) -> Option<usize> {
+    let map = &build_data.map;
+    let stairs: Vec<usize> = map
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| matches!(t, TileType::DownStairs | TileType::UpStairs))
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(start_idx))
+        .collect();
+    let from_start = DijkstraMap::new(map.width, map.height, &[start_idx], map, 10000.0);
+    let from_stairs = DijkstraMap::new(map.width, map.height, &stairs, map, 10000.0);
+    let occupied: Vec<usize> = build_data
+        .spawn_list
+        .iter()
+        .filter_map(|(pos, _)| map.try_idx(*pos))
+        .collect();
+
+    (0..map.tiles.len())
+        .filter(|idx| map.tiles[*idx] == TileType::Floor && !occupied.contains(idx))
+        .filter(|idx| from_start.map[*idx] < f32::MAX)
+        .map(|idx| (idx, f32::min(from_start.map[idx], from_stairs.map[idx])))
+        .fold(
+            None,
+            |best: Option<(usize, f32)>, (idx, distance)| match best {
+                Some((_, best_distance)) if best_distance >= distance => best,
+                _ => Some((idx, distance)),
+            },
+        )
+        .map(|(idx, _)| idx)
+}
@@ -1,91 +1,14 @@
 use crate::prelude::*;
 
 use super::{
-    area_ending_points::{AreaEndingPosition, XEnd, YEnd},
-    area_starting_points::{AreaStartingPosition, XStart, YStart},
-    automata::CellularAutomataBuilder,
     bsp::BSPDungeonBuilder,
-    cave_decorator::CaveDecorator,
-    cull_unreachable::CullUnreachable,
-    distant_exit::DistantExit,
-    dla::DLABuilder,
-    drunkard::DrunkardsWalkBuilder,
     nearest_corridors::NearestCorridors,
-    prefab::{self, PrefabBuilder},
     room_based_spawner::RoomBasedSpawner,
     room_draw::RoomDrawer,
     room_exploder::RoomExploder,
     room_sorter::{RoomSort, RoomSorter},
-    voronoi_spawning::VoronoiSpawning,
 };
 
-pub fn limestone_cavern_builder(
-    new_depth: i32,
-    _rng: &mut RandomNumberGenerator,
-    width: usize,
-    height: usize,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Limestone Caverns");
-    chain.build_data.map.theme = MapTheme::LimestoneCavern;
-    chain.build_data.map.outdoors = false;
-
-    chain.initial(DrunkardsWalkBuilder::winding_passages());
-    chain.push(AreaStartingPosition::new(XStart::Center, YStart::Center));
-    chain.push(CullUnreachable::new());
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Center));
-    chain.push(VoronoiSpawning::new());
-    chain.push(DistantExit::new());
-    chain.push(CaveDecorator::new());
-    chain
-}
-
-pub fn limestone_deep_cavern_builder(
-    new_depth: i32,
-    _rng: &mut RandomNumberGenerator,
-    width: usize,
-    height: usize,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Deep Limestone Caverns");
-    chain.build_data.map.theme = MapTheme::LimestoneCavern;
-    chain.build_data.map.outdoors = false;
-
-    chain.initial(DLABuilder::central_attractor());
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Top));
-    chain.push(VoronoiSpawning::new());
-    chain.push(DistantExit::new());
-    chain.push(CaveDecorator::new());
-    chain.push(PrefabBuilder::sectional(prefab::sections::ORC_CAMP));
-    chain
-}
-
-pub fn limestone_transition_builder(
-    new_depth: i32,
-    _rng: &mut RandomNumberGenerator,
-    width: usize,
-    height: usize,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Dwarf Fort - Upper Reaches");
-    chain.build_data.map.theme = MapTheme::transition(
-        MapTheme::LimestoneCavern,
-        MapTheme::Dungeon,
-        0.5,
-        Orientation::Horizontal,
-    );
-    chain.build_data.map.outdoors = false;
-
-    chain.initial(CellularAutomataBuilder::new());
-    chain.push(AreaStartingPosition::new(XStart::Center, YStart::Center));
-    chain.push(CullUnreachable::new());
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Center));
-    chain.push(VoronoiSpawning::new());
-    chain.push(CaveDecorator::new());
-    chain.push(CaveTransition::new(0.5));
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Center));
-    chain.push(CullUnreachable::new());
-    chain.push(AreaEndingPosition::new(XEnd::Right, YEnd::Center));
-    chain
-}
-
 pub struct CaveTransition {
     divisor: f32,
 }
@@ -118,7 +41,7 @@ impl CaveTransition {
         builder.push(NearestCorridors::new());
         builder.push(RoomExploder::new());
         builder.push(RoomBasedSpawner::new());
-        builder.build_map(rng);
+        builder.build_within(rng, build_data);
 
         // Add the history to our history
         for h in builder.build_data.history.iter() {
@@ -0,0 +1,526 @@
+use crate::prelude::*;
+
+use super::{
+    area_ending_points::{AreaEndingPosition, XEnd, YEnd},
+    area_starting_points::{AreaStartingPosition, XStart, YStart},
+    automata::CellularAutomataBuilder,
+    bsp::BSPDungeonBuilder,
+    bsp_interior::BSPInteriorBuilder,
+    cave_decorator::CaveDecorator,
+    caverns::CaveTransition,
+    cull_unreachable::CullUnreachable,
+    dark_elves::PlazaMapBuilder,
+    distant_exit::DistantExit,
+    dla::DLABuilder,
+    door_placement::DoorPlacement,
+    drunkard::DrunkardsWalkBuilder,
+    dwarf_fort::{DragonSpawner, DragonsLair},
+    forest::YellowBrickRoad,
+    maze::MazeBuilder,
+    nearest_corridors::NearestCorridors,
+    pits::Pits,
+    prefab::PrefabBuilder,
+    room_based_spawner::RoomBasedSpawner,
+    room_based_stairs::RoomBasedStairs,
+    room_based_starting_position::RoomBasedStartingPosition,
+    room_corner_rounding::RoomCornerRounder,
+    room_corridor_spawner::CorridorSpawner,
+    room_corridors_bsp::BSPCorridors,
+    room_corridors_dogleg::DoglegCorridors,
+    room_corridors_lines::StraightLineCorridors,
+    room_draw::RoomDrawer,
+    room_exploder::RoomExploder,
+    room_sorter::{RoomSort, RoomSorter},
+    secret_passages::SecretPassages,
+    simple::SimpleMapBuilder,
+    special_rooms::SpecialRooms,
+    town::TownBuilder,
+    voronoi::VoronoiCellBuilder,
+    voronoi_spawning::VoronoiSpawning,
+    waterways::Waterways,
+    waveform_collapse::WaveformCollapseBuilder,
+    PRINT_CHAIN_ITEMS,
+};
+
+// Guards against fragments that (indirectly) include themselves.
+const MAX_NESTING: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuilderKind {
+    // Only starts a chain.
+    Initial,
+    // Only modifies an existing map.
+    Meta,
+    // Starts the chain if nothing has yet, otherwise modifies the map.
+    Either,
+}
+
+// Any builder can work on a map this small.
+pub const SMALLEST_MAP: (usize, usize) = (3, 3);
+
+// What the raws may use for "builder", the parameters each needs or may be
+// given, and the smallest map (width, height) it can build on without
+// panicking or never finishing.
+pub struct BuilderInfo {
+    pub name: &'static str,
+    pub kind: BuilderKind,
+    pub variants: &'static [&'static str],
+    pub params: &'static [&'static str],
+    pub optional: &'static [&'static str],
+    pub min_size: (usize, usize),
+}
+
+impl BuilderInfo {
+    const fn min_size(self, width: usize, height: usize) -> BuilderInfo {
+        BuilderInfo {
+            min_size: (width, height),
+            ..self
+        }
+    }
+}
+
+const fn builder(
+    name: &'static str,
+    kind: BuilderKind,
+    variants: &'static [&'static str],
+    params: &'static [&'static str],
+) -> BuilderInfo {
+    builder_with_optional(name, kind, variants, params, &[])
+}
+
+const fn builder_with_optional(
+    name: &'static str,
+    kind: BuilderKind,
+    variants: &'static [&'static str],
+    params: &'static [&'static str],
+    optional: &'static [&'static str],
+) -> BuilderInfo {
+    BuilderInfo {
+        name,
+        kind,
+        variants,
+        params,
+        optional,
+        min_size: SMALLEST_MAP,
+    }
+}
+
+pub const X_POSITIONS: [&str; 3] = ["Left", "Center", "Right"];
+pub const Y_POSITIONS: [&str; 3] = ["Top", "Center", "Bottom"];
+pub const RANDOM_POSITION: &str = "Random";
+
+pub const BUILDERS: [BuilderInfo; 41] = [
+    builder("Simple", BuilderKind::Initial, &[], &[]).min_size(12, 12),
+    builder("BSPDungeon", BuilderKind::Initial, &[], &[]).min_size(5, 5),
+    builder("BSPInterior", BuilderKind::Initial, &[], &[]).min_size(8, 8),
+    builder("CellularAutomata", BuilderKind::Either, &[], &[]),
+    builder(
+        "DrunkardsWalk",
+        BuilderKind::Either,
+        &[
+            "open_area",
+            "open_halls",
+            "winding_passages",
+            "fat_passages",
+            "fearful_symmetry",
+        ],
+        &[],
+    )
+    .min_size(7, 7),
+    builder("Maze", BuilderKind::Initial, &[], &[]).min_size(17, 4),
+    builder(
+        "DLA",
+        BuilderKind::Either,
+        &[
+            "walk_inwards",
+            "walk_outwards",
+            "central_attractor",
+            "rorschach",
+            "heavy_erosion",
+        ],
+        &[],
+    )
+    .min_size(5, 5),
+    builder(
+        "Voronoi",
+        BuilderKind::Initial,
+        &["pythagoras", "manhattan", "chebyshev"],
+        &[],
+    ),
+    builder("PrefabLevel", BuilderKind::Initial, &[], &["prefab"]),
+    builder("Town", BuilderKind::Initial, &[], &[]).min_size(68, 41),
+    builder("Plaza", BuilderKind::Initial, &[], &[]).min_size(7, 7),
+    builder(
+        "RoomSorter",
+        BuilderKind::Meta,
+        &["Leftmost", "Rightmost", "Topmost", "Bottommost", "Central"],
+        &[],
+    ),
+    builder("RoomDrawer", BuilderKind::Meta, &[], &[]),
+    builder("DoglegCorridors", BuilderKind::Meta, &[], &[]),
+    builder("BSPCorridors", BuilderKind::Meta, &[], &[]).min_size(10, 10),
+    builder("StraightLineCorridors", BuilderKind::Meta, &[], &[]),
+    builder("NearestCorridors", BuilderKind::Meta, &[], &[]),
+    builder("CorridorSpawner", BuilderKind::Meta, &[], &[]),
+    builder("RoomExploder", BuilderKind::Meta, &[], &[]),
+    builder("RoomCornerRounder", BuilderKind::Meta, &[], &[]),
+    builder("RoomBasedStartingPosition", BuilderKind::Meta, &[], &[]).min_size(10, 10),
+    builder("RoomBasedStairs", BuilderKind::Meta, &[], &[]).min_size(10, 10),
+    builder("RoomBasedSpawner", BuilderKind::Meta, &[], &[]),
+    builder("SpecialRooms", BuilderKind::Meta, &[], &[]),
+    builder("AreaStartingPosition", BuilderKind::Meta, &[], &["x", "y"]).min_size(10, 8),
+    builder("AreaEndingPosition", BuilderKind::Meta, &[], &["x", "y"]).min_size(10, 8),
+    builder("DistantExit", BuilderKind::Meta, &[], &[]),
+    builder("CullUnreachable", BuilderKind::Meta, &[], &[]),
+    builder("VoronoiSpawning", BuilderKind::Meta, &[], &[]),
+    builder_with_optional(
+        "WaveformCollapse",
+        BuilderKind::Either,
+        &[],
+        &[],
+        &["prefab", "chunk_size", "overlapping"],
+    ),
+    builder("PrefabSection", BuilderKind::Meta, &[], &["prefab"]),
+    builder("Vaults", BuilderKind::Meta, &[], &[]),
+    builder("DoorPlacement", BuilderKind::Meta, &[], &[]),
+    builder("SecretPassages", BuilderKind::Meta, &[], &[]),
+    builder("Pits", BuilderKind::Meta, &[], &[]),
+    builder("CaveDecorator", BuilderKind::Meta, &[], &[]),
+    builder("CaveTransition", BuilderKind::Meta, &[], &["divisor"]),
+    builder(
+        "Waterways",
+        BuilderKind::Meta,
+        &["rivers_and_lakes", "with_chasms"],
+        &[],
+    ),
+    builder("DragonsLair", BuilderKind::Meta, &[], &[]),
+    builder("DragonSpawner", BuilderKind::Meta, &[], &[]),
+    builder("YellowBrickRoad", BuilderKind::Meta, &[], &[]),
+];
+
+pub fn builder_info(name: &str) -> Option<&'static BuilderInfo> {
+    BUILDERS.iter().find(|b| b.name == name)
+}
+
+// The kind of prefab a builder's "prefab" parameter names.
+pub fn prefab_kind(builder: &str) -> Option<PrefabKind> {
+    match builder {
+        "PrefabLevel" => Some(PrefabKind::Level),
+        "PrefabSection" => Some(PrefabKind::Section),
+        "WaveformCollapse" => Some(PrefabKind::Level),
+        _ => None,
+    }
+}
+
+// Picks the chain for a depth of a branch from the raws; where several chains
+// cover the depth, by weight. The width and height are for levels without their own.
+pub fn level_builder(
+    branch: &str,
+    new_depth: i32,
+    width: usize,
+    height: usize,
+    rng: &mut RandomNumberGenerator,
+    raws: &RawMaster,
+) -> BuilderChain {
+    let candidates = raws.level_chains_for_depth(branch, new_depth);
+    let level = match candidates.len() {
+        0 => panic!(
+            "No level chain in the raws covers depth {} of {}",
+            new_depth, branch
+        ),
+        1 => candidates[0],
+        _ => *pick_weighted(&candidates, |l| l.weight.unwrap_or(1), rng).unwrap_or(&candidates[0]),
+    };
+    let chain = chain_from_raws(level, new_depth, width, height, rng, raws);
+    if PRINT_CHAIN_ITEMS {
+        chain.debug_print();
+    }
+    chain
+}
+
+pub fn chain_from_raws(
+    level: &LevelChain,
+    new_depth: i32,
+    width: usize,
+    height: usize,
+    rng: &mut RandomNumberGenerator,
+    raws: &RawMaster,
+) -> BuilderChain {
+    let mut chain = BuilderChain::new(
+        new_depth,
+        level.width.unwrap_or(width),
+        level.height.unwrap_or(height),
+        &level.map_name,
+    );
+    if let Some(theme) = &level.theme {
+        chain.build_data.map.theme = theme.clone();
+    }
+    if let Some(outdoors) = level.outdoors {
+        chain.build_data.map.outdoors = outdoors;
+    }
+    add_steps(&mut chain, &level.steps, rng, raws, 0);
+    chain
+}
+
+// A chain made from just a named level chain or fragment, for tools.
+pub fn named_builder(
+    name: &str,
+    new_depth: i32,
+    width: usize,
+    height: usize,
+    rng: &mut RandomNumberGenerator,
+    raws: &RawMaster,
+) -> Option<BuilderChain> {
+    if let Some(level) = raws.level_chain(name) {
+        return Some(chain_from_raws(level, new_depth, width, height, rng, raws));
+    }
+    raws.builder_fragment(name).map(|fragment| {
+        let mut chain = BuilderChain::new(new_depth, width, height, "New Map");
+        add_steps(&mut chain, &fragment.steps, rng, raws, 0);
+        chain
+    })
+}
+
+// Rolls 1..=total weight, so equally weighted options behave like a plain
+// dice roll with one face per option.
+fn pick_weighted<'a, T, F: Fn(&T) -> i32>(
+    options: &'a [T],
+    weight: F,
+    rng: &mut RandomNumberGenerator,
+) -> Option<&'a T> {
+    let total: i32 = options.iter().map(|o| weight(o).max(0)).sum();
+    if total <= 0 {
+        return None;
+    }
+    let mut roll = rng.roll_dice(1, total);
+    for option in options.iter() {
+        let w = weight(option).max(0);
+        if roll <= w {
+            return Some(option);
+        }
+        roll -= w;
+    }
+    None
+}
+
+fn add_steps(
+    chain: &mut BuilderChain,
+    steps: &[BuilderStep],
+    rng: &mut RandomNumberGenerator,
+    raws: &RawMaster,
+    nesting: usize,
+) {
+    if nesting > MAX_NESTING {
+        log("WARNING: builder fragments are nested too deeply; is one including itself?");
+        return;
+    }
+
+    for step in steps.iter() {
+        if let Some(choices) = &step.choose {
+            if let Some(choice) = pick_weighted(choices, |c| c.weight, rng) {
+                add_steps(chain, &choice.steps, rng, raws, nesting + 1);
+            }
+        } else if let Some(name) = &step.include {
+            match raws.builder_fragment(name) {
+                Some(fragment) => add_steps(chain, &fragment.steps, rng, raws, nesting + 1),
+                None => log(format!("WARNING: unknown builder fragment [{}]", name)),
+            }
+        } else if let Some(name) = &step.builder {
+            if let Err(e) = add_builder(chain, name, step, rng, raws) {
+                log(format!("WARNING: skipping builder [{}]: {}", name, e));
+            }
+        }
+    }
+}
+
+fn add_builder(
+    chain: &mut BuilderChain,
+    name: &str,
+    step: &BuilderStep,
+    rng: &mut RandomNumberGenerator,
+    raws: &RawMaster,
+) -> Result<(), String> {
+    let info = builder_info(name).ok_or("unknown builder")?;
+    let as_initial = match info.kind {
+        BuilderKind::Initial if chain.has_starter() => {
+            return Err("the chain already has an initial builder".to_string())
+        }
+        BuilderKind::Initial => true,
+        BuilderKind::Meta => false,
+        BuilderKind::Either => !chain.has_starter(),
+    };
+    let variant = step.variant.as_deref().unwrap_or("");
+    let unknown_variant = || format!("unknown variant [{}]", variant);
+
+    match name {
+        "Simple" => chain.initial(SimpleMapBuilder::new()),
+        "BSPDungeon" => chain.initial(BSPDungeonBuilder::new()),
+        "BSPInterior" => chain.initial(BSPInteriorBuilder::new()),
+        "CellularAutomata" => add_either(chain, as_initial, CellularAutomataBuilder::new()),
+        "DrunkardsWalk" => {
+            let builder = match variant {
+                "open_area" => DrunkardsWalkBuilder::open_area(),
+                "open_halls" => DrunkardsWalkBuilder::open_halls(),
+                "winding_passages" => DrunkardsWalkBuilder::winding_passages(),
+                "fat_passages" => DrunkardsWalkBuilder::fat_passages(),
+                "fearful_symmetry" => DrunkardsWalkBuilder::fearful_symmetry(),
+                _ => return Err(unknown_variant()),
+            };
+            add_either(chain, as_initial, builder)
+        }
+        "Maze" => chain.initial(MazeBuilder::new()),
+        "DLA" => {
+            let builder = match variant {
+                "walk_inwards" => DLABuilder::walk_inwards(),
+                "walk_outwards" => DLABuilder::walk_outwards(),
+                "central_attractor" => DLABuilder::central_attractor(),
+                "rorschach" => DLABuilder::rorschach(),
+                "heavy_erosion" => DLABuilder::heavy_erosion(),
+                _ => return Err(unknown_variant()),
+            };
+            add_either(chain, as_initial, builder)
+        }
+        "Voronoi" => chain.initial(match variant {
+            "pythagoras" => VoronoiCellBuilder::pythagoras(),
+            "manhattan" => VoronoiCellBuilder::manhattan(),
+            "chebyshev" => VoronoiCellBuilder::chebyshev(),
+            _ => return Err(unknown_variant()),
+        }),
+        "PrefabLevel" => {
+            let level = named_prefab(PrefabKind::Level, step, raws)?;
+            match level.tiled {
+                Some(tiled) => chain.initial(PrefabBuilder::tiled(tiled)),
+                None => chain.initial(PrefabBuilder::level(level)),
+            }
+        }
+        "Town" => chain.initial(TownBuilder::new()),
+        "Plaza" => chain.initial(PlazaMapBuilder::new()),
+        "RoomSorter" => chain.push(RoomSorter::new(match variant {
+            "Leftmost" => RoomSort::Leftmost,
+            "Rightmost" => RoomSort::Rightmost,
+            "Topmost" => RoomSort::Topmost,
+            "Bottommost" => RoomSort::Bottommost,
+            "Central" => RoomSort::Central,
+            _ => return Err(unknown_variant()),
+        })),
+        "RoomDrawer" => chain.push(RoomDrawer::new()),
+        "DoglegCorridors" => chain.push(DoglegCorridors::new()),
+        "BSPCorridors" => chain.push(BSPCorridors::new()),
+        "StraightLineCorridors" => chain.push(StraightLineCorridors::new()),
+        "NearestCorridors" => chain.push(NearestCorridors::new()),
+        "CorridorSpawner" => chain.push(CorridorSpawner::new()),
+        "RoomExploder" => chain.push(RoomExploder::new()),
+        "RoomCornerRounder" => chain.push(RoomCornerRounder::new()),
+        "RoomBasedStartingPosition" => chain.push(RoomBasedStartingPosition::new()),
+        "RoomBasedStairs" => chain.push(RoomBasedStairs::new()),
+        "RoomBasedSpawner" => chain.push(RoomBasedSpawner::new()),
+        "SpecialRooms" => chain.push(SpecialRooms::new(
+            raws.room_kinds_for_depth(chain.build_data.map.depth)
+                .into_iter()
+                .cloned()
+                .collect(),
+        )),
+        "AreaStartingPosition" => {
+            // In this order, so random positions roll as they always have.
+            let x = x_start(step.x.as_deref(), rng)?;
+            let y = y_start(step.y.as_deref(), rng)?;
+            chain.push(AreaStartingPosition::new(x, y))
+        }
+        "AreaEndingPosition" => {
+            let x = match step.x.as_deref() {
+                Some("Left") => XEnd::Left,
+                Some("Center") => XEnd::Center,
+                Some("Right") => XEnd::Right,
+                other => return Err(format!("unknown x position [{:?}]", other)),
+            };
+            let y = match step.y.as_deref() {
+                Some("Top") => YEnd::Top,
+                Some("Center") => YEnd::Center,
+                Some("Bottom") => YEnd::Bottom,
+                other => return Err(format!("unknown y position [{:?}]", other)),
+            };
+            chain.push(AreaEndingPosition::new(x, y))
+        }
+        "DistantExit" => chain.push(DistantExit::new()),
+        "CullUnreachable" => chain.push(CullUnreachable::new()),
+        "VoronoiSpawning" => chain.push(VoronoiSpawning::new()),
+        "WaveformCollapse" => {
+            let sample = match step.prefab {
+                Some(_) => Some(named_prefab(PrefabKind::Level, step, raws)?),
+                None => None,
+            };
+            let builder = WaveformCollapseBuilder::new(
+                step.chunk_size,
+                step.overlapping.unwrap_or(false),
+                sample,
+            );
+            add_either(chain, as_initial, builder)
+        }
+        "PrefabSection" => {
+            let section = named_prefab(PrefabKind::Section, step, raws)?;
+            chain.push(PrefabBuilder::sectional(section))
+        }
+        "Vaults" => chain.push(PrefabBuilder::vaults(
+            raws.prefabs_of(PrefabKind::Room)
+                .into_iter()
+                .cloned()
+                .collect(),
+        )),
+        "DoorPlacement" => chain.push(DoorPlacement::new()),
+        "SecretPassages" => chain.push(SecretPassages::new()),
+        "Pits" => chain.push(Pits::new()),
+        "CaveDecorator" => chain.push(CaveDecorator::new()),
+        "CaveTransition" => chain.push(CaveTransition::new(
+            step.divisor.ok_or("CaveTransition needs a divisor")?,
+        )),
+        "Waterways" => chain.push(match variant {
+            "rivers_and_lakes" => Waterways::rivers_and_lakes(),
+            "with_chasms" => Waterways::with_chasms(),
+            _ => return Err(unknown_variant()),
+        }),
+        "DragonsLair" => chain.push(DragonsLair::new()),
+        "DragonSpawner" => chain.push(DragonSpawner::new()),
+        "YellowBrickRoad" => chain.push(YellowBrickRoad::new()),
+        _ => return Err("no constructor for this builder".to_string()),
+    }
+    Ok(())
+}
+
+fn add_either<B: InitialMapBuilder + MetaMapBuilder + 'static>(
+    chain: &mut BuilderChain,
+    as_initial: bool,
+    builder: Box<B>,
+) {
+    if as_initial {
+        chain.initial(builder);
+    } else {
+        chain.push(builder);
+    }
+}
+
+fn named_prefab(kind: PrefabKind, step: &BuilderStep, raws: &RawMaster) -> Result<Prefab, String> {
+    let name = step.prefab.as_deref().unwrap_or("");
+    raws.prefab(name, kind)
+        .cloned()
+        .ok_or(format!("unknown prefab [{}]", name))
+}
+
+fn x_start(value: Option<&str>, rng: &mut RandomNumberGenerator) -> Result<XStart, String> {
+    match value {
+        Some("Left") => Ok(XStart::Left),
+        Some("Center") => Ok(XStart::Center),
+        Some("Right") => Ok(XStart::Right),
+        Some("Random") => Ok(XStart::random(rng)),
+        other => Err(format!("unknown x position [{:?}]", other)),
+    }
+}
+
+fn y_start(value: Option<&str>, rng: &mut RandomNumberGenerator) -> Result<YStart, String> {
+    match value {
+        Some("Top") => Ok(YStart::Top),
+        Some("Center") => Ok(YStart::Center),
+        Some("Bottom") => Ok(YStart::Bottom),
+        Some("Random") => Ok(YStart::random(rng)),
+        other => Err(format!("unknown y position [{:?}]", other)),
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use crate::prelude::*;
 
 const MAX_MONSTERS: i32 = 4;
@@ -13,37 +11,36 @@ pub enum Symmetry {
     Both,
 }
 
-fn room_table(map_depth: i32) -> MasterTable {
-    spawn_table_for_depth(&RAWS.lock().unwrap(), map_depth)
-}
-
 pub fn fill_spawns_for_room(
     map: &Map,
+    spawn_table: &MasterTable,
     rng: &mut RandomNumberGenerator,
     room: &Rect,
     spawns: &mut Vec<(Point, String)>,
 ) {
-    let possible_targets: Vec<Point> = room
+    // `point_set` is unordered; sort it so seeded games spawn identically.
+    let mut possible_targets: Vec<Point> = room
         .point_set()
         .iter()
-        .map(|p| *p)
+        .copied()
         .filter(|pos| {
             let idx = map.point2d_to_index(*pos);
             map.tiles[idx] == TileType::Floor
         })
         .collect();
+    possible_targets.sort_by_key(|p| (p.y, p.x));
 
-    fill_spawns_for_region(map, rng, &possible_targets, spawns);
+    fill_spawns_for_region(map, spawn_table, rng, &possible_targets, spawns);
 }
 
 pub fn fill_spawns_for_region(
     map: &Map,
+    spawn_table: &MasterTable,
     rng: &mut RandomNumberGenerator,
     area: &[Point],
     spawns: &mut Vec<(Point, String)>,
 ) {
-    let spawn_table = room_table(map.depth);
-    let mut spawn_points: HashMap<Point, String> = HashMap::new();
+    let mut spawn_points: Vec<(Point, String)> = Vec::new();
     let mut areas: Vec<Point> = Vec::from(area);
 
     // Scope to keep the borrow checker happy
@@ -59,14 +56,12 @@ pub fn fill_spawns_for_region(
         for _ in 0..num_spawns {
             let array_idx = rng.random_slice_index(areas.as_slice()).unwrap();
             let point = areas[array_idx];
-            spawn_points.insert(point, spawn_table.roll(rng));
+            spawn_points.push((point, spawn_table.roll(rng)));
             areas.remove(array_idx);
         }
     }
 
-    for spawn in spawn_points.iter() {
-        spawns.push((*spawn.0, spawn.1.to_string()));
-    }
+    spawns.extend(spawn_points);
 }
 
 pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) -> Vec<usize> {
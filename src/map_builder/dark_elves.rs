@@ -1,48 +1,5 @@
 use crate::prelude::*;
 
-use super::{
-    area_ending_points::{AreaEndingPosition, XEnd, YEnd},
-    area_starting_points::{AreaStartingPosition, XStart, YStart},
-    bsp_interior::BSPInteriorBuilder,
-    cull_unreachable::CullUnreachable,
-    voronoi_spawning::VoronoiSpawning,
-};
-
-pub fn dark_elf_city(
-    new_depth: i32,
-    _rng: &mut RandomNumberGenerator,
-    width: usize,
-    height: usize,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Dark Elven City");
-    chain.build_data.map.outdoors = false;
-
-    chain.initial(BSPInteriorBuilder::new());
-    chain.push(AreaStartingPosition::new(XStart::Center, YStart::Center));
-    chain.push(CullUnreachable::new());
-    chain.push(AreaStartingPosition::new(XStart::Right, YStart::Center));
-    chain.push(AreaEndingPosition::new(XEnd::Left, YEnd::Center));
-    chain.push(VoronoiSpawning::new());
-
-    chain
-}
-
-pub fn dark_elf_plaza(
-    new_depth: i32,
-    _rng: &mut RandomNumberGenerator,
-    width: usize,
-    height: usize,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Dark Elf Plaza");
-    chain.build_data.map.outdoors = false;
-
-    chain.initial(PlazaMapBuilder::new());
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Center));
-    chain.push(CullUnreachable::new());
-
-    chain
-}
-
 pub struct PlazaMapBuilder;
 
 impl InitialMapBuilder for PlazaMapBuilder {
@@ -200,7 +157,11 @@ impl PlazaMapBuilder {
         // Add a stone area in the middle...
         for y in center.y - 2..=center.y + 2 {
             for x in center.x - 2..=center.x + 2 {
+                // A center near the bottom edge would run off the map
                 let idx = build_data.map.point2d_to_index(Point::new(x, y));
+                if idx >= build_data.map.tiles.len() {
+                    continue;
+                }
                 build_data.map.tiles[idx] = TileType::Road;
                 if rng.roll_dice(1, 6) > 2 {
                     build_data.map.bloodstains.insert(idx);
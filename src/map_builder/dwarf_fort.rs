@@ -1,42 +1,6 @@
 use crate::prelude::*;
 
-use super::{
-    area_ending_points::{AreaEndingPosition, XEnd, YEnd},
-    area_starting_points::{AreaStartingPosition, XStart, YStart},
-    bsp::BSPDungeonBuilder,
-    cull_unreachable::CullUnreachable,
-    dla::DLABuilder,
-    room_corridor_spawner::CorridorSpawner,
-    room_corridors_bsp::BSPCorridors,
-    room_draw::RoomDrawer,
-    room_sorter::{RoomSort, RoomSorter},
-    voronoi_spawning::VoronoiSpawning,
-};
-
-pub fn dwarf_fort_builder(
-    new_depth: i32,
-    _rng: &mut RandomNumberGenerator,
-    width: usize,
-    height: usize,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Dwarven Fortress");
-    chain.build_data.map.outdoors = false;
-
-    chain.initial(BSPDungeonBuilder::new());
-    chain.push(RoomSorter::new(RoomSort::Central));
-    chain.push(RoomDrawer::new());
-    chain.push(BSPCorridors::new());
-    chain.push(CorridorSpawner::new());
-    chain.push(DragonsLair::new());
-
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Top));
-    chain.push(CullUnreachable::new());
-    chain.push(AreaEndingPosition::new(XEnd::Right, YEnd::Bottom));
-    chain.push(VoronoiSpawning::new());
-    chain.push(DragonSpawner::new());
-
-    chain
-}
+use super::dla::DLABuilder;
 
 pub struct DragonsLair {}
 
@@ -62,7 +26,7 @@ impl DragonsLair {
             "New Map",
         );
         builder.initial(DLABuilder::rorschach());
-        builder.build_map(rng);
+        builder.build_within(rng, build_data);
 
         // Add the history to our own.
         build_data
@@ -1,36 +1,7 @@
 use crate::prelude::*;
 
-use super::{
-    area_starting_points::{AreaStartingPosition, XStart, YStart},
-    automata::CellularAutomataBuilder,
-    cull_unreachable::CullUnreachable,
-    voronoi_spawning::VoronoiSpawning,
-};
-
 #[derive(Debug, Default)]
-struct YellowBrickRoad {}
-
-pub fn forest_builder(
-    new_depth: i32,
-    width: usize,
-    height: usize,
-    _rng: &mut RandomNumberGenerator,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "Into the Woods");
-    chain.build_data.map.theme = MapTheme::Forest;
-
-    chain.initial(CellularAutomataBuilder::new());
-    chain.push(AreaStartingPosition::new(XStart::Center, YStart::Center));
-    chain.push(CullUnreachable::new());
-    chain.push(AreaStartingPosition::new(XStart::Left, YStart::Center));
-
-    // Spawn mobs.
-    chain.push(VoronoiSpawning::new());
-
-    // Build a road through the forest
-    chain.push(YellowBrickRoad::new());
-    chain
-}
+pub struct YellowBrickRoad {}
 
 impl MetaMapBuilder for YellowBrickRoad {
     fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
@@ -1,46 +1,14 @@
 use crate::prelude::*;
 
-use self::{
-    area_starting_points::*,
-    automata::CellularAutomataBuilder,
-    bsp::BSPDungeonBuilder,
-    bsp_interior::BSPInteriorBuilder,
-    caverns::*,
-    cull_unreachable::CullUnreachable,
-    dark_elves::{dark_elf_city, dark_elf_plaza},
-    distant_exit::DistantExit,
-    dla::DLABuilder,
-    door_placement::DoorPlacement,
-    drunkard::DrunkardsWalkBuilder,
-    dwarf_fort::dwarf_fort_builder,
-    maze::MazeBuilder,
-    mushroom::{mushroom_builder, mushroom_entrance, mushroom_exit},
-    nearest_corridors::NearestCorridors,
-    prefab::PrefabBuilder,
-    room_based_spawner::RoomBasedSpawner,
-    room_based_stairs::RoomBasedStairs,
-    room_based_starting_position::RoomBasedStartingPosition,
-    room_corner_rounding::RoomCornerRounder,
-    room_corridor_spawner::CorridorSpawner,
-    room_corridors_bsp::BSPCorridors,
-    room_corridors_dogleg::DoglegCorridors,
-    room_corridors_lines::StraightLineCorridors,
-    room_draw::RoomDrawer,
-    room_exploder::RoomExploder,
-    room_sorter::{RoomSort, RoomSorter},
-    simple::SimpleMapBuilder,
-    voronoi::VoronoiCellBuilder,
-    voronoi_spawning::VoronoiSpawning,
-    waveform_collapse::WaveformCollapseBuilder,
-};
-
 mod area_ending_points;
 mod area_starting_points;
 mod automata;
+mod branch_stairs;
 mod bsp;
 mod bsp_interior;
 mod cave_decorator;
 mod caverns;
+mod chains;
 mod common;
 mod cull_unreachable;
 mod dark_elves;
@@ -51,8 +19,8 @@ mod drunkard;
 mod dwarf_fort;
 mod forest;
 mod maze;
-mod mushroom;
 mod nearest_corridors;
+mod pits;
 mod prefab;
 mod room_based_spawner;
 mod room_based_stairs;
@@ -65,31 +33,57 @@ mod room_corridors_lines;
 mod room_draw;
 mod room_exploder;
 mod room_sorter;
+mod secret_passages;
 mod simple;
+mod special_rooms;
 mod themes;
 mod town;
 mod voronoi;
 mod voronoi_spawning;
+mod waterways;
 mod waveform_collapse;
 
-use forest::forest_builder;
+use branch_stairs::connect_branches;
+pub use chains::*;
 pub use themes::*;
-use town::town_builder;
 
 const PRINT_CHAIN_ITEMS: bool = false;
 
+// The size of levels that don't set their own in the raws.
+pub const DEFAULT_MAP_WIDTH: usize = 80;
+pub const DEFAULT_MAP_HEIGHT: usize = 50;
+
+// Props that look like wall until found, when the tile under them opens up.
+pub const SECRET_PASSAGES: [&str; 2] = ["Secret Door", "Loose Rubble"];
+
 pub struct BuilderMap {
     pub spawn_list: Vec<(Point, String)>,
     pub map: Map,
     pub starting_position: Option<Point>,
     pub rooms: Option<Vec<Rect>>,
     pub corridors: Option<Vec<Vec<usize>>>,
-    pub history: Vec<Map>,
+    pub history: Vec<MapSnapshot>,
+    pub record_history: bool,
+    pub spawn_table: MasterTable,
+    // The builder that is running, for labelling snapshots.
+    pub builder_name: String,
+}
+
+// The map as a builder left it, with what the chain knew at the time.
+#[derive(Clone)]
+pub struct MapSnapshot {
+    pub map: Map,
+    pub builder: String,
+    pub rooms: Option<Vec<Rect>>,
+    pub corridors: Option<Vec<Vec<usize>>>,
+    pub starting_position: Option<Point>,
+    pub spawn_list: Vec<(Point, String)>,
 }
 
 pub struct BuilderChain {
     starter: Option<Box<dyn InitialMapBuilder>>,
     builders: Vec<Box<dyn MetaMapBuilder>>,
+    steps: Vec<String>,
     pub build_data: BuilderMap,
 }
 
@@ -103,7 +97,7 @@ pub trait MetaMapBuilder {
 
 impl BuilderMap {
     fn take_snapshot(&mut self) {
-        if SHOW_MAPGEN_VISUALIZER {
+        if self.record_history {
             let mut snapshot = self.map.clone();
             for v in snapshot.revealed_tiles.iter_mut() {
                 *v = true;
@@ -111,16 +105,24 @@ impl BuilderMap {
             for v in snapshot.visible_tiles.iter_mut() {
                 *v = true;
             }
-            self.history.push(snapshot);
+            self.history.push(MapSnapshot {
+                map: snapshot,
+                builder: self.builder_name.clone(),
+                rooms: self.rooms.clone(),
+                corridors: self.corridors.clone(),
+                starting_position: self.starting_position,
+                spawn_list: self.spawn_list.clone(),
+            });
         }
     }
 }
 
 impl BuilderChain {
-    fn new<S: ToString>(depth: i32, width: usize, height: usize, name: S) -> BuilderChain {
+    pub fn new<S: ToString>(depth: i32, width: usize, height: usize, name: S) -> BuilderChain {
         BuilderChain {
             starter: None,
             builders: Vec::new(),
+            steps: Vec::new(),
             build_data: BuilderMap {
                 spawn_list: Vec::new(),
                 map: Map::new(depth, width, height, name),
@@ -128,224 +130,123 @@ impl BuilderChain {
                 rooms: None,
                 corridors: None,
                 history: Vec::new(),
+                record_history: false,
+                spawn_table: MasterTable::new(),
+                builder_name: String::new(),
             },
         }
     }
 
-    fn initial(&mut self, starter: Box<dyn InitialMapBuilder>) {
+    pub fn initial<B: InitialMapBuilder + 'static>(&mut self, starter: Box<B>) {
         match self.starter {
-            None => self.starter = Some(starter),
+            None => {
+                self.steps.insert(0, type_of::<B>());
+                self.starter = Some(starter);
+            }
             Some(_) => panic!("You can only have one starting builder."),
         }
     }
 
-    pub fn push(&mut self, metabuilder: Box<dyn MetaMapBuilder>) {
+    pub fn push<B: MetaMapBuilder + 'static>(&mut self, metabuilder: Box<B>) {
+        self.steps.push(type_of::<B>());
         self.builders.push(metabuilder)
     }
 
-    pub fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+    pub fn has_starter(&self) -> bool {
+        self.starter.is_some()
+    }
+
+    // The builders in the chain, in the order they run.
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    pub fn describe(&self) -> String {
+        self.steps.join(" > ")
+    }
+
+    pub fn build_map(&mut self, rng: &mut RandomNumberGenerator, raws: &RawMaster) {
+        self.build_data.spawn_table = spawn_table_for_depth(raws, self.build_data.map.depth);
+        self.run_builders(rng, "");
+    }
+
+    // Chains nested inside another builder share its spawn table, and record
+    // history whenever it does; their snapshots are labelled as part of it.
+    fn build_within(&mut self, rng: &mut RandomNumberGenerator, parent: &BuilderMap) {
+        self.build_data.spawn_table = parent.spawn_table.clone();
+        self.build_data.record_history = parent.record_history;
+        let prefix = format!("{} > ", parent.builder_name);
+        self.run_builders(rng, &prefix);
+    }
+
+    fn run_builders(&mut self, rng: &mut RandomNumberGenerator, prefix: &str) {
+        // The starter is always the first step.
+        let mut steps = self.steps.iter().map(|step| format!("{}{}", prefix, step));
         match &mut self.starter {
             None => panic!("Cannot run a map builder chain without a starting builder"),
             Some(starter) => {
                 // Build the starting map.
+                self.build_data.builder_name = steps.next().unwrap_or_default();
                 starter.build_map(rng, &mut self.build_data);
             }
         }
 
         // Build additional layers in turn.
         for metabuilder in self.builders.iter_mut() {
+            self.build_data.builder_name = steps.next().unwrap_or_default();
             metabuilder.build_map(rng, &mut self.build_data);
         }
     }
 
-    pub fn spawn_entities(&mut self, ecs: &mut World, dm: &MasterDungeonMap) {
+    pub fn spawn_entities(
+        &mut self,
+        ecs: &mut World,
+        raws: &RawMaster,
+        dm: &MasterDungeonMap,
+        rng: &mut RandomNumberGenerator,
+    ) {
         for entity in self.build_data.spawn_list.iter() {
-            spawn_entity(ecs, dm, &(&entity.0, &entity.1));
+            spawn_entity(ecs, raws, dm, rng, &(&entity.0, &entity.1));
         }
     }
 
     pub fn debug_print(&self) {
         println!("Build chain:");
-        println!("  {}", type_of(&self.starter));
-        for builder in &self.builders {
-            println!("  {}", type_of(builder));
+        for step in self.steps.iter() {
+            println!("  {}", step);
         }
     }
 }
 
-fn type_of<T>(_: &T) -> String {
-    format!("{}", std::any::type_name::<T>())
+// The builder's type name, without its module path.
+fn type_of<T>() -> String {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name).to_string()
 }
 
-fn random_room_builder(rng: &mut RandomNumberGenerator, builder: &mut BuilderChain) {
-    let build_roll = rng.roll_dice(1, 3);
-    match build_roll {
-        1 => builder.initial(SimpleMapBuilder::new()),
-        2 => builder.initial(BSPDungeonBuilder::new()),
-        _ => builder.initial(BSPInteriorBuilder::new()),
-    }
-
-    // BSP interior still makes it own holes in the walls
-    if build_roll != 3 {
-        // Sort by one of the 5 available algorithms
-        let sort_order = match rng.roll_dice(1, 5) {
-            1 => RoomSort::Leftmost,
-            2 => RoomSort::Rightmost,
-            3 => RoomSort::Topmost,
-            4 => RoomSort::Bottommost,
-            _ => RoomSort::Central,
-        };
-        builder.push(RoomSorter::new(sort_order));
-        builder.push(RoomDrawer::new());
-
-        match rng.roll_dice(1, 4) {
-            1 => builder.push(DoglegCorridors::new()),
-            2 => builder.push(BSPCorridors::new()),
-            3 => builder.push(StraightLineCorridors::new()),
-            _ => builder.push(NearestCorridors::new()),
-        }
-
-        if rng.roll_dice(1, 2) == 1 {
-            builder.push(CorridorSpawner::new());
-        }
-
-        match rng.roll_dice(1, 8) {
-            1 => builder.push(RoomExploder::new()),
-            2 => builder.push(RoomCornerRounder::new()),
-            3 => builder.push(DLABuilder::heavy_erosion()),
-            _ => {}
-        }
-    }
-
-    match rng.roll_dice(1, 2) {
-        1 => builder.push(RoomBasedStartingPosition::new()),
-        _ => {
-            builder.push(AreaStartingPosition::new(
-                XStart::random(rng),
-                YStart::random(rng),
-            ));
-        }
-    }
-
-    match rng.roll_dice(1, 2) {
-        1 => builder.push(RoomBasedStairs::new()),
-        _ => builder.push(DistantExit::new()),
-    }
-
-    match rng.roll_dice(1, 2) {
-        1 => builder.push(RoomBasedSpawner::new()),
-        _ => builder.push(VoronoiSpawning::new()),
-    }
-}
-
-pub fn random_shape_builder(rng: &mut RandomNumberGenerator, builder: &mut BuilderChain) {
-    match rng.roll_dice(1, 15) {
-        1 => builder.initial(CellularAutomataBuilder::new()),
-        2 => builder.initial(DrunkardsWalkBuilder::open_area()),
-        3 => builder.initial(DrunkardsWalkBuilder::open_halls()),
-        4 => builder.initial(DrunkardsWalkBuilder::winding_passages()),
-        5 => builder.initial(DrunkardsWalkBuilder::fat_passages()),
-        6 => builder.initial(DrunkardsWalkBuilder::fearful_symmetry()),
-        7 => builder.initial(MazeBuilder::new()),
-        8 => builder.initial(DLABuilder::walk_inwards()),
-        9 => builder.initial(DLABuilder::walk_outwards()),
-        10 => builder.initial(DLABuilder::central_attractor()),
-        11 => builder.initial(DLABuilder::rorschach()),
-        12 => builder.initial(VoronoiCellBuilder::pythagoras()),
-        13 => builder.initial(VoronoiCellBuilder::manhattan()),
-        14 => builder.initial(VoronoiCellBuilder::chebyshev()),
-        _ => builder.initial(PrefabBuilder::constant(prefab::levels::WFC_POPULATED)),
-    }
-
-    cull_and_finalize(rng, builder);
-}
-
-fn cull_and_finalize(rng: &mut RandomNumberGenerator, builder: &mut BuilderChain) {
-    // Set the start to the center and cull
-    builder.push(AreaStartingPosition::new(XStart::Center, YStart::Center));
-    builder.push(CullUnreachable::new());
-
-    // Now pick a random starting area.
-    builder.push(AreaStartingPosition::new(
-        XStart::random(rng),
-        YStart::random(rng),
-    ));
-
-    // Setup exit and spawn mobs
-    builder.push(VoronoiSpawning::new());
-    builder.push(DistantExit::new());
-}
-
-pub fn random_builder(
-    new_depth: i32,
+// Builds a level, exactly as the game would on first arriving there (minus
+// spawning its entities).
+pub fn build_level(
+    level: LevelId,
     width: usize,
     height: usize,
     rng: &mut RandomNumberGenerator,
+    raws: &RawMaster,
+    dm: &MasterDungeonMap,
+    record_history: bool,
 ) -> BuilderChain {
-    let mut builder = BuilderChain::new(new_depth, width, height, "New Map");
-    match rng.roll_dice(1, 2) {
-        1 => random_room_builder(rng, &mut builder),
-        _ => random_shape_builder(rng, &mut builder),
-    }
-
-    if rng.roll_dice(1, 6) == 1 {
-        builder.push(WaveformCollapseBuilder::new());
-        // if rng.roll_dice(1, 5) == 1 {
-        //     builder.push(CellularAutomataBuilder::new());
-        // }
-        cull_and_finalize(rng, &mut builder);
-    }
-
-    if rng.roll_dice(1, 20) == 1 {
-        builder.push(PrefabBuilder::sectional(prefab::sections::UNDERGROUND_FORT));
-    }
-
-    builder.push(DoorPlacement::new());
-    builder.push(PrefabBuilder::vaults());
-
-    if PRINT_CHAIN_ITEMS {
-        builder.debug_print();
+    let branch = dm.branch_name(level.branch).to_string();
+    let mut builder = level_builder(&branch, level.depth, width, height, rng, raws);
+    builder.build_data.record_history |= record_history;
+    builder.build_map(rng, raws);
+    builder.build_data.map.branch = level.branch;
+
+    if let Some(pos) = builder.build_data.starting_position {
+        if level.depth != 0 {
+            let up_idx = builder.build_data.map.point2d_to_index(pos);
+            builder.build_data.map.tiles[up_idx] = TileType::UpStairs;
+        }
     }
-
+    connect_branches(&mut builder.build_data, &branch, raws, dm);
     builder
-
-    // let mut builder = BuilderChain::new(new_depth, width, height);
-    // builder.initial(SimpleMapBuilder::new());
-    // builder.push(RoomDrawer::new());
-    // builder.push(RoomSorter::new(RoomSort::Leftmost));
-    // builder.push(BSPCorridors::new());
-    // builder.push(RoomBasedSpawner::new());
-    // builder.push(CorridorSpawner::new());
-    // builder.push(RoomBasedStairs::new());
-    // builder.push(RoomBasedStartingPosition::new());
-    // builder
-
-    // builder.initial(BSPInteriorBuilder::new());
-    // builder.push(DoorPlacement::new());
-    // builder.push(RoomBasedSpawner::new());
-    // builder.push(RoomBasedStairs::new());
-    // builder.push(RoomBasedStartingPosition::new());
-    // builder
-}
-
-pub fn level_builder(
-    new_depth: i32,
-    width: usize,
-    height: usize,
-    rng: &mut RandomNumberGenerator,
-) -> BuilderChain {
-    match new_depth {
-        0 => town_builder(new_depth, width, height, rng),
-        1 => forest_builder(new_depth, width, height, rng),
-        2 => limestone_cavern_builder(new_depth, rng, width, height),
-        3 => limestone_deep_cavern_builder(new_depth, rng, width, height),
-        4 => limestone_transition_builder(new_depth, rng, width, height),
-        5 => dwarf_fort_builder(new_depth, rng, width, height),
-        6 => mushroom_entrance(new_depth, rng, width, height),
-        7 => mushroom_builder(new_depth, rng, width, height),
-        8 => mushroom_exit(new_depth, rng, width, height),
-        9 => dark_elf_city(new_depth, rng, width, height),
-        10 => dark_elf_plaza(new_depth, rng, width, height),
-        _ => random_builder(new_depth, width, height, rng),
-    }
 }
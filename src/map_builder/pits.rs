@@ -0,0 +1,71 @@
+use crate::prelude::*;
+
+// Keeps pits this far from where the player arrives.
+const MIN_START_DISTANCE: f32 = 6.0;
+
+// Opens a few pits that drop whoever steps in to the level below. Each is
+// surrounded by open ground, so there is always a way around.
+#[derive(Default)]
+pub struct Pits {}
+
+impl MetaMapBuilder for Pits {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl Pits {
+    #[allow(dead_code)]
+    pub fn new() -> Box<Pits> {
+        Box::new(Pits::default())
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let mut candidates: Vec<usize> = (0..build_data.map.tiles.len())
+            .filter(|idx| self.open_ground(build_data, *idx))
+            .collect();
+
+        let mut to_dig = rng.roll_dice(1, 3);
+        while to_dig > 0 && !candidates.is_empty() {
+            let idx = candidates.remove(rng.random_slice_index(&candidates).unwrap());
+            build_data.map.tiles[idx] = TileType::Pit;
+            let pos = build_data.map.index_to_point2d(idx);
+            candidates.retain(|other| {
+                DistanceAlg::Chebyshev.distance2d(pos, build_data.map.index_to_point2d(*other))
+                    > 1.0
+            });
+            build_data.take_snapshot();
+            to_dig -= 1;
+        }
+    }
+
+    // Plain floor away from the start, with nothing spawned on it and nothing
+    // but walkable ground (and no stairs) all around.
+    fn open_ground(&self, build_data: &BuilderMap, idx: usize) -> bool {
+        let map = &build_data.map;
+        let pos = map.index_to_point2d(idx);
+        if !matches!(map.tiles[idx], TileType::Floor | TileType::Gravel)
+            || pos.x < 2
+            || pos.y < 2
+            || pos.x > map.width as i32 - 3
+            || pos.y > map.height as i32 - 3
+            || build_data.spawn_list.iter().any(|(p, _)| *p == pos)
+        {
+            return false;
+        }
+        if build_data.starting_position.is_some_and(|start| {
+            DistanceAlg::Pythagoras.distance2d(start, pos) < MIN_START_DISTANCE
+        }) {
+            return false;
+        }
+
+        (-1..=1).all(|dy| {
+            (-1..=1).all(|dx| {
+                let next = map.point2d_to_index(pos + Point::new(dx, dy));
+                map.tiles[next].is_walkable()
+                    && map.tiles[next] != TileType::Pit
+                    && map.exit_from(next).is_none()
+            })
+        })
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashSet;
+
+use crate::prelude::*;
+use crate::tiled::TiledLevel;
+
+pub enum PrefabMode {
+    Level { level: Prefab },
+    Sectional { section: Prefab },
+    RoomVaults { vaults: Vec<Prefab> },
+    Tiled { level: TiledLevel },
+}
+
+pub struct PrefabBuilder {
+    mode: PrefabMode,
+}
+
+impl MetaMapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build_meta(rng, build_data);
+    }
+}
+
+impl InitialMapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build_initial(rng, build_data);
+    }
+}
+
+impl PrefabBuilder {
+    pub fn level(level: Prefab) -> Box<PrefabBuilder> {
+        Box::new(PrefabBuilder {
+            mode: PrefabMode::Level { level },
+        })
+    }
+
+    pub fn tiled(level: TiledLevel) -> Box<PrefabBuilder> {
+        Box::new(PrefabBuilder {
+            mode: PrefabMode::Tiled { level },
+        })
+    }
+
+    pub fn sectional(section: Prefab) -> Box<PrefabBuilder> {
+        Box::new(PrefabBuilder {
+            mode: PrefabMode::Sectional { section },
+        })
+    }
+
+    pub fn vaults(vaults: Vec<Prefab>) -> Box<PrefabBuilder> {
+        Box::new(PrefabBuilder {
+            mode: PrefabMode::RoomVaults { vaults },
+        })
+    }
+
+    fn build_initial(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        match &self.mode {
+            PrefabMode::Level { level } => PrefabBuilder::load_level(level, build_data),
+            PrefabMode::Tiled { level } => PrefabBuilder::load_tiled(level, build_data),
+            _ => panic!("Can't use a meta prefab as an initial builder"),
+        }
+    }
+
+    fn build_meta(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        match &self.mode {
+            PrefabMode::Sectional { section } => {
+                PrefabBuilder::apply_sectional(section, rng, build_data)
+            }
+            PrefabMode::RoomVaults { vaults } => {
+                PrefabBuilder::apply_room_vaults(vaults, rng, build_data)
+            }
+            _ => panic!("Can't use an initial prefab as a meta builder"),
+        }
+    }
+
+    fn load_level(level: &Prefab, build_data: &mut BuilderMap) {
+        for ty in 0..level.height() {
+            for tx in 0..level.width() {
+                if tx < build_data.map.width && ty < build_data.map.height {
+                    let idx = build_data.map.point2d_to_index(Point::new(tx, ty));
+                    PrefabBuilder::char_to_map(level, level.glyph_at(tx, ty), idx, build_data);
+                }
+            }
+        }
+    }
+
+    // The level's own name and depth stand; the map takes the size and the
+    // look it was polished with.
+    fn load_tiled(level: &TiledLevel, build_data: &mut BuilderMap) {
+        let old_map = &build_data.map;
+        let mut map = Map::new(old_map.depth, level.width, level.height, &old_map.name);
+        map.branch = old_map.branch;
+        map.theme = level.theme.clone().unwrap_or_else(|| old_map.theme.clone());
+        map.outdoors = old_map.outdoors;
+        map.tiles = level.tiles.clone();
+        build_data.map = map;
+
+        let fits = |pos: &Point| build_data.map.in_bounds(*pos);
+        build_data
+            .spawn_list
+            .extend(level.spawns.iter().filter(|(pos, _)| fits(pos)).cloned());
+        build_data.starting_position = level.start.filter(fits);
+        let rooms: Vec<Rect> = level
+            .rooms
+            .iter()
+            .filter(|room| {
+                fits(&Point::new(room.x1, room.y1)) && fits(&Point::new(room.x2, room.y2))
+            })
+            .copied()
+            .collect();
+        if !rooms.is_empty() {
+            build_data.rooms = Some(rooms);
+        }
+        if !level.corridors.is_empty() {
+            let tiles = build_data.map.tiles.len();
+            let corridors = level
+                .corridors
+                .iter()
+                .map(|corridor| {
+                    corridor
+                        .iter()
+                        .copied()
+                        .filter(|idx| *idx < tiles)
+                        .collect()
+                })
+                .collect();
+            build_data.corridors = Some(corridors);
+        }
+        build_data.take_snapshot();
+    }
+
+    fn apply_prior<F>(build_data: &mut BuilderMap, mut filter: F)
+    where
+        F: FnMut(Point) -> bool,
+    {
+        build_data.spawn_list.retain(|(pos, _)| filter(*pos));
+        build_data.take_snapshot();
+    }
+
+    fn apply_sectional(
+        section: &Prefab,
+        _rng: &mut RandomNumberGenerator,
+        build_data: &mut BuilderMap,
+    ) {
+        let (width, height) = (section.width(), section.height());
+        if width >= build_data.map.width || height >= build_data.map.height {
+            log(format!(
+                "WARNING: a {}x{} prefab section doesn't fit on a {}x{} map",
+                width, height, build_data.map.width, build_data.map.height
+            ));
+            return;
+        }
+
+        // Place the new section.
+        let chunk_x = match section.x.unwrap_or(HorizontalPlacement::Center) {
+            HorizontalPlacement::Left => 0,
+            HorizontalPlacement::Center => (build_data.map.width / 2) - (width / 2),
+            HorizontalPlacement::Right => (build_data.map.width - 1) - width,
+        };
+
+        let chunk_y = match section.y.unwrap_or(VerticalPlacement::Center) {
+            VerticalPlacement::Top => 0,
+            VerticalPlacement::Center => (build_data.map.height / 2) - (height / 2),
+            VerticalPlacement::Bottom => (build_data.map.height - 1) - height,
+        };
+
+        let region = Rect::with_size(chunk_x, chunk_y, width, height);
+        PrefabBuilder::apply_prior(build_data, |pos| !region.point_in_rect(pos));
+
+        for ty in 0..height {
+            for tx in 0..width {
+                if tx + chunk_x < build_data.map.width && ty + chunk_y < build_data.map.height {
+                    let idx = build_data
+                        .map
+                        .point2d_to_index(Point::new(tx + chunk_x, ty + chunk_y));
+                    PrefabBuilder::char_to_map(section, section.glyph_at(tx, ty), idx, build_data);
+                }
+            }
+        }
+        build_data.take_snapshot();
+    }
+
+    fn apply_room_vaults(
+        vaults: &[Prefab],
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuilderMap,
+    ) {
+        PrefabBuilder::apply_prior(build_data, |_| true);
+
+        // Do we want a vault at all?
+        let vault_roll = rng.roll_dice(1, 6) + build_data.map.depth;
+        if vault_roll < 4 {
+            return;
+        }
+
+        // Filter the list down to ones applicable to the current level
+        let mut possible_vaults: Vec<&Prefab> = vaults
+            .iter()
+            .filter(|v| {
+                v.covers_depth(build_data.map.depth)
+                    && v.suits_theme(&build_data.map.theme)
+                    && v.weight.unwrap_or(1) > 0
+            })
+            .collect();
+
+        if possible_vaults.is_empty() {
+            return;
+        }
+
+        let n_vaults = i32::min(rng.roll_dice(1, 3), possible_vaults.len() as i32);
+        let mut used_tiles: HashSet<usize> = HashSet::new();
+
+        for _ in 0..n_vaults {
+            let vault_idx = PrefabBuilder::pick_vault(&possible_vaults, rng);
+            let orientations = PrefabBuilder::orientations(possible_vaults[vault_idx]);
+
+            // Make a list of places in which the vault could fit, in any of
+            // the ways it may be turned.
+            let mut vault_positions: Vec<(Point, usize)> = Vec::new();
+            for (orientation, vault) in orientations.iter().enumerate() {
+                let (width, height) = (vault.width(), vault.height());
+                let mut idx = 0usize;
+                loop {
+                    let pos = build_data.map.index_to_point2d(idx);
+
+                    // Check that we won't overflow the map
+                    if pos.x > 1
+                        && pos.x as usize + width < build_data.map.width.saturating_sub(2)
+                        && pos.y > 1
+                        && pos.y as usize + height < build_data.map.height.saturating_sub(2)
+                    {
+                        let mut possible = true;
+                        for ty in 0..height {
+                            for tx in 0..width {
+                                let idx = build_data.map.point2d_to_index(pos + Point::new(tx, ty));
+                                if build_data.map.tiles[idx] != TileType::Floor
+                                    || used_tiles.contains(&idx)
+                                {
+                                    possible = false;
+                                    break;
+                                }
+                            }
+                            if !possible {
+                                break;
+                            }
+                        }
+
+                        if possible {
+                            vault_positions.push((pos, orientation));
+                        }
+                    }
+
+                    idx += 1;
+                    if idx >= build_data.map.tiles.len() - 1 {
+                        break;
+                    }
+                }
+            }
+
+            if !vault_positions.is_empty() {
+                let pos_idx = rng.random_slice_index(vault_positions.as_slice()).unwrap();
+                let (pos, orientation) = vault_positions[pos_idx];
+                let vault = &orientations[orientation];
+                let (width, height) = (vault.width(), vault.height());
+
+                // clear out any spawns from our chosen area
+                let region = Rect::with_size(pos.x, pos.y, width as i32, height as i32);
+                build_data
+                    .spawn_list
+                    .retain(|pos| !region.point_in_rect(pos.0));
+
+                for ty in 0..height {
+                    for tx in 0..width {
+                        let idx = build_data.map.point2d_to_index(pos + Point::new(tx, ty));
+                        PrefabBuilder::char_to_map(vault, vault.glyph_at(tx, ty), idx, build_data);
+                        used_tiles.insert(idx);
+                    }
+                }
+                build_data.take_snapshot();
+                possible_vaults.remove(vault_idx);
+            }
+        }
+    }
+
+    // The distinct ways a vault may be placed, as authored first.
+    fn orientations(vault: &Prefab) -> Vec<Prefab> {
+        let mut mirrors = vec![vault.clone()];
+        if vault.mirror {
+            mirrors.push(PrefabBuilder::mirrored(vault));
+        }
+
+        let mut orientations: Vec<Prefab> = Vec::new();
+        for mut oriented in mirrors {
+            for turn in 0..4 {
+                if turn > 0 {
+                    if !vault.rotate {
+                        break;
+                    }
+                    oriented = PrefabBuilder::turned(&oriented);
+                }
+                // Symmetric vaults look the same in several orientations.
+                if !orientations.iter().any(|o| o.template == oriented.template) {
+                    orientations.push(oriented.clone());
+                }
+            }
+        }
+        orientations
+    }
+
+    // A quarter turn clockwise.
+    fn turned(prefab: &Prefab) -> Prefab {
+        let (width, height) = (prefab.width(), prefab.height());
+        let template = (0..width)
+            .map(|y| {
+                (0..height)
+                    .map(|x| {
+                        let ch = prefab.glyph_at(y, height - 1 - x);
+                        prefab.legend.get(&ch).and_then(|g| g.rotated).unwrap_or(ch)
+                    })
+                    .collect()
+            })
+            .collect();
+        Prefab {
+            template,
+            ..prefab.clone()
+        }
+    }
+
+    // Flipped left to right.
+    fn mirrored(prefab: &Prefab) -> Prefab {
+        let width = prefab.width();
+        let template = (0..prefab.height())
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        let ch = prefab.glyph_at(width - 1 - x, y);
+                        prefab
+                            .legend
+                            .get(&ch)
+                            .and_then(|g| g.mirrored)
+                            .unwrap_or(ch)
+                    })
+                    .collect()
+            })
+            .collect();
+        Prefab {
+            template,
+            ..prefab.clone()
+        }
+    }
+
+    // By weight; with equal weights this rolls exactly as picking from a
+    // slice does.
+    fn pick_vault(vaults: &[&Prefab], rng: &mut RandomNumberGenerator) -> usize {
+        if vaults.len() == 1 {
+            return 0;
+        }
+        let total: i32 = vaults.iter().map(|v| v.weight.unwrap_or(1)).sum();
+        let mut roll = rng.roll_dice(1, total);
+        for (i, vault) in vaults.iter().enumerate() {
+            let weight = vault.weight.unwrap_or(1);
+            if roll <= weight {
+                return i;
+            }
+            roll -= weight;
+        }
+        0
+    }
+
+    fn char_to_map(prefab: &Prefab, ch: char, idx: usize, build_data: &mut BuilderMap) {
+        let glyph = match prefab.legend.get(&ch) {
+            Some(glyph) => glyph,
+            None => {
+                log(format!("Unknown glyph loading {}: {}", prefab.name, ch));
+                return;
+            }
+        };
+        let pos = build_data.map.index_to_point2d(idx);
+        build_data.map.tiles[idx] = glyph.tile;
+        if glyph.start {
+            build_data.starting_position = Some(pos);
+        }
+        if let Some(spawn) = &glyph.spawn {
+            build_data.spawn_list.push((pos, spawn.clone()));
+        }
+    }
+}
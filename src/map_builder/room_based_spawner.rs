@@ -19,7 +19,13 @@ impl RoomBasedSpawner {
     fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
         if let Some(rooms) = &build_data.rooms {
             for room in rooms.iter().skip(1) {
-                fill_spawns_for_room(&mut build_data.map, rng, &room, &mut build_data.spawn_list);
+                fill_spawns_for_room(
+                    &build_data.map,
+                    &build_data.spawn_table,
+                    rng,
+                    room,
+                    &mut build_data.spawn_list,
+                );
             }
         } else {
             panic!("Room Based Spawning only works after rooms have been created");
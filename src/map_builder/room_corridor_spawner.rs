@@ -23,7 +23,13 @@ impl CorridorSpawner {
                     .iter()
                     .map(|idx| build_data.map.index_to_point2d(*idx))
                     .collect();
-                fill_spawns_for_region(&build_data.map, rng, &points, &mut build_data.spawn_list);
+                fill_spawns_for_region(
+                    &build_data.map,
+                    &build_data.spawn_table,
+                    rng,
+                    &points,
+                    &mut build_data.spawn_list,
+                );
             }
         }
     }
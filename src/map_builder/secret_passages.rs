@@ -0,0 +1,96 @@
+use crate::prelude::*;
+
+// Hides a few of the level's connections behind what looks like wall: where
+// the chain laid corridors their ends become secret doors, elsewhere narrow
+// gaps are filled with loose rubble. Nothing is hidden that would cut the
+// start off from a way out.
+#[derive(Default)]
+pub struct SecretPassages {}
+
+impl MetaMapBuilder for SecretPassages {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl SecretPassages {
+    #[allow(dead_code)]
+    pub fn new() -> Box<SecretPassages> {
+        Box::new(SecretPassages::default())
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let start = match build_data.starting_position {
+            Some(start) => build_data.map.point2d_to_index(start),
+            None => return,
+        };
+        let (mut candidates, name): (Vec<usize>, &str) = match &build_data.corridors {
+            Some(corridors) => (
+                corridors
+                    .iter()
+                    .filter(|hall| hall.len() > 2)
+                    .map(|hall| hall[0])
+                    .collect(),
+                SECRET_PASSAGES[0],
+            ),
+            None => (
+                (0..build_data.map.tiles.len()).collect(),
+                SECRET_PASSAGES[1],
+            ),
+        };
+        candidates.retain(|idx| self.narrow_gap(build_data, *idx));
+
+        let exits = reachable_exits(&mut build_data.map, start);
+        let mut to_hide = rng.roll_dice(1, 3);
+        while to_hide > 0 && !candidates.is_empty() {
+            let idx = candidates.remove(rng.random_slice_index(&candidates).unwrap());
+            let tile = build_data.map.tiles[idx];
+            build_data.map.tiles[idx] = TileType::Wall;
+            if reachable_exits(&mut build_data.map, start) != exits {
+                build_data.map.tiles[idx] = tile;
+                continue;
+            }
+
+            let pos = build_data.map.index_to_point2d(idx);
+            build_data.spawn_list.push((pos, name.to_string()));
+            build_data.take_snapshot();
+            to_hide -= 1;
+        }
+    }
+
+    // Ground between two walls, open either side, with nothing already
+    // spawned on it.
+    fn narrow_gap(&self, build_data: &BuilderMap, idx: usize) -> bool {
+        let map = &build_data.map;
+        let pos = map.index_to_point2d(idx);
+        if !matches!(map.tiles[idx], TileType::Floor | TileType::Gravel)
+            || pos.x < 1
+            || pos.y < 1
+            || pos.x > map.width as i32 - 2
+            || pos.y > map.height as i32 - 2
+            || build_data.spawn_list.iter().any(|(p, _)| *p == pos)
+        {
+            return false;
+        }
+
+        let tile = |dx: i32, dy: i32| map.tiles[map.point2d_to_index(pos + Point::new(dx, dy))];
+        let walls = |a: TileType, b: TileType| a == TileType::Wall && b == TileType::Wall;
+        let open = |a: TileType, b: TileType| a.is_walkable() && b.is_walkable();
+        (walls(tile(0, -1), tile(0, 1)) && open(tile(-1, 0), tile(1, 0)))
+            || (walls(tile(-1, 0), tile(1, 0)) && open(tile(0, -1), tile(0, 1)))
+    }
+}
+
+// The stairs and other exits that can be walked to from the start.
+fn reachable_exits(map: &mut Map, start: usize) -> Vec<usize> {
+    map.populate_blocked();
+    let map = &*map;
+    let dijkstra = DijkstraMap::new(map.width, map.height, &[start], map, 10000.0);
+    (0..map.tiles.len())
+        .filter(|idx| {
+            matches!(map.tiles[*idx], TileType::DownStairs | TileType::UpStairs)
+                || map.exits.contains_key(idx)
+        })
+        .filter(|idx| dijkstra.map[*idx] < f32::MAX)
+        .collect()
+}
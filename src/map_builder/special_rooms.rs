@@ -0,0 +1,174 @@
+use super::{BuilderMap, MetaMapBuilder};
+use crate::prelude::*;
+
+// One room in this many is given a purpose.
+const SPECIAL_ROOM_CHANCE: i32 = 4;
+
+// Gives some of the level's rooms a purpose from the raws' room kinds: a
+// vault, a lair, a library and so on. Whatever the chain had already spawned
+// in those rooms is replaced with what the kind calls for.
+pub struct SpecialRooms {
+    kinds: Vec<RoomKind>,
+}
+
+impl MetaMapBuilder for SpecialRooms {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl SpecialRooms {
+    #[allow(dead_code)]
+    pub fn new(kinds: Vec<RoomKind>) -> Box<SpecialRooms> {
+        Box::new(SpecialRooms { kinds })
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = match &build_data.rooms {
+            Some(rooms) => rooms.clone(),
+            None => panic!("Special rooms only work after rooms have been created"),
+        };
+        if self.kinds.is_empty() {
+            return;
+        }
+
+        let mut table = RandomTable::new();
+        self.kinds
+            .iter()
+            .for_each(|kind| table.add(&kind.name, kind.weight));
+
+        for room in rooms.iter() {
+            if build_data
+                .starting_position
+                .is_some_and(|start| room.point_in_rect(start))
+            {
+                continue;
+            }
+            if rng.roll_dice(1, SPECIAL_ROOM_CHANCE) != 1 {
+                continue;
+            }
+            let name = table.roll(rng);
+            if let Some(kind) = self.kinds.iter().find(|k| k.name == name) {
+                self.furnish(rng, build_data, room, kind);
+                build_data.take_snapshot();
+            }
+        }
+    }
+
+    fn furnish(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuilderMap,
+        room: &Rect,
+        kind: &RoomKind,
+    ) {
+        build_data
+            .spawn_list
+            .retain(|(pos, _)| !room.point_in_rect(*pos));
+
+        // `point_set` is unordered; sort it so seeded games build identically.
+        let mut tiles: Vec<Point> = room.point_set().into_iter().collect();
+        tiles.sort_by_key(|p| (p.y, p.x));
+        tiles.retain(|p| build_data.map.in_bounds(*p));
+
+        let map = &mut build_data.map;
+        if let Some(floor) = kind.floor {
+            for pos in tiles.iter() {
+                let idx = map.point2d_to_index(*pos);
+                if map.tiles[idx] == TileType::Floor {
+                    map.tiles[idx] = floor;
+                }
+            }
+        }
+        if kind.dark {
+            for pos in tiles.iter() {
+                let idx = map.point2d_to_index(*pos);
+                map.dark_tiles.insert(idx);
+            }
+        }
+
+        // Only open ground that isn't a way off the level.
+        let mut free: Vec<Point> = tiles
+            .iter()
+            .filter(|pos| {
+                let idx = map.point2d_to_index(**pos);
+                map.tiles[idx].is_walkable()
+                    && !matches!(map.tiles[idx], TileType::DownStairs | TileType::UpStairs)
+                    && !map.exits.contains_key(&idx)
+            })
+            .copied()
+            .collect();
+
+        for decoration in kind.decorations.iter() {
+            let count = rng.roll_str(&decoration.count).unwrap_or(0);
+            for _ in 0..count {
+                let spots: Vec<usize> = (0..free.len())
+                    .filter(|i| !decoration.along_walls || against_wall(map, room, free[*i]))
+                    .collect();
+                if spots.is_empty() {
+                    break;
+                }
+                let pos = free.remove(spots[rng.random_slice_index(&spots).unwrap()]);
+                build_data.spawn_list.push((pos, decoration.name.clone()));
+            }
+        }
+
+        let count = match &kind.spawn_count {
+            Some(dice) => rng.roll_str(dice).unwrap_or(0),
+            None => 0,
+        };
+        let mut spawns = RandomTable::new();
+        kind.spawns
+            .iter()
+            .for_each(|spawn| spawns.add(&spawn.name, spawn.weight));
+        for _ in 0..count {
+            if free.is_empty() {
+                break;
+            }
+            let pos = free.remove(rng.random_slice_index(&free).unwrap());
+            let name = match kind.from_depth_table.as_deref() {
+                Some("mobs") if kind.spawns.is_empty() => {
+                    build_data.spawn_table.roll_of(SpawnTableType::Mob, rng)
+                }
+                Some("items") if kind.spawns.is_empty() => {
+                    build_data.spawn_table.roll_of(SpawnTableType::Item, rng)
+                }
+                _ => spawns.roll(rng),
+            };
+            if name != "None" {
+                build_data.spawn_list.push((pos, name));
+            }
+        }
+
+        build_data.map.special_rooms.push(SpecialRoom {
+            kind: kind.name.clone(),
+            message: kind.message.clone(),
+            area: *room,
+            entered: false,
+        });
+    }
+}
+
+// Floor beside one of the room's walls, and not beside a way out of it.
+fn against_wall(map: &Map, room: &Rect, pos: Point) -> bool {
+    let neighbours = [
+        Point::new(-1, 0),
+        Point::new(1, 0),
+        Point::new(0, -1),
+        Point::new(0, 1),
+    ];
+    let mut by_wall = false;
+    for delta in neighbours.iter() {
+        let next = pos + *delta;
+        if !map.in_bounds(next) {
+            continue;
+        }
+        let tile = map.tiles[map.point2d_to_index(next)];
+        if !tile.is_walkable() {
+            by_wall = true;
+        } else if !room.point_in_rect(next) {
+            return false;
+        }
+    }
+    by_wall
+}
@@ -57,6 +57,8 @@ impl MapTheme {
             TileType::UpStairs => (to_cp437('<'), RGB::named(CYAN)),
             TileType::Stalactite => (to_cp437('╨'), RGB::named(GREY50)),
             TileType::Stalagmite => (to_cp437('╥'), RGB::named(GREY50)),
+            TileType::Chasm => (to_cp437('▓'), RGB::from_f32(0.1, 0.1, 0.15)),
+            TileType::Pit => (to_cp437('○'), RGB::named(GREY30)),
         }
     }
 
@@ -89,6 +91,7 @@ impl MapTheme {
                 TileType::Gravel => (to_cp437(';'), RGB::named(LIGHTSLATEGREY)),
                 TileType::DownStairs => (to_cp437('>'), RGB::named(CYAN)),
                 TileType::UpStairs => (to_cp437('<'), RGB::named(CYAN)),
+                TileType::Chasm | TileType::Pit => self.default_glyph_for_tile(map, idx),
                 _ => (to_cp437('"'), RGB::from_f32(0.0, 0.6, 0.0)),
             },
             MapTheme::Transition { .. } => self
@@ -1,18 +1,7 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 use crate::prelude::*;
 
-pub fn town_builder(
-    new_depth: i32,
-    width: usize,
-    height: usize,
-    _rng: &mut RandomNumberGenerator,
-) -> BuilderChain {
-    let mut chain = BuilderChain::new(new_depth, width, height, "The Town of Brackleton");
-    chain.initial(TownBuilder::new());
-    chain
-}
-
 #[derive(Copy, Clone, Debug)]
 enum BuildingTag {
     Pub,
@@ -117,8 +106,8 @@ impl TownBuilder {
         &mut self,
         rng: &mut RandomNumberGenerator,
         build_data: &mut BuilderMap,
-    ) -> (HashSet<usize>, i32) {
-        let mut available_building_tiles: HashSet<usize> = HashSet::new();
+    ) -> (BTreeSet<usize>, i32) {
+        let mut available_building_tiles: BTreeSet<usize> = BTreeSet::new();
         let wall_gap_y = rng.roll_dice(1, build_data.map.height as i32 - 9) + 5;
 
         for y in 1..build_data.map.height as i32 - 2 {
@@ -163,7 +152,7 @@ impl TownBuilder {
         &mut self,
         rng: &mut RandomNumberGenerator,
         build_data: &mut BuilderMap,
-        available_building_tiles: &mut HashSet<usize>,
+        available_building_tiles: &mut BTreeSet<usize>,
     ) -> Vec<Rect> {
         let mut buildings: Vec<Rect> = Vec::new();
         let mut n_buildings = 0;
@@ -503,7 +492,7 @@ impl TownBuilder {
         &mut self,
         build_data: &mut BuilderMap,
         rng: &mut RandomNumberGenerator,
-        available_building_tiles: &mut HashSet<usize>,
+        available_building_tiles: &mut BTreeSet<usize>,
     ) {
         for idx in available_building_tiles.iter() {
             if rng.roll_dice(1, 20) == 1 {
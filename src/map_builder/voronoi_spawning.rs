@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use crate::prelude::*;
 
@@ -20,7 +20,7 @@ impl VoronoiSpawning {
     }
 
     fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
-        let mut noise_areas: HashMap<i32, Vec<Point>> = HashMap::new();
+        let mut noise_areas: BTreeMap<i32, Vec<Point>> = BTreeMap::new();
         let mut noise = FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
         noise.set_noise_type(NoiseType::Cellular);
         noise.set_frequency(0.08);
@@ -45,7 +45,13 @@ impl VoronoiSpawning {
 
         // Spawn the entries.
         for area in noise_areas.iter() {
-            fill_spawns_for_region(&build_data.map, rng, area.1, &mut build_data.spawn_list);
+            fill_spawns_for_region(
+                &build_data.map,
+                &build_data.spawn_table,
+                rng,
+                area.1,
+                &mut build_data.spawn_list,
+            );
         }
     }
 }
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::prelude::*;
+
+// Carves rivers across whatever is already there and pools water in the low
+// ground, optionally splitting the level with chasms too. Anywhere that gets
+// cut off from the rest of its area is joined back up with a bridge or a
+// ford.
+pub struct Waterways {
+    chasms: bool,
+}
+
+impl MetaMapBuilder for Waterways {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl Waterways {
+    pub fn rivers_and_lakes() -> Box<Waterways> {
+        Box::new(Waterways { chasms: false })
+    }
+
+    pub fn with_chasms() -> Box<Waterways> {
+        Box::new(Waterways { chasms: true })
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let tiles_before = build_data.map.tiles.clone();
+        let regions_before = walkable_regions(&build_data.map);
+
+        for _ in 0..rng.roll_dice(1, 2) {
+            let width = rng.roll_dice(1, 2) + 1;
+            carve_river(rng, build_data, TileType::DeepWater, width);
+            build_data.take_snapshot();
+        }
+        fill_lakes(rng, build_data);
+        add_banks(build_data, &tiles_before);
+        build_data.take_snapshot();
+
+        if self.chasms {
+            let width = rng.roll_dice(1, 2);
+            carve_river(rng, build_data, TileType::Chasm, width);
+            build_data.take_snapshot();
+        }
+
+        reconnect(rng, build_data, &regions_before);
+
+        // Nothing spawns in deep water or over a chasm.
+        let map = &build_data.map;
+        build_data
+            .spawn_list
+            .retain(|(pos, _)| map.tiles[map.point2d_to_index(*pos)].is_walkable());
+        build_data.take_snapshot();
+    }
+}
+
+// Leaves the border, stairs and the start alone.
+fn can_carve(build_data: &BuilderMap, pos: Point) -> bool {
+    let map = &build_data.map;
+    if pos.x < 1 || pos.y < 1 || pos.x > map.width as i32 - 2 || pos.y > map.height as i32 - 2 {
+        return false;
+    }
+    let idx = map.point2d_to_index(pos);
+    !matches!(map.tiles[idx], TileType::DownStairs | TileType::UpStairs)
+        && !map.exits.contains_key(&idx)
+        && build_data.starting_position != Some(pos)
+}
+
+// Wanders from one edge of the map to the opposite one. Each step is
+// orthogonal, so even a narrow river can't be crossed diagonally.
+fn carve_river(
+    rng: &mut RandomNumberGenerator,
+    build_data: &mut BuilderMap,
+    tile: TileType,
+    width: i32,
+) {
+    let (w, h) = (build_data.map.width as i32, build_data.map.height as i32);
+    let across = rng.roll_dice(1, 2) == 1;
+    let (length, breadth) = if across { (w, h) } else { (h, w) };
+    let mut along = 1;
+    let mut side = rng.range(breadth / 4, breadth * 3 / 4);
+
+    while along < length - 1 {
+        for a in 0..width {
+            for b in 0..width {
+                let pos = if across {
+                    Point::new(along + a, side + b)
+                } else {
+                    Point::new(side + b, along + a)
+                };
+                if can_carve(build_data, pos) {
+                    let idx = build_data.map.point2d_to_index(pos);
+                    build_data.map.tiles[idx] = tile;
+                }
+            }
+        }
+
+        // Mostly flow onwards, sometimes drift to one side.
+        match rng.roll_dice(1, 6) {
+            1 => side = i32::max(side - 1, 1),
+            2 => side = i32::min(side + 1, breadth - 1 - width),
+            _ => along += 1,
+        }
+    }
+}
+
+// Low ground, by a noise height map, floods.
+fn fill_lakes(rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+    let mut noise = FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+    noise.set_noise_type(NoiseType::Simplex);
+    noise.set_frequency(0.08);
+
+    for y in 1..build_data.map.height as i32 - 1 {
+        for x in 1..build_data.map.width as i32 - 1 {
+            let pos = Point::new(x, y);
+            let idx = build_data.map.point2d_to_index(pos);
+            if build_data.map.tiles[idx] == TileType::Floor
+                && noise.get_noise(x as f32, y as f32) < -0.5
+                && can_carve(build_data, pos)
+            {
+                build_data.map.tiles[idx] = TileType::DeepWater;
+            }
+        }
+    }
+}
+
+// Floor beside new deep water becomes shallows.
+fn add_banks(build_data: &mut BuilderMap, tiles_before: &[TileType]) {
+    let map = build_data.map.clone();
+    for (idx, tile) in build_data.map.tiles.iter_mut().enumerate() {
+        if *tile != TileType::Floor {
+            continue;
+        }
+        let pos = map.index_to_point2d(idx);
+        let by_water = neighbors(&map, pos)
+            .any(|n| map.tiles[n] == TileType::DeepWater && tiles_before[n] != TileType::DeepWater);
+        if by_water {
+            *tile = TileType::ShallowWater;
+        }
+    }
+}
+
+fn neighbors(map: &Map, pos: Point) -> impl Iterator<Item = usize> + '_ {
+    (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| pos + Point::new(dx, dy)))
+        .filter(move |n| *n != pos && map.in_bounds(*n))
+        .map(move |n| map.point2d_to_index(n))
+}
+
+// Labels each walkable tile with the area it belongs to, moving as the player
+// does (diagonals included).
+fn walkable_regions(map: &Map) -> Vec<Option<usize>> {
+    let mut regions: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut next = 0;
+    for start in 0..map.tiles.len() {
+        if regions[start].is_some() || !map.tiles[start].is_walkable() {
+            continue;
+        }
+        regions[start] = Some(next);
+        let mut open = VecDeque::from([start]);
+        while let Some(idx) = open.pop_front() {
+            for n in neighbors(map, map.index_to_point2d(idx)) {
+                if regions[n].is_none() && map.tiles[n].is_walkable() {
+                    regions[n] = Some(next);
+                    open.push_back(n);
+                }
+            }
+        }
+        next += 1;
+    }
+    regions
+}
+
+// Joins up areas that used to be one, crossing only what was carved out of
+// them: over a chasm by bridge, over water by bridge or ford.
+fn reconnect(
+    rng: &mut RandomNumberGenerator,
+    build_data: &mut BuilderMap,
+    regions_before: &[Option<usize>],
+) {
+    loop {
+        let regions = walkable_regions(&build_data.map);
+
+        // Find an area that has been split.
+        let mut first_part: HashMap<usize, usize> = HashMap::new();
+        let mut split = None;
+        for (before, after) in regions_before.iter().zip(regions.iter()) {
+            if let (Some(before), Some(after)) = (before, after) {
+                match first_part.get(before) {
+                    Some(part) if part != after => {
+                        split = Some(*before);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => {
+                        first_part.insert(*before, *after);
+                    }
+                }
+            }
+        }
+        let split = match split {
+            Some(split) => split,
+            None => return,
+        };
+
+        // Work outwards from its biggest part, to the nearest other one.
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for (before, after) in regions_before.iter().zip(regions.iter()) {
+            if let (Some(before), Some(after)) = (before, after) {
+                if *before == split {
+                    *sizes.entry(*after).or_insert(0) += 1;
+                }
+            }
+        }
+        let main = sizes
+            .iter()
+            .max_by_key(|(part, size)| (**size, std::cmp::Reverse(**part)))
+            .map(|(part, _)| *part)
+            .unwrap();
+
+        let map = &build_data.map;
+        let mut came_from: Vec<Option<usize>> = vec![None; map.tiles.len()];
+        let mut open: VecDeque<usize> = (0..map.tiles.len())
+            .filter(|idx| regions[*idx] == Some(main))
+            .collect();
+        let mut reached = None;
+        'search: while let Some(idx) = open.pop_front() {
+            for n in neighbors(map, map.index_to_point2d(idx)) {
+                if came_from[n].is_some() || regions[n] == Some(main) {
+                    continue;
+                }
+                if regions_before[n] != Some(split) {
+                    continue;
+                }
+                came_from[n] = Some(idx);
+                if regions[n].is_some() {
+                    reached = Some(n);
+                    break 'search;
+                }
+                open.push_back(n);
+            }
+        }
+
+        let mut crossing = Vec::new();
+        let mut step = reached.and_then(|r| came_from[r]);
+        while let Some(idx) = step {
+            if regions[idx] == Some(main) {
+                break;
+            }
+            crossing.push(idx);
+            step = came_from[idx];
+        }
+        if crossing.is_empty() {
+            log("WARNING: unable to reconnect an area cut off by water");
+            return;
+        }
+
+        let over_chasm = crossing
+            .iter()
+            .any(|idx| build_data.map.tiles[*idx] == TileType::Chasm);
+        let tile = if !over_chasm && rng.roll_dice(1, 3) == 1 {
+            TileType::ShallowWater
+        } else {
+            TileType::Bridge
+        };
+        for idx in crossing {
+            build_data.map.tiles[idx] = tile;
+        }
+    }
+}
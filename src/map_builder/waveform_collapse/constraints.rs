@@ -1,13 +1,8 @@
 use super::{tile_idx_in_chunk, MapChunk};
 use crate::prelude::*;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
-pub fn build_patterns(
-    map: &Map,
-    chunk_size: i32,
-    include_flipping: bool,
-    dedupe: bool,
-) -> Vec<Vec<TileType>> {
+pub fn build_patterns(map: &Map, chunk_size: i32, include_flipping: bool) -> Vec<Vec<TileType>> {
     let chunks_x = (map.width as i32 - 2) / chunk_size;
     let chunks_y = (map.height as i32 - 2) / chunk_size;
     let mut patterns = Vec::new();
@@ -64,19 +59,66 @@ pub fn build_patterns(
         }
     }
 
-    if dedupe {
-        log(format!(
-            "Pre de-duplication, there are {} patterns",
-            patterns.len()
-        ));
-        let set: HashSet<Vec<TileType>> = patterns.drain(..).collect(); // de-duplicate
-        patterns.extend(set.into_iter());
-        log(format!("There are {} unique patterns", patterns.len()));
-    }
+    patterns
+}
 
+// Every size x size window of the map, overlapping each other, for the
+// overlapping model.
+pub fn build_overlapping_patterns(
+    map: &Map,
+    size: i32,
+    include_flipping: bool,
+) -> Vec<Vec<TileType>> {
+    let mut patterns = Vec::new();
+    for start_y in 0..=(map.height as i32 - size) {
+        for start_x in 0..=(map.width as i32 - size) {
+            let window = |flip_x: bool, flip_y: bool| {
+                let mut pattern = Vec::new();
+                for y in 0..size {
+                    for x in 0..size {
+                        let x = if flip_x { size - (x + 1) } else { x };
+                        let y = if flip_y { size - (y + 1) } else { y };
+                        let idx = map.point2d_to_index(Point::new(start_x + x, start_y + y));
+                        pattern.push(map.tiles[idx]);
+                    }
+                }
+                pattern
+            };
+
+            patterns.push(window(false, false));
+            if include_flipping {
+                patterns.push(window(true, false));
+                patterns.push(window(false, true));
+                patterns.push(window(true, true));
+            }
+        }
+    }
     patterns
 }
 
+// De-duplicates patterns, counting how often each was seen so common ones
+// can be picked more often. Keeps the original order so seeded builds are
+// stable.
+pub fn count_patterns(patterns: Vec<Vec<TileType>>) -> Vec<(Vec<TileType>, i32)> {
+    log(format!(
+        "Pre de-duplication, there are {} patterns",
+        patterns.len()
+    ));
+    let mut counted: Vec<(Vec<TileType>, i32)> = Vec::new();
+    let mut seen: HashMap<Vec<TileType>, usize> = HashMap::new();
+    for p in patterns {
+        match seen.get(&p) {
+            Some(i) => counted[*i].1 += 1,
+            None => {
+                seen.insert(p.clone(), counted.len());
+                counted.push((p, 1));
+            }
+        }
+    }
+    log(format!("There are {} unique patterns", counted.len()));
+    counted
+}
+
 pub fn render_pattern_to_map(map: &mut Map, chunk: &MapChunk, chunk_size: i32, start: Point) {
     let mut i = 0usize;
     for tile_y in 0..chunk_size {
@@ -113,11 +155,12 @@ pub fn render_pattern_to_map(map: &mut Map, chunk: &MapChunk, chunk_size: i32, s
     }
 }
 
-pub fn patterns_to_constraints(patterns: Vec<Vec<TileType>>, chunk_size: i32) -> Vec<MapChunk> {
+fn patterns_to_chunks(patterns: Vec<(Vec<TileType>, i32)>, chunk_size: i32) -> Vec<MapChunk> {
     let mut constraints: Vec<MapChunk> = Vec::new();
-    for p in patterns {
+    for (p, weight) in patterns {
         let mut new_chunk = MapChunk {
             pattern: p,
+            weight,
             exits: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
             has_exits: true,
             compatible_with: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
@@ -165,6 +208,14 @@ pub fn patterns_to_constraints(patterns: Vec<Vec<TileType>>, chunk_size: i32) ->
 
         constraints.push(new_chunk);
     }
+    constraints
+}
+
+pub fn patterns_to_constraints(
+    patterns: Vec<(Vec<TileType>, i32)>,
+    chunk_size: i32,
+) -> Vec<MapChunk> {
+    let mut constraints = patterns_to_chunks(patterns, chunk_size);
 
     // Build compatibility matrix
     let ch = constraints.clone();
@@ -202,7 +253,7 @@ pub fn patterns_to_constraints(patterns: Vec<Vec<TileType>>, chunk_size: i32) ->
                         // No exits on this side, let's match only if the other edge also has
                         // no exits.
                         let matching_exit_count =
-                            potential.exits[opposite].iter().filter(|a| !**a).count();
+                            potential.exits[opposite].iter().filter(|a| **a).count();
                         if matching_exit_count == 0 {
                             c.compatible_with[direction].push(j);
                         }
@@ -214,3 +265,39 @@ pub fn patterns_to_constraints(patterns: Vec<Vec<TileType>>, chunk_size: i32) ->
 
     constraints
 }
+
+// In the overlapping model neighbouring patterns sit one tile apart, so they
+// fit if they agree wherever they overlap.
+pub fn overlapping_constraints(patterns: Vec<(Vec<TileType>, i32)>, size: i32) -> Vec<MapChunk> {
+    let mut constraints = patterns_to_chunks(patterns, size);
+    let agrees = |a: &MapChunk, b: &MapChunk, dx: i32, dy: i32| {
+        for y in 0..size {
+            for x in 0..size {
+                let (bx, by) = (x - dx, y - dy);
+                if bx < 0 || bx >= size || by < 0 || by >= size {
+                    continue;
+                }
+                if a.pattern[tile_idx_in_chunk(size, x, y)]
+                    != b.pattern[tile_idx_in_chunk(size, bx, by)]
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    };
+
+    let ch = constraints.clone();
+    for c in constraints.iter_mut() {
+        for (j, potential) in ch.iter().enumerate() {
+            // North, south, west, east
+            for (direction, (dx, dy)) in [(0, -1), (0, 1), (-1, 0), (1, 0)].iter().enumerate() {
+                if agrees(c, potential, *dx, *dy) {
+                    c.compatible_with[direction].push(j);
+                }
+            }
+        }
+    }
+
+    constraints
+}
@@ -9,6 +9,7 @@ use solver::*;
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub struct MapChunk {
     pub pattern: Vec<TileType>,
+    pub weight: i32,
     pub exits: [Vec<bool>; 4],
     pub has_exits: bool,
     pub compatible_with: [Vec<usize>; 4],
@@ -18,8 +19,22 @@ fn tile_idx_in_chunk(chunk_size: i32, x: i32, y: i32) -> usize {
     ((y * chunk_size) + x) as usize
 }
 
-#[derive(Default)]
-pub struct WaveformCollapseBuilder {}
+pub const DEFAULT_CHUNK_SIZE: i32 = 8;
+pub const DEFAULT_OVERLAP_SIZE: i32 = 3;
+
+// Fresh solves to try before giving up and leaving the map as it was.
+const MAX_ATTEMPTS: usize = 5;
+
+// Learns which patterns may sit next to each other from a sample, then fills
+// the map with them. The sample is the map so far, or a prefab level (which
+// may come from an .xp image). Tiled mode lays whole chunks side by side;
+// overlapping mode works a tile at a time from small overlapping windows,
+// which follows the sample far more closely.
+pub struct WaveformCollapseBuilder {
+    chunk_size: i32,
+    overlapping: bool,
+    sample: Option<Prefab>,
+}
 
 impl MetaMapBuilder for WaveformCollapseBuilder {
     fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
@@ -27,40 +42,121 @@ impl MetaMapBuilder for WaveformCollapseBuilder {
     }
 }
 
+impl InitialMapBuilder for WaveformCollapseBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        if self.sample.is_none() {
+            log("WARNING: wave function collapse needs a sample to start a map from");
+        }
+        self.build(rng, build_data);
+    }
+}
+
 impl WaveformCollapseBuilder {
-    #[allow(dead_code)]
-    pub fn new() -> Box<WaveformCollapseBuilder> {
-        Box::new(WaveformCollapseBuilder::default())
+    pub fn new(
+        chunk_size: Option<i32>,
+        overlapping: bool,
+        sample: Option<Prefab>,
+    ) -> Box<WaveformCollapseBuilder> {
+        let default_size = if overlapping {
+            DEFAULT_OVERLAP_SIZE
+        } else {
+            DEFAULT_CHUNK_SIZE
+        };
+        Box::new(WaveformCollapseBuilder {
+            chunk_size: chunk_size.unwrap_or(default_size),
+            overlapping,
+            sample,
+        })
     }
 
     fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
-        const CHUNK_SIZE: i32 = 8;
+        let chunk_size = self.chunk_size;
+
+        // Tiled patterns are sampled from whole chunks inside the outer ones.
+        let min_size = if self.overlapping {
+            chunk_size as usize + 3
+        } else {
+            chunk_size as usize * 2 + 2
+        };
+        if chunk_size < 1 || build_data.map.width < min_size || build_data.map.height < min_size {
+            log("WARNING: the map is too small for wave function collapse");
+            return;
+        }
         build_data.take_snapshot();
 
+        let mut sample = match &self.sample {
+            Some(prefab) => sample_map(prefab, &build_data.map),
+            None => build_data.map.clone(),
+        };
         // remove any stairs (they will need to be replaced)
-        for t in build_data.map.tiles.iter_mut() {
+        for t in sample.tiles.iter_mut() {
             if *t == TileType::DownStairs {
                 *t = TileType::Floor;
             }
         }
 
-        let patterns = build_patterns(&build_data.map, CHUNK_SIZE, true, true);
-        let constraints = patterns_to_constraints(patterns, CHUNK_SIZE);
-        self.render_tile_gallery(&constraints, CHUNK_SIZE, build_data);
-
-        build_data.map = Map::clone_empty(&build_data.map);
-        build_data.map.fill(TileType::Wall);
-        loop {
-            let mut solver = Solver::new(constraints.clone(), CHUNK_SIZE, &build_data.map);
-            while !solver.iteration(&mut build_data.map, rng) {
-                build_data.take_snapshot();
+        let constraints = if self.overlapping {
+            let patterns = build_overlapping_patterns(&sample, chunk_size, true);
+            overlapping_constraints(count_patterns(patterns), chunk_size)
+        } else {
+            let patterns = build_patterns(&sample, chunk_size, true);
+            patterns_to_constraints(count_patterns(patterns), chunk_size)
+        };
+        if constraints.is_empty() {
+            log("WARNING: the wave function collapse sample is too small");
+            return;
+        }
+        let original = if self.sample.is_some() {
+            build_data.map.clone()
+        } else {
+            sample
+        };
+        self.render_tile_gallery(&constraints, chunk_size, build_data);
+
+        // A tiled solve snapshots every chunk, an overlapping one about every
+        // row.
+        let mut solved = false;
+        for _ in 0..MAX_ATTEMPTS {
+            let mut solver = Solver::new(
+                constraints.clone(),
+                chunk_size,
+                self.overlapping,
+                &build_data.map,
+            );
+            let snapshot_every = if self.overlapping {
+                build_data.map.width
+            } else {
+                1
+            };
+            let mut steps = 0;
+            while !solver.iteration(rng) {
+                steps += 1;
+                if build_data.record_history && steps % snapshot_every == 0 {
+                    solver.draw(&mut build_data.map);
+                    build_data.take_snapshot();
+                }
             }
+            solver.draw(&mut build_data.map);
             build_data.take_snapshot();
-            if solver.possible {
+
+            let result = solver.result();
+            if result.solved {
+                solved = true;
                 break;
             }
+            log(format!(
+                "WARNING: wave function collapse failed after {} backtracks, around {:?}",
+                result.backtracks, result.failed_region
+            ));
+        }
+
+        if solved {
+            build_data.spawn_list.clear();
+        } else {
+            log("WARNING: giving up on wave function collapse; keeping the map as it was");
+            build_data.map = original;
+            build_data.take_snapshot();
         }
-        build_data.spawn_list.clear();
     }
 
     fn render_tile_gallery(
@@ -97,3 +193,18 @@ impl WaveformCollapseBuilder {
         build_data.take_snapshot()
     }
 }
+
+// The prefab's tiles, on a map of its own size; unknown glyphs become wall.
+fn sample_map(prefab: &Prefab, map: &Map) -> Map {
+    let mut sample = Map::new(map.depth, prefab.width(), prefab.height(), &prefab.name);
+    for y in 0..prefab.height() {
+        for x in 0..prefab.width() {
+            let idx = sample.point2d_to_index(Point::new(x, y));
+            sample.tiles[idx] = prefab
+                .legend
+                .get(&prefab.glyph_at(x, y))
+                .map_or(TileType::Wall, |glyph| glyph.tile);
+        }
+    }
+    sample
+}
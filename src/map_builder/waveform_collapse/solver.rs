@@ -1,227 +1,326 @@
-use super::MapChunk;
+use super::{tile_idx_in_chunk, MapChunk};
 use crate::prelude::*;
-use std::collections::HashSet;
 
+// Gives up on a solve that keeps running into dead ends.
+const MAX_BACKTRACKS: usize = 500;
+
+// North, south, west, east; the same order as a chunk's exits.
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+fn opposite(direction: usize) -> usize {
+    match direction {
+        0 => 1,
+        1 => 0,
+        2 => 3,
+        _ => 2,
+    }
+}
+
+// How a solve went. The region is where the last contradiction was found, in
+// map coordinates.
+#[derive(Clone, Debug, Default)]
+pub struct SolveResult {
+    pub solved: bool,
+    pub backtracks: usize,
+    pub failed_region: Option<Rect>,
+}
+
+// Each cell of the output holds the set of patterns it could still be. A
+// pattern stays possible while every neighbour still has an option that
+// allows it ("support"); removals are kept on a trail so that a choice which
+// leads to a contradiction can be undone, and the next option tried.
+//
+// Tiled cells are whole chunks side by side; overlapping cells are one tile
+// apart, and their patterns overlap.
 pub struct Solver {
     constraints: Vec<MapChunk>,
     chunk_size: i32,
-    chunks: Vec<Option<usize>>,
-    chunks_x: usize,
-    chunks_y: usize,
-    remaining: Vec<(usize, i32)>, // (index, # neighbors)
-    pub possible: bool,
+    overlapping: bool,
+    cells_x: usize,
+    cells_y: usize,
+    wave: Vec<bool>,
+    options: Vec<usize>,
+    support: Vec<i32>,
+    trail: Vec<(usize, usize)>,
+    propagated: usize,
+    decisions: Vec<(usize, usize, usize)>, // (trail length, cell, pattern)
+    contradiction: Option<usize>,
+    failed_at: Option<usize>,
+    finished: bool,
+    backtracks: usize,
 }
 
 impl Solver {
-    pub fn new(constraints: Vec<MapChunk>, chunk_size: i32, map: &Map) -> Solver {
-        let chunks_x = map.width / chunk_size as usize;
-        let chunks_y = map.height / chunk_size as usize;
-        let mut remaining: Vec<(usize, i32)> = Vec::new();
-        for i in 0..(chunks_x * chunks_y) {
-            remaining.push((i, 0));
+    pub fn new(
+        constraints: Vec<MapChunk>,
+        chunk_size: i32,
+        overlapping: bool,
+        map: &Map,
+    ) -> Solver {
+        let (cells_x, cells_y) = if overlapping {
+            // Patterns cover the map inside its border.
+            (
+                (map.width as i32 - 1 - chunk_size).max(0) as usize,
+                (map.height as i32 - 1 - chunk_size).max(0) as usize,
+            )
+        } else {
+            (
+                map.width / chunk_size as usize,
+                map.height / chunk_size as usize,
+            )
+        };
+        let n_cells = cells_x * cells_y;
+        let n_patterns = constraints.len();
+
+        // How many patterns allow each pattern on each side of them.
+        let mut supporters = vec![0; 4 * n_patterns];
+        for c in constraints.iter() {
+            for (direction, compatible) in c.compatible_with.iter().enumerate() {
+                for j in compatible.iter() {
+                    supporters[opposite(direction) * n_patterns + j] += 1;
+                }
+            }
+        }
+        let mut support = Vec::with_capacity(n_cells * 4 * n_patterns);
+        for _ in 0..n_cells {
+            support.extend_from_slice(&supporters);
         }
 
-        Solver {
+        let mut solver = Solver {
             constraints,
             chunk_size,
-            chunks: vec![None; chunks_x * chunks_y],
-            chunks_x,
-            chunks_y,
-            remaining,
-            possible: true,
+            overlapping,
+            cells_x,
+            cells_y,
+            wave: vec![true; n_cells * n_patterns],
+            options: vec![n_patterns; n_cells],
+            support,
+            trail: Vec::new(),
+            propagated: 0,
+            decisions: Vec::new(),
+            contradiction: None,
+            failed_at: None,
+            finished: n_cells == 0,
+            backtracks: 0,
+        };
+
+        // Rule out patterns that nothing can sit next to.
+        for cell in 0..n_cells {
+            for direction in 0..4 {
+                if solver.neighbor(cell, direction).is_none() {
+                    continue;
+                }
+                for pattern in 0..n_patterns {
+                    if solver.support[solver.support_idx(cell, direction, pattern)] == 0
+                        && solver.wave[cell * n_patterns + pattern]
+                    {
+                        solver.ban(cell, pattern);
+                    }
+                }
+            }
+        }
+        solver.propagate();
+        if let Some(cell) = solver.contradiction {
+            solver.failed_at = Some(cell);
+            solver.finished = true;
         }
+        solver
     }
 
-    fn chunk_idx(&self, x: usize, y: usize) -> usize {
-        (y * self.chunks_x) + x
+    fn neighbor(&self, cell: usize, direction: usize) -> Option<usize> {
+        let (dx, dy) = DIRECTIONS[direction];
+        let x = (cell % self.cells_x) as i32 + dx;
+        let y = (cell / self.cells_x) as i32 + dy;
+        if x < 0 || x >= self.cells_x as i32 || y < 0 || y >= self.cells_y as i32 {
+            None
+        } else {
+            Some(y as usize * self.cells_x + x as usize)
+        }
     }
 
-    fn count_neighbors(&self, chunk_x: usize, chunk_y: usize) -> i32 {
-        let mut neighbors = 0;
+    fn support_idx(&self, cell: usize, direction: usize, pattern: usize) -> usize {
+        (cell * 4 + direction) * self.constraints.len() + pattern
+    }
 
-        if chunk_x > 0 {
-            let left_idx = self.chunk_idx(chunk_x - 1, chunk_y);
-            if self.chunks[left_idx].is_some() {
-                neighbors += 1;
-            }
+    fn ban(&mut self, cell: usize, pattern: usize) {
+        self.wave[cell * self.constraints.len() + pattern] = false;
+        self.options[cell] -= 1;
+        self.trail.push((cell, pattern));
+        if self.options[cell] == 0 && self.contradiction.is_none() {
+            self.contradiction = Some(cell);
         }
+    }
 
-        if chunk_x < self.chunks_x - 1 {
-            let right_idx = self.chunk_idx(chunk_x + 1, chunk_y);
-            if self.chunks[right_idx].is_some() {
-                neighbors += 1;
+    // Removals are propagated in the order they were made, so everything on
+    // the trail before `propagated` has had its effect on its neighbours.
+    fn propagate(&mut self) {
+        while self.propagated < self.trail.len() && self.contradiction.is_none() {
+            let (cell, pattern) = self.trail[self.propagated];
+            self.propagated += 1;
+            for direction in 0..4 {
+                let neighbor = match self.neighbor(cell, direction) {
+                    Some(neighbor) => neighbor,
+                    None => continue,
+                };
+                for i in 0..self.constraints[pattern].compatible_with[direction].len() {
+                    let other = self.constraints[pattern].compatible_with[direction][i];
+                    let idx = self.support_idx(neighbor, opposite(direction), other);
+                    self.support[idx] -= 1;
+                    if self.support[idx] == 0
+                        && self.wave[neighbor * self.constraints.len() + other]
+                    {
+                        self.ban(neighbor, other);
+                    }
+                }
             }
         }
+    }
 
-        if chunk_y > 0 {
-            let up_idx = self.chunk_idx(chunk_x, chunk_y - 1);
-            if self.chunks[up_idx].is_some() {
-                neighbors += 1;
+    // Puts back everything removed since the trail was `mark` long.
+    fn undo(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let (cell, pattern) = self.trail.pop().unwrap();
+            if self.trail.len() < self.propagated {
+                for direction in 0..4 {
+                    if let Some(neighbor) = self.neighbor(cell, direction) {
+                        for other in self.constraints[pattern].compatible_with[direction].iter() {
+                            let idx = self.support_idx(neighbor, opposite(direction), *other);
+                            self.support[idx] += 1;
+                        }
+                    }
+                }
             }
+            self.wave[cell * self.constraints.len() + pattern] = true;
+            self.options[cell] += 1;
         }
+        self.propagated = mark;
+        self.contradiction = None;
+    }
 
-        if chunk_y < self.chunks_y - 1 {
-            let down_idx = self.chunk_idx(chunk_x, chunk_y + 1);
-            if self.chunks[down_idx].is_some() {
-                neighbors += 1;
+    // Undoes choices until one can be ruled out without a contradiction.
+    fn backtrack(&mut self) {
+        while let Some(cell) = self.contradiction {
+            let decision = if self.backtracks < MAX_BACKTRACKS {
+                self.decisions.pop()
+            } else {
+                None
+            };
+            match decision {
+                Some((mark, cell, pattern)) => {
+                    self.backtracks += 1;
+                    self.undo(mark);
+                    self.ban(cell, pattern);
+                    self.propagate();
+                }
+                None => {
+                    self.failed_at = Some(cell);
+                    self.finished = true;
+                    return;
+                }
             }
         }
-
-        neighbors
     }
 
-    pub fn iteration(&mut self, map: &mut Map, rng: &mut RandomNumberGenerator) -> bool {
-        if self.remaining.is_empty() {
+    // Collapses one cell; returns true once the solve is over, either way.
+    pub fn iteration(&mut self, rng: &mut RandomNumberGenerator) -> bool {
+        if self.finished {
             return true;
         }
 
-        // Populate the neighbor count of the remaining list
-        let mut remain_copy = self.remaining.clone();
-        let mut neighbors_exist = false;
-        for r in remain_copy.iter_mut() {
-            let idx = r.0;
-            let chunk_x = idx % self.chunks_x;
-            let chunk_y = idx / self.chunks_x;
-            let neighbor_count = self.count_neighbors(chunk_x, chunk_y);
-            if neighbor_count > 0 {
-                neighbors_exist = true
+        // The undecided cells with the fewest options left, picking between
+        // them at random.
+        let fewest = self.options.iter().filter(|o| **o > 1).min().copied();
+        let fewest = match fewest {
+            Some(fewest) => fewest,
+            None => {
+                self.finished = true;
+                return true;
             }
-            *r = (r.0, neighbor_count);
-        }
-        remain_copy.sort_by(|a, b| b.1.cmp(&a.1));
-        self.remaining = remain_copy;
-
-        // Pick a random chunk we haven't dealt with yet and get its index, remove from remaining
-        let remaining_index = if !neighbors_exist {
-            rng.random_slice_index(self.remaining.as_slice()).unwrap()
-        } else {
-            0usize
         };
-        let chunk_index = self.remaining[remaining_index].0;
-        self.remaining.remove(remaining_index);
+        let candidates: Vec<usize> = (0..self.options.len())
+            .filter(|cell| self.options[*cell] == fewest)
+            .collect();
+        let cell = candidates[rng.random_slice_index(candidates.as_slice()).unwrap()];
 
-        let chunk_x = chunk_index % self.chunks_x;
-        let chunk_y = chunk_index / self.chunks_x;
-
-        let mut neighbors = 0;
-        let mut options: Vec<Vec<usize>> = Vec::new();
-
-        if chunk_x > 0 {
-            let idx = self.chunk_idx(chunk_x - 1, chunk_y);
-            if let Some(nt) = self.chunks[idx] {
-                neighbors += 1;
-                options.push(self.constraints[nt].compatible_with[3].clone());
-            }
-        }
-        if chunk_x < self.chunks_x - 1 {
-            let idx = self.chunk_idx(chunk_x + 1, chunk_y);
-            if let Some(nt) = self.chunks[idx] {
-                neighbors += 1;
-                options.push(self.constraints[nt].compatible_with[2].clone());
+        // Common patterns are more likely.
+        let n_patterns = self.constraints.len();
+        let possible: Vec<usize> = (0..n_patterns)
+            .filter(|p| self.wave[cell * n_patterns + p])
+            .collect();
+        let total: i32 = possible.iter().map(|p| self.constraints[*p].weight).sum();
+        let mut roll = rng.roll_dice(1, total);
+        let mut chosen = possible[possible.len() - 1];
+        for p in possible.iter() {
+            if roll <= self.constraints[*p].weight {
+                chosen = *p;
+                break;
             }
+            roll -= self.constraints[*p].weight;
         }
 
-        if chunk_y > 0 {
-            let idx = self.chunk_idx(chunk_x, chunk_y - 1);
-            if let Some(nt) = self.chunks[idx] {
-                neighbors += 1;
-                options.push(self.constraints[nt].compatible_with[1].clone());
+        self.decisions.push((self.trail.len(), cell, chosen));
+        for p in possible {
+            if p != chosen {
+                self.ban(cell, p);
             }
         }
-        if chunk_y < self.chunks_y - 1 {
-            let idx = self.chunk_idx(chunk_x, chunk_y + 1);
-            if let Some(nt) = self.chunks[idx] {
-                neighbors += 1;
-                options.push(self.constraints[nt].compatible_with[0].clone());
-            }
+        self.propagate();
+        self.backtrack();
+
+        self.finished
+    }
+
+    pub fn result(&self) -> SolveResult {
+        SolveResult {
+            solved: self.finished && self.failed_at.is_none(),
+            backtracks: self.backtracks,
+            failed_region: self.failed_at.map(|cell| self.cell_rect(cell)),
         }
+    }
 
-        if neighbors == 0 {
-            // There is nothing nearby, so we can have anything!
-            let new_chunk_idx = rng.random_slice_index(self.constraints.as_slice()).unwrap();
-            self.chunks[chunk_index] = Some(new_chunk_idx);
-            // Avoid drawing in the rightmost column or bottommost row
-            let left_x = chunk_x as i32 * self.chunk_size + 1;
-            let right_x = i32::min(
-                (chunk_x as i32 + 1) * self.chunk_size + 1,
-                map.width as i32 - 1,
-            );
-            let top_y = chunk_y as i32 * self.chunk_size + 1;
-            let bottom_y = i32::min(
-                (chunk_y as i32 + 1) * self.chunk_size + 1,
-                map.height as i32 - 1,
-            );
-
-            let mut i = 0usize;
-            for y in top_y..bottom_y {
-                for x in left_x..right_x {
-                    // Don't wrap.
-                    if let Some(mapidx) = map.try_idx(Point::new(x, y)) {
-                        let tile = self.constraints[new_chunk_idx].pattern[i];
-                        map.tiles[mapidx] = tile;
-                    }
-                    i += 1;
-                }
-            }
+    fn cell_origin(&self, cell: usize) -> Point {
+        let (x, y) = ((cell % self.cells_x) as i32, (cell / self.cells_x) as i32);
+        if self.overlapping {
+            Point::new(x + 1, y + 1)
         } else {
-            // There are neighbors, so we try to be compatible with them
-            let mut options_to_check: HashSet<usize> = HashSet::new();
-            for o in options.iter() {
-                for i in o.iter() {
-                    options_to_check.insert(*i);
-                }
-            }
+            Point::new(x * self.chunk_size + 1, y * self.chunk_size + 1)
+        }
+    }
 
-            let mut possible_options: Vec<usize> = Vec::new();
-            for new_chunk_idx in options_to_check.iter() {
-                let mut possible = true;
-                for o in options.iter() {
-                    if !o.contains(new_chunk_idx) {
-                        possible = false;
-                    }
-                }
-                if possible {
-                    possible_options.push(*new_chunk_idx);
-                }
+    fn cell_rect(&self, cell: usize) -> Rect {
+        let origin = self.cell_origin(cell);
+        Rect::with_size(origin.x, origin.y, self.chunk_size, self.chunk_size)
+    }
+
+    // Draws the decided cells over solid wall.
+    pub fn draw(&self, map: &mut Map) {
+        map.fill(TileType::Wall);
+        let n_patterns = self.constraints.len();
+        for cell in 0..self.options.len() {
+            if self.options[cell] != 1 {
+                continue;
+            }
+            if let Some(pattern) = (0..n_patterns).find(|p| self.wave[cell * n_patterns + p]) {
+                self.draw_chunk(map, pattern, self.cell_origin(cell));
             }
+        }
+    }
 
-            if possible_options.is_empty() {
-                log("Oh no! It's not possible!");
-                self.possible = false;
-                return true;
-            } else {
-                let new_chunk_idx = if possible_options.len() == 1 {
-                    0
-                } else {
-                    rng.random_slice_index(possible_options.as_slice()).unwrap()
-                };
+    fn draw_chunk(&self, map: &mut Map, pattern_idx: usize, origin: Point) {
+        // Avoid drawing in the rightmost column or bottommost row
+        let right_x = i32::min(origin.x + self.chunk_size, map.width as i32 - 1);
+        let bottom_y = i32::min(origin.y + self.chunk_size, map.height as i32 - 1);
 
-                self.chunks[chunk_index] = Some(new_chunk_idx);
-                // Avoid drawing in the rightmost column or bottommost row
-                let left_x = chunk_x as i32 * self.chunk_size + 1;
-                let right_x = i32::min(
-                    (chunk_x as i32 + 1) * self.chunk_size + 1,
-                    map.width as i32 - 1,
-                );
-                let top_y = chunk_y as i32 * self.chunk_size + 1;
-                let bottom_y = i32::min(
-                    (chunk_y as i32 + 1) * self.chunk_size + 1,
-                    map.height as i32 - 1,
-                );
-
-                let mut i = 0usize;
-                for y in top_y..bottom_y {
-                    for x in left_x..right_x {
-                        if let Some(mapidx) = map.try_idx(Point::new(x, y)) {
-                            let tile = self.constraints[new_chunk_idx].pattern[i];
-                            map.tiles[mapidx] = tile;
-                        }
-                        i += 1;
-                    }
+        // Chunks cut short by the map edge still read whole pattern rows.
+        let pattern = &self.constraints[pattern_idx].pattern;
+        for y in origin.y..bottom_y {
+            for x in origin.x..right_x {
+                // Don't wrap.
+                if let Some(mapidx) = map.try_idx(Point::new(x, y)) {
+                    let i = tile_idx_in_chunk(self.chunk_size, x - origin.x, y - origin.y);
+                    map.tiles[mapidx] = pattern[i];
                 }
             }
         }
-
-        false
     }
 }
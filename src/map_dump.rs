@@ -0,0 +1,244 @@
+use crate::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::tiled::{TiledMap, TILESET_IMAGE};
+
+const START_GLYPH: char = '@';
+const UNKNOWN_GLYPH: char = '?';
+const TILESET_PNG: &[u8] = include_bytes!("../resources/terminal8x8.png");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    Ascii,
+    Rex,
+    Tiled,
+}
+
+impl DumpFormat {
+    pub fn from_name(name: &str) -> Option<DumpFormat> {
+        match name {
+            "ascii" | "txt" => Some(DumpFormat::Ascii),
+            "xp" | "rex" => Some(DumpFormat::Rex),
+            "tiled" | "tmj" | "json" => Some(DumpFormat::Tiled),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            DumpFormat::Ascii => "txt",
+            DumpFormat::Rex => "xp",
+            DumpFormat::Tiled => "tmj",
+        }
+    }
+}
+
+struct Cell {
+    glyph: FontCharType,
+    fg: RGB,
+}
+
+// Generates the level the game would build for this seed, branch and depth;
+// the width and height are used if the level doesn't set its own.
+pub fn generate_level(
+    seed: GameSeed,
+    branch: &str,
+    depth: i32,
+    width: usize,
+    height: usize,
+    raws: &RawMaster,
+    with_history: bool,
+) -> BuilderMap {
+    let dm = MasterDungeonMap::new(raws, &mut seed.identification_rng());
+    let level = LevelId::new(dm.branch_id(branch).unwrap_or(MAIN_BRANCH_ID), depth);
+    let mut rng = seed.branch_level_rng(branch, depth);
+    build_level(level, width, height, &mut rng, raws, &dm, with_history).build_data
+}
+
+// Writes the level to `<dir>/depthNN_seedS.<ext>` (prefixed with the branch
+// outside the main dungeon), followed by one file per history snapshot if
+// requested. Returns the files written.
+pub fn dump_level(
+    level: &BuilderMap,
+    branch: &str,
+    raws: &RawMaster,
+    seed: GameSeed,
+    format: DumpFormat,
+    dir: &Path,
+    with_history: bool,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut stem = format!("depth{:02}_seed{}", level.map.depth, seed);
+    if branch != MAIN_BRANCH {
+        stem = format!("{}_{}", branch.to_lowercase().replace(' ', "_"), stem);
+    }
+    let mut written = Vec::new();
+
+    let path = dir.join(format!("{}.{}", stem, format.extension()));
+    let entities = entity_cells(level, raws);
+    match format {
+        DumpFormat::Ascii => write_ascii(&path, level, &entities, seed)?,
+        DumpFormat::Rex => write_rex(&path, &level.map, &entities)?,
+        DumpFormat::Tiled => write_tiled(&path, &TiledMap::from_level(level))?,
+    }
+    written.push(path);
+
+    if with_history {
+        for (step, snapshot) in level.history.iter().enumerate() {
+            let path = dir.join(format!("{}_step{:03}.{}", stem, step, format.extension()));
+            match format {
+                DumpFormat::Ascii => {
+                    let mut file = BufWriter::new(File::create(&path)?);
+                    writeln!(file, "{} step {} | {}", stem, step, snapshot.builder)?;
+                    write_grid(&mut file, &snapshot.map, &[])?;
+                }
+                DumpFormat::Rex => write_rex(&path, &snapshot.map, &[])?,
+                DumpFormat::Tiled => write_tiled(&path, &TiledMap::from_snapshot(snapshot))?,
+            }
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+fn tile_cells(map: &Map) -> Vec<Cell> {
+    // Wall glyphs depend on which neighbours have been seen.
+    let mut map = map.clone();
+    map.revealed_tiles.iter_mut().for_each(|t| *t = true);
+    (0..map.tiles.len())
+        .map(|idx| {
+            let (glyph, fg) = map.theme.tile_to_render(&map, idx);
+            Cell { glyph, fg }
+        })
+        .collect()
+}
+
+fn entity_cells(level: &BuilderMap, raws: &RawMaster) -> Vec<(Point, Cell)> {
+    let mut cells: Vec<(Point, Cell)> = level
+        .spawn_list
+        .iter()
+        .map(|(pos, name)| {
+            let cell = match raws.render_of(name) {
+                Some(render) => Cell {
+                    glyph: render.glyph,
+                    fg: render.color.fg.to_rgb(),
+                },
+                None => Cell {
+                    glyph: to_cp437(UNKNOWN_GLYPH),
+                    fg: RGB::named(MAGENTA),
+                },
+            };
+            (*pos, cell)
+        })
+        .collect();
+    if let Some(start) = level.starting_position {
+        cells.push((
+            start,
+            Cell {
+                glyph: to_cp437(START_GLYPH),
+                fg: RGB::named(YELLOW),
+            },
+        ));
+    }
+    cells
+}
+
+fn write_grid<W: Write>(out: &mut W, map: &Map, entities: &[(Point, Cell)]) -> io::Result<()> {
+    let mut glyphs: Vec<FontCharType> = tile_cells(map).iter().map(|c| c.glyph).collect();
+    for (pos, cell) in entities.iter() {
+        if let Some(idx) = map.try_idx(*pos) {
+            glyphs[idx] = cell.glyph;
+        }
+    }
+    for row in glyphs.chunks(map.width) {
+        let line: String = row.iter().map(|g| to_char(*g as u8)).collect();
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn write_ascii(
+    path: &Path,
+    level: &BuilderMap,
+    entities: &[(Point, Cell)],
+    seed: GameSeed,
+) -> io::Result<()> {
+    let map = &level.map;
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "{} | depth {} | seed {} | {}x{}",
+        map.name, map.depth, seed, map.width, map.height
+    )?;
+    write_grid(&mut out, map, entities)?;
+
+    match level.starting_position {
+        Some(start) => writeln!(out, "start: {},{}", start.x, start.y)?,
+        None => writeln!(out, "start: none")?,
+    }
+    for (label, tile) in [
+        ("down stairs", TileType::DownStairs),
+        ("up stairs", TileType::UpStairs),
+    ] {
+        let stairs: Vec<String> = map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| **t == tile)
+            .map(|(idx, _)| {
+                let pos = map.index_to_point2d(idx);
+                format!("{},{}", pos.x, pos.y)
+            })
+            .collect();
+        writeln!(out, "{}: {}", label, stairs.join(" "))?;
+    }
+
+    writeln!(out, "spawns: {}", level.spawn_list.len())?;
+    for (pos, name) in level.spawn_list.iter() {
+        writeln!(out, "  {},{} {}", pos.x, pos.y, name)?;
+    }
+    Ok(())
+}
+
+// Tiles go on the first layer; spawns and the starting position on a second,
+// transparent layer so they can be hidden in REXPaint.
+fn write_rex(path: &Path, map: &Map, entities: &[(Point, Cell)]) -> io::Result<()> {
+    let mut xp = XpFile::new(map.width, map.height);
+    for (idx, cell) in tile_cells(map).iter().enumerate() {
+        let pos = map.index_to_point2d(idx);
+        if let Some(xp_cell) = xp.layers[0].get_mut(pos.x as usize, pos.y as usize) {
+            xp_cell.ch = cell.glyph as u32;
+            xp_cell.fg = cell.fg.to_xp();
+        }
+    }
+
+    if !entities.is_empty() {
+        let mut layer = XpLayer::new(map.width, map.height);
+        layer
+            .cells
+            .iter_mut()
+            .for_each(|c| c.bg = XpColor::TRANSPARENT);
+        for (pos, cell) in entities.iter() {
+            if let Some(xp_cell) = layer.get_mut(pos.x as usize, pos.y as usize) {
+                xp_cell.ch = cell.glyph as u32;
+                xp_cell.fg = cell.fg.to_xp();
+            }
+        }
+        xp.layers.push(layer);
+    }
+
+    xp.write(&mut BufWriter::new(File::create(path)?))
+}
+
+// Tiled looks for the tileset image beside the map, so it is written there too.
+fn write_tiled(path: &Path, tiled: &TiledMap) -> io::Result<()> {
+    let image = path.with_file_name(TILESET_IMAGE);
+    if !image.exists() {
+        fs::write(image, TILESET_PNG)?;
+    }
+    let file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(file, tiled).map_err(io::Error::other)
+}
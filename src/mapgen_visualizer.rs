@@ -0,0 +1,236 @@
+use crate::prelude::*;
+
+// How long each snapshot shows for while playing.
+const STEP_MS: f32 = 300.0;
+
+// Replays the snapshots taken while a new level was built, before play
+// starts on it. Turned on and off from the cheat menu.
+pub struct MapgenVisualizer {
+    pub enabled: bool,
+    history: Vec<MapSnapshot>,
+    real_map: Map,
+    timer: f32,
+    paused: bool,
+    overlays: bool,
+}
+
+impl MapgenVisualizer {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            history: Vec::new(),
+            real_map: Map::default(),
+            timer: 0.0,
+            paused: false,
+            overlays: true,
+        }
+    }
+
+    pub fn start(&mut self, history: Vec<MapSnapshot>) {
+        self.history = history;
+        self.timer = 0.0;
+        self.paused = false;
+    }
+
+    // The finished map, put back once the replay ends.
+    pub fn set_real_map(&mut self, map: Map) {
+        self.real_map = map;
+    }
+
+    pub fn finish(&mut self) -> Map {
+        self.history.clear();
+        std::mem::take(&mut self.real_map)
+    }
+
+    pub fn has_history(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn snapshot(&self, step: usize) -> Option<&MapSnapshot> {
+        self.history.get(step)
+    }
+
+    // The first snapshot taken by each builder in turn.
+    fn stages(&self) -> Vec<(usize, &str)> {
+        let mut stages: Vec<(usize, &str)> = Vec::new();
+        for (step, snapshot) in self.history.iter().enumerate() {
+            if stages
+                .last()
+                .is_none_or(|(_, name)| *name != snapshot.builder)
+            {
+                stages.push((step, &snapshot.builder));
+            }
+        }
+        stages
+    }
+
+    fn stage_of(&self, step: usize) -> usize {
+        self.stages()
+            .iter()
+            .rposition(|(first, _)| *first <= step)
+            .unwrap_or(0)
+    }
+
+    // The step to show next, or None once the player is done watching.
+    pub fn next_step(
+        &mut self,
+        step: usize,
+        frame_time_ms: f32,
+        key: Option<VirtualKeyCode>,
+    ) -> Option<usize> {
+        let last = self.history.len().saturating_sub(1);
+        let stages = self.stages();
+        let stage = self.stage_of(step);
+        let jump = |to: usize, paused: &mut bool| {
+            *paused = true;
+            Some(to.min(last))
+        };
+
+        match key {
+            Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::Escape) => None,
+            Some(VirtualKeyCode::Space) => {
+                self.paused = !self.paused;
+                Some(step)
+            }
+            Some(VirtualKeyCode::Tab) => {
+                self.overlays = !self.overlays;
+                Some(step)
+            }
+            Some(VirtualKeyCode::Right) | Some(VirtualKeyCode::Period) => {
+                jump(step + 1, &mut self.paused)
+            }
+            Some(VirtualKeyCode::Left) | Some(VirtualKeyCode::Comma) => {
+                jump(step.saturating_sub(1), &mut self.paused)
+            }
+            Some(VirtualKeyCode::PageDown) => match stages.get(stage + 1) {
+                Some((first, _)) => jump(*first, &mut self.paused),
+                None => jump(last, &mut self.paused),
+            },
+            Some(VirtualKeyCode::PageUp) => {
+                jump(stages[stage.saturating_sub(1)].0, &mut self.paused)
+            }
+            Some(key) if letter_to_option(key) >= 0 => {
+                match stages.get(letter_to_option(key) as usize) {
+                    Some((first, _)) => jump(*first, &mut self.paused),
+                    None => Some(step),
+                }
+            }
+            _ => {
+                // Play on, stopping at the finished map.
+                if self.paused || step >= last {
+                    return Some(step);
+                }
+                self.timer += frame_time_ms;
+                if self.timer < STEP_MS {
+                    return Some(step);
+                }
+                self.timer = 0.0;
+                Some(step + 1)
+            }
+        }
+    }
+
+    pub fn render(&self, step: usize, camera: &Camera) {
+        let snapshot = match self.snapshot(step) {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        if self.overlays {
+            render_overlays(snapshot, camera);
+        }
+
+        let mut batch = DrawBatch::new();
+        batch.target(2);
+        let white = ColorPair::new(WHITE, BLACK);
+        let gray = ColorPair::new(GRAY, BLACK);
+        let yellow = ColorPair::new(YELLOW, BLACK);
+
+        // Over the stats panel.
+        let panel = Rect::with_size(49, 8, 30, 37);
+        batch.draw_box(panel, ColorPair::new(GRAY60, BLACK));
+        batch.print_color(Point::new(51, 8), "Map generation", yellow);
+        let status = if self.paused || step + 1 >= self.history.len() {
+            "paused"
+        } else {
+            "playing"
+        };
+        batch.print_color(
+            Point::new(51, 9),
+            format!("Step {}/{} ({})", step + 1, self.history.len(), status),
+            white,
+        );
+
+        let current = self.stage_of(step);
+        let rows = (panel.y2 - 16) as usize;
+        for (n, (_, name)) in self.stages().iter().enumerate().take(rows.min(26)) {
+            let color = if n == current { yellow } else { white };
+            let key = (b'a' + n as u8) as char;
+            let name: String = name.chars().take(24).collect();
+            batch.print_color(
+                Point::new(51, 11 + n as i32),
+                format!("{} {}", key, name),
+                color,
+            );
+        }
+
+        let help = [
+            "SPACE pause, </> step",
+            "PGUP/PGDN or a-z: stage",
+            "TAB overlays",
+            "ENTER to play",
+        ];
+        for (n, line) in help.iter().enumerate() {
+            batch.print_color(Point::new(51, panel.y2 - 4 + n as i32), *line, gray);
+        }
+        batch.submit(10000).expect("Batch error");
+    }
+}
+
+// Rooms, corridors, the start and the spawn list, as the chain saw them.
+fn render_overlays(snapshot: &MapSnapshot, camera: &Camera) {
+    let mut batch = DrawBatch::new();
+    batch.target(1);
+    let offset = Point::new(camera.left_x, camera.top_y);
+    let on_screen = |pt: Point| {
+        pt.x >= camera.left_x
+            && pt.x <= camera.right_x
+            && pt.y >= camera.top_y
+            && pt.y <= camera.bottom_y
+    };
+
+    let room_color = ColorPair::new(CYAN, BLACK);
+    for room in snapshot.rooms.iter().flatten() {
+        room.for_each(|pt| {
+            let edge =
+                pt.x == room.x1 || pt.x == room.x2 - 1 || pt.y == room.y1 || pt.y == room.y2 - 1;
+            if edge && on_screen(pt) {
+                batch.set(pt - offset, room_color, to_cp437('∙'));
+            }
+        });
+    }
+
+    let corridor_color = ColorPair::new(ORANGE, BLACK);
+    for corridor in snapshot.corridors.iter().flatten() {
+        for idx in corridor.iter() {
+            let pt = snapshot.map.index_to_point2d(*idx);
+            if on_screen(pt) {
+                batch.set(pt - offset, corridor_color, to_cp437('░'));
+            }
+        }
+    }
+
+    let spawn_color = ColorPair::new(MAGENTA, BLACK);
+    for (pt, name) in snapshot.spawn_list.iter() {
+        if on_screen(*pt) {
+            let glyph = name.chars().next().unwrap_or('?');
+            batch.set(*pt - offset, spawn_color, to_cp437(glyph));
+        }
+    }
+
+    if let Some(start) = snapshot.starting_position {
+        if on_screen(start) {
+            batch.set(start - offset, ColorPair::new(GREEN, BLACK), to_cp437('@'));
+        }
+    }
+    batch.submit(8000).expect("Batch error");
+}
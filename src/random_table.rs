@@ -15,7 +15,7 @@ impl RandomEntry {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct MasterTable {
     items: RandomTable,
     mobs: RandomTable,
@@ -35,6 +35,15 @@ impl MasterTable {
         }
     }
 
+    // Rolls on just the one kind of thing.
+    pub fn roll_of(&self, kind: SpawnTableType, rng: &mut RandomNumberGenerator) -> String {
+        match kind {
+            SpawnTableType::Item => self.items.roll(rng),
+            SpawnTableType::Mob => self.mobs.roll(rng),
+            SpawnTableType::Prop => self.props.roll(rng),
+        }
+    }
+
     pub fn roll(&self, rng: &mut RandomNumberGenerator) -> String {
         match rng.roll_dice(1, 4) {
             1 => self.items.roll(rng),
@@ -1,7 +1,7 @@
 use crate::prelude::*;
 use std::collections::HashMap;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Item {
     pub name: String,
     pub renderable: Option<Renderable>,
@@ -15,15 +15,16 @@ pub struct Item {
     pub magic: Option<MagicItem>,
     pub attributes: Option<ItemAttributeBonus>,
     pub template_magic: Option<ItemMagicTemplate>,
+    pub digs: Option<TileType>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Consumable {
     pub effects: HashMap<String, String>,
     pub charges: Option<i32>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Weapon {
     pub range: String,
     pub attribute: String,
@@ -34,20 +35,20 @@ pub struct Weapon {
     pub proc_effects: Option<HashMap<String, String>>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Wearable {
     pub armor_class: f32,
     pub slot: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct MagicItem {
     pub class: String,
     pub naming: String,
     pub cursed: Option<bool>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct ItemAttributeBonus {
     pub might: Option<i32>,
     pub fitness: Option<i32>,
@@ -55,7 +56,7 @@ pub struct ItemAttributeBonus {
     pub intelligence: Option<i32>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct ItemMagicTemplate {
     pub unidentified_name: String,
     pub bonus_min: i32,
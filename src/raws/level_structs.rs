@@ -0,0 +1,94 @@
+use crate::prelude::*;
+
+// Levels and branches that don't name a branch belong to this one.
+pub const MAIN_BRANCH: &str = "Main";
+
+// One way of building a level. Every depth picks between the chains that
+// cover it, by weight.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct LevelChain {
+    pub name: String,
+    pub map_name: String,
+    pub branch: Option<String>,
+    pub min_depth: i32,
+    pub max_depth: Option<i32>,
+    pub weight: Option<i32>,
+    pub theme: Option<MapTheme>,
+    pub outdoors: Option<bool>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub steps: Vec<BuilderStep>,
+}
+
+impl LevelChain {
+    pub fn branch(&self) -> &str {
+        self.branch.as_deref().unwrap_or(MAIN_BRANCH)
+    }
+
+    pub fn covers_depth(&self, depth: i32) -> bool {
+        depth >= self.min_depth && self.max_depth.is_none_or(|max| depth <= max)
+    }
+}
+
+// Steps shared between chains, pulled in with "include".
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BuilderFragment {
+    pub name: String,
+    pub steps: Vec<BuilderStep>,
+}
+
+// Each step is exactly one of: a builder (with whichever of the parameters
+// it takes), a weighted choice between lists of steps, or an include.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BuilderStep {
+    pub builder: Option<String>,
+    pub variant: Option<String>,
+    pub x: Option<String>,
+    pub y: Option<String>,
+    pub divisor: Option<f32>,
+    pub prefab: Option<String>,
+    pub chunk_size: Option<i32>,
+    pub overlapping: Option<bool>,
+    pub choose: Option<Vec<BuilderChoice>>,
+    pub include: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BuilderChoice {
+    pub weight: i32,
+    pub steps: Vec<BuilderStep>,
+}
+
+// A side branch of the dungeon, entered by its own stairs from a level of
+// its parent. Its levels carry on from the entrance's depth, so the first is
+// one deeper than the entrance.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Branch {
+    pub name: String,
+    pub parent: Option<String>,
+    pub entrance_depth: i32,
+    pub levels: i32,
+}
+
+impl Branch {
+    pub fn parent(&self) -> &str {
+        self.parent.as_deref().unwrap_or(MAIN_BRANCH)
+    }
+
+    pub fn first_depth(&self) -> i32 {
+        self.entrance_depth + 1
+    }
+
+    pub fn last_depth(&self) -> i32 {
+        self.entrance_depth + self.levels
+    }
+}
+
+// The layout of levels.json.
+#[derive(Deserialize, Debug)]
+pub struct LevelRaws {
+    pub levels: Vec<LevelChain>,
+    pub builder_fragments: Vec<BuilderFragment>,
+    #[serde(default)]
+    pub branches: Vec<Branch>,
+}
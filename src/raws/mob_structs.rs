@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use super::Renderable;
 use crate::prelude::*;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct Mob {
     pub name: String,
     pub renderable: Option<Renderable>,
@@ -25,9 +25,10 @@ pub struct Mob {
     pub vendor: Option<Vec<String>>,
     pub abilities: Option<Vec<MobAbility>>,
     pub on_death: Option<Vec<MobAbility>>,
+    pub digs: Option<TileType>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct MobAttributes {
     pub might: Option<i32>,
     pub fitness: Option<i32>,
@@ -35,26 +36,26 @@ pub struct MobAttributes {
     pub intelligence: Option<i32>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct MobNatural {
     pub armor_class: Option<i32>,
     pub attacks: Option<Vec<NaturalAttack>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct NaturalAttack {
     pub name: String,
     pub hit_bonus: i32,
     pub damage: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 pub struct MobLight {
     pub range: i32,
     pub color: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 pub struct MobAbility {
     pub spell: String,
     pub chance: f32,
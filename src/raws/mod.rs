@@ -1,24 +1,38 @@
 use crate::prelude::*;
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::fs;
 
 mod faction_structs;
 mod item_structs;
+mod level_structs;
 mod loot_structs;
 mod mob_structs;
+mod mods;
+mod prefab_structs;
 mod prop_structs;
 mod rawmaster;
+mod room_structs;
 mod spawn_table_structs;
 mod spell_structs;
+mod validate;
 mod weapon_traits;
 
 pub use faction_structs::Reaction;
+pub use level_structs::{
+    Branch, BuilderChoice, BuilderFragment, BuilderStep, LevelChain, MAIN_BRANCH,
+};
+pub use mods::{base_source, RawSources, MODS_DIR};
+pub use prefab_structs::{HorizontalPlacement, Prefab, PrefabGlyph, PrefabKind, VerticalPlacement};
 pub use rawmaster::*;
-
-lazy_static! {
-    pub static ref RAWS: Mutex<RawMaster> = Mutex::new(RawMaster::empty());
-}
+pub use room_structs::RoomKind;
+pub use validate::{validate_raws, RawProblem};
 
 embedded_resource!(RAW_FILE, "../../raws/spawns.json");
+embedded_resource!(LEVEL_FILE, "../../raws/levels.json");
+embedded_resource!(PREFAB_FILE, "../../raws/prefabs.json");
+// Built-in copies of the files the base game's prefabs are read from.
+embedded_resource!(WFC_POPULATED_FILE, "../../resources/wfc-populated.xp");
+embedded_resource!(WFC_DEMO_FILE, "../../resources/wfc-demo1.xp");
 
 #[derive(Deserialize, Debug)]
 pub struct Raws {
@@ -30,9 +44,25 @@ pub struct Raws {
     pub loot_tables: Vec<loot_structs::LootTable>,
     pub faction_table: Vec<faction_structs::FactionInfo>,
     pub weapon_traits: Vec<weapon_traits::WeaponTrait>,
+    #[serde(default)]
+    pub room_kinds: Vec<RoomKind>,
+    // These come from levels.json.
+    #[serde(default)]
+    pub levels: Vec<LevelChain>,
+    #[serde(default)]
+    pub builder_fragments: Vec<BuilderFragment>,
+    #[serde(default)]
+    pub branches: Vec<Branch>,
+    // These come from prefabs.json.
+    #[serde(default)]
+    pub prefab_legend: HashMap<char, PrefabGlyph>,
+    #[serde(default)]
+    pub prefabs: Vec<Prefab>,
+    #[serde(skip)]
+    pub sources: RawSources,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Renderable {
     pub glyph: String,
     pub fg: String,
@@ -42,16 +72,89 @@ pub struct Renderable {
     pub y_size: Option<i32>,
 }
 
-pub fn load_raws() {
-    link_resource!(RAW_FILE, "../../raws/spawns.json");
-
+fn embedded_json<T: serde::de::DeserializeOwned>(path: &str) -> T {
     let raw_data = embedding::EMBED
         .lock()
-        .get_resource("../../raws/spawns.json".to_string())
+        .get_resource(path.to_string())
         .unwrap();
     let raw_string =
         std::str::from_utf8(&raw_data).expect("Unable to convert to valid UTF-8 string");
-    let decoder: Raws = serde_json::from_str(&raw_string).expect("Unable to parse JSON");
+    serde_json::from_str(raw_string).expect("Unable to parse JSON")
+}
+
+// A base raw file, read from disk when it is there so it can be edited and
+// reloaded without a rebuild. Otherwise (or if it doesn't parse) the copy
+// built into the game is used.
+fn base_json<T: serde::de::DeserializeOwned>(path: &str) -> T {
+    if let Ok(text) = fs::read_to_string(path) {
+        match serde_json::from_str(&text) {
+            Ok(data) => return data,
+            Err(e) => log(format!(
+                "WARNING: unable to parse {}, using the built-in copy: {}",
+                path, e
+            )),
+        }
+    }
+    embedded_json(&format!("../../{}", path))
+}
+
+// The base game's prefab files are read from resources/, falling back to
+// the ones built into the game.
+fn load_base_templates(prefabs: &mut [Prefab]) {
+    for prefab in prefabs.iter_mut() {
+        let file = match prefab.file.clone() {
+            Some(file) => file,
+            None => continue,
+        };
+        let data = fs::read(format!("resources/{}", file)).ok().or_else(|| {
+            embedding::EMBED
+                .lock()
+                .get_resource(format!("../../resources/{}", file))
+                .map(|data| data.to_vec())
+        });
+        let loaded = match data {
+            Some(data) => prefab
+                .load_template(&file, &data)
+                .map_err(|e| e.to_string()),
+            None => Err("no such file in resources/".to_string()),
+        };
+        if let Err(e) = loaded {
+            log(format!(
+                "WARNING: unable to read prefab {} from {}: {}",
+                prefab.name, file, e
+            ));
+        }
+    }
+}
+
+// The base raws, with any mods applied.
+pub fn read_raws() -> Raws {
+    link_resource!(RAW_FILE, "../../raws/spawns.json");
+    link_resource!(LEVEL_FILE, "../../raws/levels.json");
+    link_resource!(PREFAB_FILE, "../../raws/prefabs.json");
+    link_resource!(WFC_POPULATED_FILE, "../../resources/wfc-populated.xp");
+    link_resource!(WFC_DEMO_FILE, "../../resources/wfc-demo1.xp");
+
+    let mut decoder: Raws = base_json("raws/spawns.json");
+    let levels: level_structs::LevelRaws = base_json("raws/levels.json");
+    decoder.levels = levels.levels;
+    decoder.builder_fragments = levels.builder_fragments;
+    decoder.branches = levels.branches;
+    let mut prefabs: prefab_structs::PrefabRaws = base_json("raws/prefabs.json");
+    load_base_templates(&mut prefabs.prefabs);
+    decoder.prefab_legend = prefabs.legend;
+    decoder.prefabs = prefabs.prefabs;
+    mods::apply_mods(&mut decoder, MODS_DIR);
+    decoder
+}
+
+pub fn load_raws() -> RawMaster {
+    let decoder = read_raws();
+    for problem in validate_raws(&decoder) {
+        log(format!("WARNING: {}", problem));
+    }
 
-    RAWS.lock().unwrap().load(decoder);
+    let mut raws = RawMaster::empty();
+    raws.load(decoder);
+    raws
 }
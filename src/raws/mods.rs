@@ -0,0 +1,350 @@
+use super::{
+    faction_structs::FactionInfo, item_structs::Item, loot_structs::LootTable, mob_structs::Mob,
+    prop_structs::Prop, spawn_table_structs::SpawnTableEntry, spell_structs::Spell,
+    weapon_traits::WeaponTrait, Branch, BuilderFragment, LevelChain, Prefab, Raws, RoomKind,
+};
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MODS_DIR: &str = "./mods";
+
+// The base game's raw file each section is read from.
+pub fn base_source(section: &str) -> &'static str {
+    match section {
+        "levels" | "builder_fragments" | "branches" => "raws/levels.json",
+        "prefabs" => "raws/prefabs.json",
+        _ => "raws/spawns.json",
+    }
+}
+
+// A mod is any JSON file in the mods directory, holding any of the sections
+// of spawns.json, levels.json or prefabs.json (bar the shared legend).
+// Entries replace the existing entry of the same name or are added; names
+// listed under "remove" are deleted first. Prefab files are found relative to
+// the mod.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawsMod {
+    items: Vec<Item>,
+    mobs: Vec<Mob>,
+    props: Vec<Prop>,
+    spells: Vec<Spell>,
+    spawn_table: Vec<SpawnTableEntry>,
+    loot_tables: Vec<LootTable>,
+    faction_table: Vec<FactionInfo>,
+    weapon_traits: Vec<WeaponTrait>,
+    room_kinds: Vec<RoomKind>,
+    levels: Vec<LevelChain>,
+    builder_fragments: Vec<BuilderFragment>,
+    branches: Vec<Branch>,
+    prefabs: Vec<Prefab>,
+    remove: RawsRemovals,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawsRemovals {
+    items: Vec<String>,
+    mobs: Vec<String>,
+    props: Vec<String>,
+    spells: Vec<String>,
+    spawn_table: Vec<String>,
+    loot_tables: Vec<String>,
+    faction_table: Vec<String>,
+    weapon_traits: Vec<String>,
+    room_kinds: Vec<String>,
+    levels: Vec<String>,
+    builder_fragments: Vec<String>,
+    branches: Vec<String>,
+    prefabs: Vec<String>,
+}
+
+// Which file last supplied each entry, and where in that file's section it
+// was, by section and name.
+#[derive(Debug, Default)]
+pub struct RawSources(HashMap<(String, String), (String, usize)>);
+
+impl RawSources {
+    pub fn source_of(&self, section: &str, name: &str) -> &str {
+        self.0
+            .get(&(section.to_string(), name.to_string()))
+            .map_or(base_source(section), |(source, _)| source.as_str())
+    }
+
+    pub fn index_of(&self, section: &str, name: &str) -> Option<usize> {
+        self.0
+            .get(&(section.to_string(), name.to_string()))
+            .map(|(_, index)| *index)
+    }
+
+    fn record(&mut self, section: &str, name: &str, source: &str, index: usize) {
+        self.0.insert(
+            (section.to_string(), name.to_string()),
+            (source.to_string(), index),
+        );
+    }
+
+    fn forget(&mut self, section: &str, name: &str) {
+        self.0.remove(&(section.to_string(), name.to_string()));
+    }
+}
+
+trait Named {
+    fn name(&self) -> &str;
+}
+
+macro_rules! named {
+    ($($t:ty),*) => {
+        $(impl Named for $t {
+            fn name(&self) -> &str {
+                &self.name
+            }
+        })*
+    };
+}
+
+named!(
+    Item,
+    Mob,
+    Prop,
+    Spell,
+    SpawnTableEntry,
+    LootTable,
+    FactionInfo,
+    WeaponTrait,
+    RoomKind,
+    LevelChain,
+    BuilderFragment,
+    Branch,
+    Prefab
+);
+
+// Mods are applied in file name order, so a later file can override an
+// earlier one ("00_base_tweaks.json" before "10_new_monsters.json").
+pub fn mod_files(dir: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+pub fn apply_mods(raws: &mut Raws, dir: &str) {
+    record_base(raws);
+    for path in mod_files(dir) {
+        match read_mod(&path) {
+            Ok(mut raws_mod) => {
+                load_templates(&mut raws_mod.prefabs, &path);
+                apply_mod(raws, raws_mod, &path.display().to_string())
+            }
+            Err(e) => log(format!("WARNING: skipping mod {}: {}", path.display(), e)),
+        }
+    }
+}
+
+fn record_base(raws: &mut Raws) {
+    let sources = &mut raws.sources;
+    record_section("items", &raws.items, sources);
+    record_section("mobs", &raws.mobs, sources);
+    record_section("props", &raws.props, sources);
+    record_section("spells", &raws.spells, sources);
+    record_section("spawn_table", &raws.spawn_table, sources);
+    record_section("loot_tables", &raws.loot_tables, sources);
+    record_section("faction_table", &raws.faction_table, sources);
+    record_section("weapon_traits", &raws.weapon_traits, sources);
+    record_section("room_kinds", &raws.room_kinds, sources);
+    record_section("levels", &raws.levels, sources);
+    record_section("builder_fragments", &raws.builder_fragments, sources);
+    record_section("branches", &raws.branches, sources);
+    record_section("prefabs", &raws.prefabs, sources);
+}
+
+fn record_section<T: Named>(section: &str, entries: &[T], sources: &mut RawSources) {
+    for (i, entry) in entries.iter().enumerate() {
+        sources.record(section, entry.name(), base_source(section), i);
+    }
+}
+
+fn load_templates(prefabs: &mut [Prefab], mod_path: &Path) {
+    let dir = mod_path.parent().unwrap_or(Path::new("."));
+    for prefab in prefabs.iter_mut() {
+        let file = match prefab.file.clone() {
+            Some(file) => file,
+            None => continue,
+        };
+        let loaded = fs::read(dir.join(&file)).and_then(|data| prefab.load_template(&file, &data));
+        if let Err(e) = loaded {
+            log(format!(
+                "WARNING: {}: unable to read prefab {} from {}: {}",
+                mod_path.display(),
+                prefab.name,
+                file,
+                e
+            ));
+        }
+    }
+}
+
+fn read_mod(path: &Path) -> Result<RawsMod, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn apply_mod(raws: &mut Raws, raws_mod: RawsMod, source: &str) {
+    let sources = &mut raws.sources;
+    let remove = raws_mod.remove;
+    merge_section(
+        "items",
+        &mut raws.items,
+        raws_mod.items,
+        &remove.items,
+        source,
+        sources,
+    );
+    merge_section(
+        "mobs",
+        &mut raws.mobs,
+        raws_mod.mobs,
+        &remove.mobs,
+        source,
+        sources,
+    );
+    merge_section(
+        "props",
+        &mut raws.props,
+        raws_mod.props,
+        &remove.props,
+        source,
+        sources,
+    );
+    merge_section(
+        "spells",
+        &mut raws.spells,
+        raws_mod.spells,
+        &remove.spells,
+        source,
+        sources,
+    );
+    merge_section(
+        "spawn_table",
+        &mut raws.spawn_table,
+        raws_mod.spawn_table,
+        &remove.spawn_table,
+        source,
+        sources,
+    );
+    merge_section(
+        "loot_tables",
+        &mut raws.loot_tables,
+        raws_mod.loot_tables,
+        &remove.loot_tables,
+        source,
+        sources,
+    );
+    merge_section(
+        "faction_table",
+        &mut raws.faction_table,
+        raws_mod.faction_table,
+        &remove.faction_table,
+        source,
+        sources,
+    );
+    merge_section(
+        "weapon_traits",
+        &mut raws.weapon_traits,
+        raws_mod.weapon_traits,
+        &remove.weapon_traits,
+        source,
+        sources,
+    );
+    merge_section(
+        "room_kinds",
+        &mut raws.room_kinds,
+        raws_mod.room_kinds,
+        &remove.room_kinds,
+        source,
+        sources,
+    );
+    merge_section(
+        "levels",
+        &mut raws.levels,
+        raws_mod.levels,
+        &remove.levels,
+        source,
+        sources,
+    );
+    merge_section(
+        "builder_fragments",
+        &mut raws.builder_fragments,
+        raws_mod.builder_fragments,
+        &remove.builder_fragments,
+        source,
+        sources,
+    );
+    merge_section(
+        "branches",
+        &mut raws.branches,
+        raws_mod.branches,
+        &remove.branches,
+        source,
+        sources,
+    );
+    merge_section(
+        "prefabs",
+        &mut raws.prefabs,
+        raws_mod.prefabs,
+        &remove.prefabs,
+        source,
+        sources,
+    );
+}
+
+fn merge_section<T: Named>(
+    section: &str,
+    existing: &mut Vec<T>,
+    entries: Vec<T>,
+    removals: &[String],
+    source: &str,
+    sources: &mut RawSources,
+) {
+    for name in removals {
+        match existing.iter().position(|e| e.name() == name) {
+            Some(idx) => {
+                existing.remove(idx);
+                sources.forget(section, name);
+                log(format!("{}: removed {} [{}]", source, section, name));
+            }
+            None => log(format!(
+                "WARNING: {}: cannot remove unknown {} [{}]",
+                source, section, name
+            )),
+        }
+    }
+
+    for (i, entry) in entries.into_iter().enumerate() {
+        let name = entry.name().to_string();
+        match existing.iter_mut().find(|e| e.name() == name) {
+            Some(old) => {
+                log(format!(
+                    "{}: overrides {} [{}] from {}",
+                    source,
+                    section,
+                    name,
+                    sources.source_of(section, &name)
+                ));
+                *old = entry;
+            }
+            None => {
+                log(format!("{}: adds {} [{}]", source, section, name));
+                existing.push(entry);
+            }
+        }
+        sources.record(section, &name, source, i);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::prelude::*;
+use crate::tiled::{TiledLevel, TiledMap};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PrefabKind {
+    // A whole level, used as an initial builder.
+    Level,
+    // Stamped over part of a level at a fixed placement.
+    Section,
+    // A vault, dropped into open floor where it fits.
+    Room,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum HorizontalPlacement {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum VerticalPlacement {
+    Top,
+    Center,
+    Bottom,
+}
+
+// What a character in a template becomes. Glyphs that point a particular way
+// name the glyph they become when the prefab is turned a quarter clockwise,
+// or mirrored left to right.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PrefabGlyph {
+    pub tile: TileType,
+    pub spawn: Option<String>,
+    #[serde(default)]
+    pub start: bool,
+    pub rotated: Option<char>,
+    pub mirrored: Option<char>,
+}
+
+// The template is either given inline, one string per row, or read from
+// `file`: a text file of rows, or a REXPaint .xp whose layers are flattened
+// top to bottom. Characters are looked up in the prefab's own legend, then
+// the shared one. A level may instead be a Tiled map (.tmj or .json), which
+// brings its own terrain, spawns, rooms and corridors.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Prefab {
+    pub name: String,
+    pub kind: PrefabKind,
+    #[serde(default)]
+    pub template: Vec<String>,
+    pub file: Option<String>,
+    #[serde(default)]
+    pub legend: HashMap<char, PrefabGlyph>,
+    pub min_depth: Option<i32>,
+    pub max_depth: Option<i32>,
+    pub weight: Option<i32>,
+    pub theme: Option<MapTheme>,
+    // Whether vaults may be placed turned and/or mirrored.
+    #[serde(default)]
+    pub rotate: bool,
+    #[serde(default)]
+    pub mirror: bool,
+    pub x: Option<HorizontalPlacement>,
+    pub y: Option<VerticalPlacement>,
+    #[serde(skip)]
+    pub tiled: Option<TiledLevel>,
+}
+
+impl Prefab {
+    pub fn width(&self) -> usize {
+        self.template
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.template.len()
+    }
+
+    // Short rows are padded with spaces.
+    pub fn glyph_at(&self, x: usize, y: usize) -> char {
+        match self.template.get(y).and_then(|row| row.chars().nth(x)) {
+            // Non-breaking spaces sneak in from some editors.
+            Some('\u{a0}') | None => ' ',
+            Some(ch) => ch,
+        }
+    }
+
+    pub fn covers_depth(&self, depth: i32) -> bool {
+        depth >= self.min_depth.unwrap_or(0) && self.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    pub fn suits_theme(&self, theme: &MapTheme) -> bool {
+        self.theme.as_ref().is_none_or(|t| t == theme)
+    }
+
+    // Replaces the template with the contents of `file`.
+    pub fn load_template(&mut self, file: &str, data: &[u8]) -> io::Result<()> {
+        if file.ends_with(".tmj") || file.ends_with(".json") {
+            let tiled: TiledMap = serde_json::from_slice(data)?;
+            let level = tiled.to_level().map_err(io::Error::other)?;
+            self.tiled = Some(level);
+            return Ok(());
+        }
+        self.template = if file.ends_with(".xp") {
+            xp_rows(&XpFile::read(&mut &data[..])?)
+        } else {
+            String::from_utf8_lossy(data)
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        };
+        Ok(())
+    }
+}
+
+// REXPaint marks empty cells with a magenta background; those let the layers
+// below show through.
+fn xp_rows(xp: &XpFile) -> Vec<String> {
+    let width = xp.layers.iter().map(|l| l.width).max().unwrap_or(0);
+    let height = xp.layers.iter().map(|l| l.height).max().unwrap_or(0);
+    let mut glyphs = vec![' '; width * height];
+    for (n, layer) in xp.layers.iter().enumerate() {
+        for y in 0..layer.height {
+            for x in 0..layer.width {
+                let cell = layer.get(x, y).unwrap();
+                let transparent = cell.bg.r == 255 && cell.bg.g == 0 && cell.bg.b == 255;
+                if n == 0 || !transparent {
+                    glyphs[y * width + x] = to_char(cell.ch as u8);
+                }
+            }
+        }
+    }
+    glyphs
+        .chunks(width.max(1))
+        .map(|row| row.iter().collect())
+        .collect()
+}
+
+// The layout of prefabs.json.
+#[derive(Deserialize, Debug)]
+pub struct PrefabRaws {
+    pub legend: HashMap<char, PrefabGlyph>,
+    pub prefabs: Vec<Prefab>,
+}
@@ -1,8 +1,9 @@
 use super::Renderable;
+use crate::map::TileType;
 use serde::Deserialize;
 use std::collections::HashMap;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct Prop {
     pub name: String,
     pub renderable: Option<Renderable>,
@@ -10,12 +11,13 @@ pub struct Prop {
     pub blocks_tile: Option<bool>,
     pub blocks_visibility: Option<bool>,
     pub door_open: Option<bool>,
+    pub secret_passage: Option<TileType>,
     pub entry_trigger: Option<EntryTrigger>,
     pub always_visible: Option<bool>,
     pub light: Option<super::mob_structs::MobLight>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct EntryTrigger {
     pub effects: HashMap<String, String>,
 }
@@ -38,6 +38,13 @@ impl RawMaster {
                 loot_tables: Vec::new(),
                 faction_table: Vec::new(),
                 weapon_traits: Vec::new(),
+                room_kinds: Vec::new(),
+                levels: Vec::new(),
+                builder_fragments: Vec::new(),
+                branches: Vec::new(),
+                prefab_legend: HashMap::new(),
+                prefabs: Vec::new(),
+                sources: RawSources::default(),
             },
             item_index: HashMap::new(),
             mob_index: HashMap::new(),
@@ -50,45 +57,30 @@ impl RawMaster {
 
     pub fn load(&mut self, raws: Raws) {
         self.raws = raws;
-        let mut used_names = HashSet::new();
+
+        // Prefabs only need to carry their own legend from here on.
+        for prefab in self.raws.prefabs.iter_mut() {
+            for (ch, glyph) in self.raws.prefab_legend.iter() {
+                prefab.legend.entry(*ch).or_insert_with(|| glyph.clone());
+            }
+        }
         let mut items_to_build: Vec<NewMagicItem> = Vec::new();
 
         self.item_index = HashMap::new();
         for (i, item) in self.raws.items.iter().enumerate() {
-            if used_names.contains(&item.name) {
-                log(format!(
-                    "WARNING: duplicate item name in raws [{}]",
-                    &item.name
-                ))
-            }
             self.item_index.insert(item.name.clone(), i);
-            used_names.insert(item.name.clone());
 
             RawMaster::append_magic_template(&mut items_to_build, item);
         }
 
         self.mob_index = HashMap::new();
         for (i, mob) in self.raws.mobs.iter().enumerate() {
-            if used_names.contains(&mob.name) {
-                log(format!(
-                    "WARNING: duplicate mob name in raws [{}]",
-                    &mob.name
-                ))
-            }
             self.mob_index.insert(mob.name.clone(), i);
-            used_names.insert(mob.name.clone());
         }
 
         self.prop_index = HashMap::new();
         for (i, prop) in self.raws.props.iter().enumerate() {
-            if used_names.contains(&prop.name) {
-                log(format!(
-                    "WARNING: duplicate prop name in raws [{}]",
-                    &prop.name
-                ))
-            }
             self.prop_index.insert(prop.name.clone(), i);
-            used_names.insert(prop.name.clone());
         }
 
         self.loot_index = HashMap::new();
@@ -101,15 +93,6 @@ impl RawMaster {
             self.spell_index.insert(spell.name.clone(), i);
         }
 
-        for spawn in self.raws.spawn_table.iter() {
-            if !used_names.contains(&spawn.name) {
-                log(format!(
-                    "WARNING: Spawn table references unspecified entity [{}]",
-                    &spawn.name
-                ));
-            }
-        }
-
         for faction in self.raws.faction_table.iter() {
             let mut reactions: HashMap<String, Reaction> = HashMap::new();
             for other in faction.responses.iter() {
@@ -129,10 +112,88 @@ impl RawMaster {
         self.build_traited_weapons(&items_to_build);
     }
 
+    // Which mod (or base raw file) supplied a raw entry; `section` is its
+    // section name in the JSON, e.g. "mobs".
+    pub fn source_of(&self, section: &str, name: &str) -> &str {
+        self.raws.sources.source_of(section, name)
+    }
+
+    // Every level chain that can build this depth of the branch.
+    pub fn level_chains_for_depth(&self, branch: &str, depth: i32) -> Vec<&super::LevelChain> {
+        self.raws
+            .levels
+            .iter()
+            .filter(|l| l.branch() == branch && l.covers_depth(depth))
+            .collect()
+    }
+
+    pub fn level_chain(&self, name: &str) -> Option<&super::LevelChain> {
+        self.raws.levels.iter().find(|l| l.name == name)
+    }
+
+    pub fn builder_fragment(&self, name: &str) -> Option<&super::BuilderFragment> {
+        self.raws.builder_fragments.iter().find(|f| f.name == name)
+    }
+
+    pub fn branch(&self, name: &str) -> Option<&super::Branch> {
+        self.raws.branches.iter().find(|b| b.name == name)
+    }
+
+    pub fn prefab(&self, name: &str, kind: PrefabKind) -> Option<&Prefab> {
+        self.raws
+            .prefabs
+            .iter()
+            .find(|p| p.name == name && p.kind == kind)
+    }
+
+    pub fn prefabs_of(&self, kind: PrefabKind) -> Vec<&Prefab> {
+        self.raws
+            .prefabs
+            .iter()
+            .filter(|p| p.kind == kind)
+            .collect()
+    }
+
+    pub fn room_kinds_for_depth(&self, depth: i32) -> Vec<&super::RoomKind> {
+        self.raws
+            .room_kinds
+            .iter()
+            .filter(|k| depth >= k.min_depth && depth <= k.max_depth)
+            .collect()
+    }
+
+    pub fn branches(&self) -> &[super::Branch] {
+        &self.raws.branches
+    }
+
+    // The side branches entered from this depth of a branch.
+    pub fn branches_from(&self, branch: &str, depth: i32) -> Vec<&super::Branch> {
+        self.raws
+            .branches
+            .iter()
+            .filter(|b| b.parent() == branch && b.entrance_depth == depth)
+            .collect()
+    }
+
+    // How a named mob, item or prop is drawn.
+    pub fn render_of(&self, name: &str) -> Option<Render> {
+        let renderable = if let Some(idx) = self.mob_index.get(name) {
+            &self.raws.mobs[*idx].renderable
+        } else if let Some(idx) = self.item_index.get(name) {
+            &self.raws.items[*idx].renderable
+        } else if let Some(idx) = self.prop_index.get(name) {
+            &self.raws.props[*idx].renderable
+        } else {
+            return None;
+        };
+        renderable.as_ref().map(get_renderable)
+    }
+
     fn append_magic_template(
         items_to_build: &mut Vec<NewMagicItem>,
         item: &super::item_structs::Item,
     ) {
+        // Templates on anything else are reported by the validator.
         if let Some(template) = &item.template_magic {
             if item.weapon.is_some() || item.wearable.is_some() {
                 if template.include_cursed {
@@ -147,11 +208,6 @@ impl RawMaster {
                         bonus,
                     })
                 }
-            } else {
-                log(format!(
-                    "{} is marked as templated, but isn't a weapon or armor",
-                    &item.name
-                ));
             }
         }
     }
@@ -276,19 +332,25 @@ impl RawMaster {
     }
 }
 
-fn find_slot_for_equippable_item(tag: &str, raws: &RawMaster) -> EquipmentSlot {
-    if !raws.item_index.contains_key(tag) {
-        panic!("Trying to equip an unknown item: {}", tag);
-    }
+fn find_slot_for_equippable_item(tag: &str, raws: &RawMaster) -> Option<EquipmentSlot> {
+    let item = match raws.item_index.get(tag) {
+        Some(item_index) => &raws.raws.items[*item_index],
+        None => {
+            log(format!("WARNING: Trying to equip an unknown item: {}", tag));
+            return None;
+        }
+    };
 
-    let item_index = raws.item_index[tag];
-    let item = &raws.raws.items[item_index];
     if item.weapon.is_some() {
-        return EquipmentSlot::Melee;
+        return Some(EquipmentSlot::Melee);
     } else if let Some(wearable) = &item.wearable {
-        return string_to_slot(&wearable.slot);
+        return Some(string_to_slot(&wearable.slot));
     }
-    panic!("Trying to equip {}, but it has no slot tag.", tag);
+    log(format!(
+        "WARNING: Trying to equip {}, but it has no slot tag.",
+        tag
+    ));
+    None
 }
 
 fn string_to_slot(name: &str) -> EquipmentSlot {
@@ -307,27 +369,56 @@ fn string_to_slot(name: &str) -> EquipmentSlot {
     }
 }
 
-fn parse_particle_line(n: &str) -> SpawnParticleLine {
+// Particles are "glyph;#RRGGBB;lifetime in ms".
+fn parse_particle_parts(n: &str) -> Option<(FontCharType, RGB, f32)> {
     let tokens: Vec<_> = n.split(';').collect();
-    SpawnParticleLine {
-        glyph: to_cp437(tokens[0].chars().next().unwrap()),
-        color: RGB::from_hex(tokens[1]).expect("Bad RGB"),
-        lifetime_ms: tokens[2].parse::<f32>().unwrap(),
+    if tokens.len() != 3 {
+        return None;
     }
+    let glyph = to_cp437(tokens[0].chars().next()?);
+    let color = RGB::from_hex(tokens[1]).ok()?;
+    let lifetime_ms = tokens[2].parse::<f32>().ok()?;
+    Some((glyph, color, lifetime_ms))
 }
 
-fn parse_particle(n: &str) -> SpawnParticleBurst {
-    let tokens: Vec<_> = n.split(';').collect();
-    SpawnParticleBurst {
-        glyph: to_cp437(tokens[0].chars().next().unwrap()),
-        color: RGB::from_hex(tokens[1]).expect("Bad RGB"),
-        lifetime_ms: tokens[2].parse::<f32>().unwrap(),
-    }
+fn parse_particle_line(n: &str) -> Option<SpawnParticleLine> {
+    let (glyph, color, lifetime_ms) = parse_particle_parts(n)?;
+    Some(SpawnParticleLine {
+        glyph,
+        color,
+        lifetime_ms,
+    })
+}
+
+pub fn parse_tile_type(n: &str) -> Option<TileType> {
+    serde_json::from_value(serde_json::Value::String(n.to_string())).ok()
+}
+
+fn parse_particle(n: &str) -> Option<SpawnParticleBurst> {
+    let (glyph, color, lifetime_ms) = parse_particle_parts(n)?;
+    Some(SpawnParticleBurst {
+        glyph,
+        color,
+        lifetime_ms,
+    })
 }
 
 macro_rules! i32_component {
     ( $name:ident, $item:expr ) => {
-        $name($item.1.parse::<i32>().unwrap())
+        $item.1.parse::<i32>().ok().map($name)
+    };
+}
+
+// Effects whose value doesn't parse are left off the entity.
+macro_rules! add_parsed {
+    ( $e:expr, $effect:expr, $cmd:expr, $component:expr ) => {
+        match $component {
+            Some(component) => $cmd.add_component($e, component),
+            None => log(format!(
+                "WARNING: consumable effect {} has a bad value [{}]",
+                $effect.0, $effect.1
+            )),
+        }
     };
 }
 
@@ -336,42 +427,62 @@ macro_rules! apply_effects {
         for effect in $effects.iter() {
             let effect_name = effect.0.as_str();
             match effect_name {
-                "provides_healing" => $cmd.add_component(
+                "provides_healing" => add_parsed!(
                     $e,
-                    ProvidesHealing {
-                        amount: effect.1.parse::<i32>().unwrap(),
-                    },
+                    effect,
+                    $cmd,
+                    effect
+                        .1
+                        .parse::<i32>()
+                        .ok()
+                        .map(|amount| ProvidesHealing { amount })
                 ),
-                "ranged" => $cmd.add_component($e, i32_component!(Ranged, effect)),
-                "damage" => $cmd.add_component($e, i32_component!(Damage, effect)),
-                "area_of_effect" => $cmd.add_component($e, i32_component!(AreaOfEffect, effect)),
+                "ranged" => add_parsed!($e, effect, $cmd, i32_component!(Ranged, effect)),
+                "damage" => add_parsed!($e, effect, $cmd, i32_component!(Damage, effect)),
+                "area_of_effect" => {
+                    add_parsed!($e, effect, $cmd, i32_component!(AreaOfEffect, effect))
+                }
                 "confusion" => {
-                    $cmd.add_component($e, Confusion);
-                    $cmd.add_component($e, i32_component!(Duration, effect));
+                    if i32_component!(Duration, effect).is_some() {
+                        $cmd.add_component($e, Confusion);
+                    }
+                    add_parsed!($e, effect, $cmd, i32_component!(Duration, effect));
                 }
                 "magic_mapping" => $cmd.add_component($e, ProvidesDungeonMap),
                 "town_portal" => $cmd.add_component($e, TownPortal),
+                "trapdoor" => $cmd.add_component($e, Trapdoor),
                 "food" => $cmd.add_component($e, ProvidesFood),
                 "single_activation" => $cmd.add_component($e, SingleActivation),
-                "particle_line" => $cmd.add_component($e, parse_particle_line(&effect.1)),
-                "particle" => $cmd.add_component($e, parse_particle(&effect.1)),
+                "particle_line" => add_parsed!($e, effect, $cmd, parse_particle_line(&effect.1)),
+                "particle" => add_parsed!($e, effect, $cmd, parse_particle(&effect.1)),
                 "remove_curse" => $cmd.add_component($e, ProvidesRemoveCurse),
                 "identify" => $cmd.add_component($e, ProvidesIdentify),
-                "provides_mana" => $cmd.add_component($e, i32_component!(ProvidesMana, effect)),
+                "provides_mana" => {
+                    add_parsed!($e, effect, $cmd, i32_component!(ProvidesMana, effect))
+                }
                 "teach_spell" => $cmd.add_component($e, TeachSpell(effect.1.to_string())),
-                "slow" => $cmd.add_component(
+                "slow" => add_parsed!(
                     $e,
-                    Slow {
-                        initiative_penalty: effect.1.parse::<f32>().unwrap(),
-                    },
+                    effect,
+                    $cmd,
+                    effect
+                        .1
+                        .parse::<f32>()
+                        .ok()
+                        .map(|initiative_penalty| Slow { initiative_penalty })
                 ),
-                "damage_over_time" => $cmd.add_component(
+                "damage_over_time" => add_parsed!(
                     $e,
-                    DamageOverTime {
-                        damage: effect.1.parse::<i32>().unwrap(),
-                    },
+                    effect,
+                    $cmd,
+                    effect
+                        .1
+                        .parse::<i32>()
+                        .ok()
+                        .map(|damage| DamageOverTime { damage })
                 ),
                 "target_self" => $cmd.add_component($e, AlwaysTargetsSelf),
+                "dig" => add_parsed!($e, effect, $cmd, parse_tile_type(&effect.1).map(Digs)),
                 _ => log(format!(
                     "Warning: consumable effect {} not implemented.",
                     effect_name
@@ -400,8 +511,7 @@ pub fn spawn_named_spell(
     None
 }
 
-pub fn spawn_all_spells(commands: &mut CommandBuffer) {
-    let raws = &RAWS.lock().unwrap();
+pub fn spawn_all_spells(raws: &RawMaster, commands: &mut CommandBuffer) {
     for spell in raws.raws.spells.iter() {
         spawn_named_spell(raws, &spell.name, commands);
     }
@@ -526,6 +636,10 @@ pub fn spawn_named_item(
         }
     }
 
+    if let Some(tile) = item_template.digs {
+        commands.add_component(entity, Digs(tile));
+    }
+
     if let Some(ab) = &item_template.attributes {
         commands.add_component(
             entity,
@@ -546,6 +660,7 @@ pub fn spawn_named_mob(
     key: &str,
     pos: SpawnType,
     dm: &MasterDungeonMap,
+    rng: &mut RandomNumberGenerator,
     commands: &mut CommandBuffer,
 ) -> Option<Entity> {
     if !raws.mob_index.contains_key(key) {
@@ -605,56 +720,18 @@ pub fn spawn_named_mob(
 
     commands.add_component(entity, Initiative { current: 2 });
 
-    let mut attr = Attributes::default();
-    let mut mob_fitness = 11;
-    let mut mob_int = 11;
-    if let Some(might) = mob_template.attributes.might {
-        attr.might = Attribute {
-            base: might,
-            modifiers: 0,
-            bonus: attr_bonus(might),
-        }
-    }
-    if let Some(fitness) = mob_template.attributes.fitness {
-        attr.fitness = Attribute {
-            base: fitness,
-            modifiers: 0,
-            bonus: attr_bonus(fitness),
-        };
-        mob_fitness = fitness;
-    }
-    if let Some(quickness) = mob_template.attributes.quickness {
-        attr.quickness = Attribute {
-            base: quickness,
-            modifiers: 0,
-            bonus: attr_bonus(quickness),
-        }
-    }
-    if let Some(intelligence) = mob_template.attributes.intelligence {
-        attr.intelligence = Attribute {
-            base: intelligence,
-            modifiers: 0,
-            bonus: attr_bonus(intelligence),
-        };
-        mob_int = intelligence;
-    }
-    commands.add_component(entity, attr);
+    commands.add_component(entity, mob_attributes(mob_template));
 
-    let mob_level = mob_template.level.unwrap_or(1);
-    let mob_hp = npc_hp(mob_fitness, mob_level);
-    let mob_mana = mana_at_level(mob_int, mob_level);
+    let (mob_hp, mob_mana) = mob_pool_maxima(mob_template);
     let mob_gold = mob_template
         .gold
         .as_ref()
-        .map(|gold| {
-            let mut rng = RandomNumberGenerator::new();
-            rng.roll_str(gold).map_or(0.0, |v| v as f32)
-        })
+        .map(|gold| rng.roll_str(gold).map_or(0.0, |v| v as f32))
         .unwrap_or(0.0);
     commands.add_component(
         entity,
         Pools {
-            level: mob_level,
+            level: mob_template.level.unwrap_or(1),
             xp: 0,
             hit_points: Pool {
                 current: mob_hp,
@@ -671,48 +748,22 @@ pub fn spawn_named_mob(
         },
     );
 
-    let mut skills = Skills::default();
-    if let Some(mobskills) = &mob_template.skills {
-        for (name, value) in mobskills.iter() {
-            match name.as_ref() {
-                "Melee" => {
-                    skills.0.insert(Skill::Melee, *value);
-                }
-                "Defense" => {
-                    skills.0.insert(Skill::Defense, *value);
-                }
-                "Magic" => {
-                    skills.0.insert(Skill::Magic, *value);
-                }
-                _ => {
-                    log(format!("Unknown skill referenced: [{}]", name));
-                }
-            }
-        }
-    }
-    commands.add_component(entity, skills);
+    commands.add_component(entity, mob_skills(mob_template));
 
     if let Some(wielding) = &mob_template.equipped {
         for tag in wielding.iter() {
-            spawn_named_entity(raws, tag, SpawnType::Equipped { by: entity }, dm, commands);
+            spawn_named_entity(
+                raws,
+                tag,
+                SpawnType::Equipped { by: entity },
+                dm,
+                rng,
+                commands,
+            );
         }
     }
 
-    if let Some(natural) = &mob_template.natural {
-        let mut nature = NaturalAttackDefense {
-            armor_class: natural.armor_class.unwrap_or(0),
-            attacks: Vec::new(),
-        };
-        if let Some(attacks) = &natural.attacks {
-            for nattack in attacks.iter() {
-                let attack = NaturalAttack {
-                    name: nattack.name.clone(),
-                    hit_bonus: nattack.hit_bonus,
-                    damage_die: nattack.damage.clone(),
-                };
-                nature.attacks.push(attack);
-            }
-        }
+    if let Some(nature) = mob_natural_attacks(mob_template) {
         commands.add_component(entity, nature);
     }
 
@@ -720,6 +771,10 @@ pub fn spawn_named_mob(
         commands.add_component(entity, LootTable(loot.clone()));
     }
 
+    if let Some(tile) = mob_template.digs {
+        commands.add_component(entity, Digs(tile));
+    }
+
     if let Some(light) = &mob_template.light {
         commands.add_component(
             entity,
@@ -780,6 +835,202 @@ pub fn spawn_named_mob(
     Some(entity)
 }
 
+fn mob_attributes(mob_template: &super::mob_structs::Mob) -> Attributes {
+    let mut attr = Attributes::default();
+    if let Some(might) = mob_template.attributes.might {
+        attr.might = Attribute {
+            base: might,
+            modifiers: 0,
+            bonus: attr_bonus(might),
+        }
+    }
+    if let Some(fitness) = mob_template.attributes.fitness {
+        attr.fitness = Attribute {
+            base: fitness,
+            modifiers: 0,
+            bonus: attr_bonus(fitness),
+        };
+    }
+    if let Some(quickness) = mob_template.attributes.quickness {
+        attr.quickness = Attribute {
+            base: quickness,
+            modifiers: 0,
+            bonus: attr_bonus(quickness),
+        }
+    }
+    if let Some(intelligence) = mob_template.attributes.intelligence {
+        attr.intelligence = Attribute {
+            base: intelligence,
+            modifiers: 0,
+            bonus: attr_bonus(intelligence),
+        };
+    }
+    attr
+}
+
+// Maximum hit points and mana.
+fn mob_pool_maxima(mob_template: &super::mob_structs::Mob) -> (i32, i32) {
+    let mob_fitness = mob_template.attributes.fitness.unwrap_or(11);
+    let mob_int = mob_template.attributes.intelligence.unwrap_or(11);
+    let mob_level = mob_template.level.unwrap_or(1);
+    (
+        npc_hp(mob_fitness, mob_level),
+        mana_at_level(mob_int, mob_level),
+    )
+}
+
+fn mob_skills(mob_template: &super::mob_structs::Mob) -> Skills {
+    let mut skills = Skills::default();
+    if let Some(mobskills) = &mob_template.skills {
+        for (name, value) in mobskills.iter() {
+            match name.as_ref() {
+                "Melee" => {
+                    skills.0.insert(Skill::Melee, *value);
+                }
+                "Defense" => {
+                    skills.0.insert(Skill::Defense, *value);
+                }
+                "Magic" => {
+                    skills.0.insert(Skill::Magic, *value);
+                }
+                _ => {
+                    log(format!("Unknown skill referenced: [{}]", name));
+                }
+            }
+        }
+    }
+    skills
+}
+
+fn mob_natural_attacks(mob_template: &super::mob_structs::Mob) -> Option<NaturalAttackDefense> {
+    mob_template.natural.as_ref().map(|natural| {
+        let mut nature = NaturalAttackDefense {
+            armor_class: natural.armor_class.unwrap_or(0),
+            attacks: Vec::new(),
+        };
+        if let Some(attacks) = &natural.attacks {
+            for nattack in attacks.iter() {
+                let attack = NaturalAttack {
+                    name: nattack.name.clone(),
+                    hit_bonus: nattack.hit_bonus,
+                    damage_die: nattack.damage.clone(),
+                };
+                nature.attacks.push(attack);
+            }
+        }
+        nature
+    })
+}
+
+// Brings entities that are already spawned in line with reloaded raws. Only
+// entities whose definition changed are touched; they keep their position,
+// inventory and gold, and wounded mobs keep the same fraction of their hit
+// points. Returns how many entities were updated.
+pub fn refresh_spawned_entities(ecs: &mut World, old: &RawMaster, new: &RawMaster) -> usize {
+    let spawned: Vec<(Entity, String)> = <(Entity, &Name)>::query()
+        .filter(!component::<Player>() & !component::<SpellTemplate>())
+        .iter(ecs)
+        .map(|(entity, name)| (*entity, name.0.clone()))
+        .collect();
+
+    let mut updated = 0;
+    for (entity, name) in spawned {
+        let mut entry = ecs.entry(entity).unwrap();
+        if let (Some(before), Some(after)) = (old.mob_index.get(&name), new.mob_index.get(&name)) {
+            let template = &new.raws.mobs[*after];
+            if old.raws.mobs[*before] != *template {
+                refresh_mob(&mut entry, template);
+                updated += 1;
+            }
+        } else if let (Some(before), Some(after)) =
+            (old.item_index.get(&name), new.item_index.get(&name))
+        {
+            let template = &new.raws.items[*after];
+            if old.raws.items[*before] != *template {
+                entry.add_component(Item {
+                    initiative_penalty: template.initiative_penalty.unwrap_or(0.0),
+                    weight_lbs: template.weight_lbs.unwrap_or(0.0),
+                    base_value: template.base_value.unwrap_or(0.0),
+                });
+                if let Some(renderable) = &template.renderable {
+                    entry.add_component(get_renderable(renderable));
+                }
+                updated += 1;
+            }
+        } else if let (Some(before), Some(after)) =
+            (old.prop_index.get(&name), new.prop_index.get(&name))
+        {
+            let template = &new.raws.props[*after];
+            if old.raws.props[*before] != *template {
+                if let Some(renderable) = &template.renderable {
+                    entry.add_component(get_renderable(renderable));
+                }
+                updated += 1;
+            }
+        }
+    }
+
+    // Carried loads (and hence encumbrance) may have changed.
+    <Entity>::query()
+        .filter(component::<Pools>())
+        .iter(ecs)
+        .copied()
+        .collect::<Vec<Entity>>()
+        .into_iter()
+        .for_each(|entity| ecs.entry(entity).unwrap().add_component(EquipmentChanged));
+
+    updated
+}
+
+fn refresh_mob(entry: &mut legion::world::Entry, mob_template: &super::mob_structs::Mob) {
+    if let Some(renderable) = &mob_template.renderable {
+        entry.add_component(get_renderable(renderable));
+        match (renderable.x_size, renderable.y_size) {
+            (Some(x), Some(y)) => entry.add_component(TileSize { x, y }),
+            _ => entry.remove_component::<TileSize>(),
+        }
+    }
+
+    match &mob_template.quips {
+        Some(quips) => entry.add_component(Quips(quips.clone())),
+        None => entry.remove_component::<Quips>(),
+    }
+    match &mob_template.loot_table {
+        Some(loot) => entry.add_component(LootTable(loot.clone())),
+        None => entry.remove_component::<LootTable>(),
+    }
+    match mob_natural_attacks(mob_template) {
+        Some(nature) => entry.add_component(nature),
+        None => entry.remove_component::<NaturalAttackDefense>(),
+    }
+    entry.add_component(mob_skills(mob_template));
+
+    // Equipment bonuses are added back by the encumbrance system.
+    entry.add_component(mob_attributes(mob_template));
+
+    if let Ok(fov) = entry.get_component_mut::<FieldOfView>() {
+        fov.radius = mob_template.vision_range;
+        fov.is_dirty = true;
+    }
+
+    if let Ok(pools) = entry.get_component_mut::<Pools>() {
+        let (hp, mana) = mob_pool_maxima(mob_template);
+        pools.level = mob_template.level.unwrap_or(1);
+        rescale_pool(&mut pools.hit_points, hp);
+        rescale_pool(&mut pools.mana, mana);
+    }
+}
+
+fn rescale_pool(pool: &mut Pool, max: i32) {
+    if pool.max > 0 {
+        let ratio = pool.current as f32 / pool.max as f32;
+        pool.current = i32::min((ratio * max as f32).round() as i32, max);
+    } else {
+        pool.current = max;
+    }
+    pool.max = max;
+}
+
 pub fn spawn_named_prop(
     raws: &RawMaster,
     key: &str,
@@ -814,6 +1065,9 @@ pub fn spawn_named_prop(
     if let Some(door_open) = &template.door_open {
         commands.add_component(entity, Door { open: *door_open });
     }
+    if let Some(opens_to) = template.secret_passage {
+        commands.add_component(entity, SecretPassage { opens_to });
+    }
     if let Some(entry_trigger) = &template.entry_trigger {
         commands.add_component(entity, EntryTrigger);
         apply_effects!(entity, entry_trigger.effects, commands);
@@ -844,12 +1098,13 @@ pub fn spawn_named_entity(
     key: &str,
     pos: SpawnType,
     dm: &MasterDungeonMap,
+    rng: &mut RandomNumberGenerator,
     commands: &mut CommandBuffer,
 ) -> Option<Entity> {
     if raws.item_index.contains_key(key) {
         spawn_named_item(raws, key, pos, dm, commands)
     } else if raws.mob_index.contains_key(key) {
-        spawn_named_mob(raws, key, pos, dm, commands)
+        spawn_named_mob(raws, key, pos, dm, rng, commands)
     } else if raws.prop_index.contains_key(key) {
         spawn_named_prop(raws, key, pos, commands)
     } else {
@@ -942,8 +1197,7 @@ pub fn get_item_color(ecs: &SubWorld, item: Entity, dm: &MasterDungeonMap) -> Co
     ColorPair::new(fg, BLACK)
 }
 
-pub fn get_scroll_tags() -> Vec<String> {
-    let raws = &super::RAWS.lock().unwrap();
+pub fn get_scroll_tags(raws: &RawMaster) -> Vec<String> {
     let mut result = Vec::new();
 
     for item in raws.raws.items.iter() {
@@ -957,8 +1211,7 @@ pub fn get_scroll_tags() -> Vec<String> {
     result
 }
 
-pub fn get_potion_tags() -> Vec<String> {
-    let raws = &super::RAWS.lock().unwrap();
+pub fn get_potion_tags(raws: &RawMaster) -> Vec<String> {
     let mut result = Vec::new();
 
     for item in raws.raws.items.iter() {
@@ -972,8 +1225,7 @@ pub fn get_potion_tags() -> Vec<String> {
     result
 }
 
-pub fn get_wand_tags() -> Vec<String> {
-    let raws = &super::RAWS.lock().unwrap();
+pub fn get_wand_tags(raws: &RawMaster) -> Vec<String> {
     let mut result = Vec::new();
 
     for item in raws.raws.items.iter() {
@@ -1017,8 +1269,7 @@ pub fn get_item_display_name(ecs: &SubWorld, item: Entity, dm: &MasterDungeonMap
     }
 }
 
-pub fn is_tag_magic(tag: &str) -> bool {
-    let raws = &RAWS.lock().unwrap();
+pub fn is_tag_magic(raws: &RawMaster, tag: &str) -> bool {
     if let Some(idx) = raws.item_index.get(tag) {
         let item_template = &raws.raws.items[*idx];
         item_template.magic.is_some()
@@ -1060,13 +1311,10 @@ fn set_position(
         SpawnType::Carried { by } => commands.add_component(*entity, Carried(by)),
         SpawnType::Equipped { by } => {
             commands.add_component(*entity, Carried(by));
-            commands.add_component(
-                *entity,
-                Equipped {
-                    owner: by,
-                    slot: find_slot_for_equippable_item(tag, raws),
-                },
-            );
+            // Something that can't be worn is only carried.
+            if let Some(slot) = find_slot_for_equippable_item(tag, raws) {
+                commands.add_component(*entity, Equipped { owner: by, slot });
+            }
         }
     }
 }
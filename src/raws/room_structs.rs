@@ -0,0 +1,40 @@
+use crate::prelude::*;
+
+// A purpose the SpecialRooms builder can give a room: what fills it, how it
+// is dressed, and what the player is told on first walking in.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomKind {
+    pub name: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    pub max_depth: i32,
+    pub message: String,
+    // How many things are rolled, on `spawns` or else on the level's own
+    // spawn table limited to `from_depth_table` ("items" or "mobs").
+    pub spawn_count: Option<String>,
+    #[serde(default)]
+    pub spawns: Vec<RoomSpawn>,
+    pub from_depth_table: Option<String>,
+    #[serde(default)]
+    pub decorations: Vec<RoomDecoration>,
+    // What the room's bare floor becomes.
+    pub floor: Option<TileType>,
+    // Nothing more than a step away can be seen in the room.
+    #[serde(default)]
+    pub dark: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomSpawn {
+    pub name: String,
+    pub weight: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomDecoration {
+    pub name: String,
+    pub count: String,
+    // Kept to the edges of the room, clear of its ways in and out.
+    #[serde(default)]
+    pub along_walls: bool,
+}
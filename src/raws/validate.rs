@@ -0,0 +1,1001 @@
+use super::{BuilderStep, Raws, Renderable};
+use crate::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+const SLOTS: [&str; 7] = ["Shield", "Head", "Torso", "Legs", "Feet", "Hands", "Melee"];
+const MOVEMENTS: [&str; 4] = ["static", "random", "random_waypoint", "immobile"];
+const SKILLS: [&str; 3] = ["Melee", "Defense", "Magic"];
+const REACTIONS: [&str; 3] = ["ignore", "flee", "attack"];
+const MAGIC_CLASSES: [&str; 3] = ["common", "rare", "legendary"];
+const WEAPON_ATTRIBUTES: [&str; 2] = ["Might", "Quickness"];
+const PROC_TARGETS: [&str; 2] = ["Self", "Target"];
+const DEPTH_TABLES: [&str; 2] = ["items", "mobs"];
+
+pub struct RawProblem {
+    // The file that supplied the entry: one of the base raws or a mod.
+    pub source: String,
+    pub entity: String,
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for RawProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} [{}]: {}",
+            self.source, self.path, self.entity, self.message
+        )
+    }
+}
+
+// Checks everything the spawning code would otherwise only discover (often
+// by panicking) when the entry is first used in a game.
+pub fn validate_raws(raws: &Raws) -> Vec<RawProblem> {
+    let mut v = Validator::new(raws);
+    v.check_names();
+    v.check_spawn_table();
+    v.check_items();
+    v.check_mobs();
+    v.check_props();
+    v.check_spells();
+    v.check_loot_tables();
+    v.check_factions();
+    v.check_weapon_traits();
+    v.check_room_kinds();
+    v.check_levels();
+    v.check_branches();
+    v.check_prefabs();
+    v.problems
+}
+
+// An item's own name, plus those of the magic and traited variants that
+// RawMaster generates from its template.
+fn item_names<'a>(
+    raws: &Raws,
+    item: &'a super::item_structs::Item,
+) -> Vec<(String, &'a super::item_structs::Item)> {
+    let mut names = vec![item.name.clone()];
+    if let Some(template) = &item.template_magic {
+        if item.weapon.is_some() || item.wearable.is_some() {
+            if template.include_cursed {
+                names.push(format!("{} -1", item.name));
+            }
+            for bonus in template.bonus_min..=template.bonus_max {
+                let name = format!("{} +{}", item.name, bonus);
+                if item.weapon.is_some() && bonus > 0 {
+                    for weapon_trait in raws.weapon_traits.iter() {
+                        names.push(format!("{} {}", weapon_trait.name, name));
+                    }
+                }
+                names.push(name);
+            }
+        }
+    }
+    names.into_iter().map(|name| (name, item)).collect()
+}
+
+struct Validator<'a> {
+    raws: &'a Raws,
+    entities: HashSet<String>,
+    items: HashMap<String, &'a super::item_structs::Item>,
+    spells: HashSet<&'a str>,
+    loot_tables: HashSet<&'a str>,
+    factions: HashSet<&'a str>,
+    vendor_categories: HashSet<&'a str>,
+    problems: Vec<RawProblem>,
+
+    // The entry currently being checked.
+    section: &'static str,
+    entity: &'a str,
+}
+
+impl<'a> Validator<'a> {
+    fn new(raws: &'a Raws) -> Self {
+        let items: HashMap<String, &super::item_structs::Item> = raws
+            .items
+            .iter()
+            .flat_map(|i| item_names(raws, i))
+            .collect();
+        let entities = items
+            .keys()
+            .cloned()
+            .chain(raws.mobs.iter().map(|m| m.name.clone()))
+            .chain(raws.props.iter().map(|p| p.name.clone()))
+            .collect();
+        let vendor_categories = raws
+            .items
+            .iter()
+            .filter(|i| i.base_value.is_some())
+            .filter_map(|i| i.vendor_category.as_deref())
+            .collect();
+
+        Self {
+            raws,
+            entities,
+            items,
+            spells: raws.spells.iter().map(|s| s.name.as_str()).collect(),
+            loot_tables: raws.loot_tables.iter().map(|l| l.name.as_str()).collect(),
+            factions: raws.faction_table.iter().map(|f| f.name.as_str()).collect(),
+            vendor_categories,
+            problems: Vec::new(),
+            section: "",
+            entity: "",
+        }
+    }
+
+    // Starts on a new entry, returning its JSON path in the file it came from.
+    fn enter(&mut self, section: &'static str, entity: &'a str, index: usize) -> String {
+        self.section = section;
+        self.entity = entity;
+        let index = self.raws.sources.index_of(section, entity).unwrap_or(index);
+        format!("{}[{}]", section, index)
+    }
+
+    fn problem<S: ToString>(&mut self, path: String, message: S) {
+        self.problems.push(RawProblem {
+            source: self
+                .raws
+                .sources
+                .source_of(self.section, self.entity)
+                .to_string(),
+            entity: self.entity.to_string(),
+            path,
+            message: message.to_string(),
+        });
+    }
+
+    fn one_of(&mut self, path: String, value: &str, allowed: &[&str]) {
+        if !allowed.contains(&value) {
+            self.problem(
+                path,
+                format!("[{}] must be one of: {}", value, allowed.join(", ")),
+            );
+        }
+    }
+
+    fn dice(&mut self, path: String, dice: &str) {
+        if parse_dice_string(dice).is_err() {
+            self.problem(path, format!("[{}] is not a dice expression", dice));
+        }
+    }
+
+    fn color(&mut self, path: String, color: &str) {
+        if RGB::from_hex(color).is_err() {
+            self.problem(path, format!("[{}] is not a #RRGGBB colour", color));
+        }
+    }
+
+    fn renderable(&mut self, path: String, renderable: &Renderable) {
+        if renderable.glyph.chars().next().is_none() {
+            self.problem(format!("{}.glyph", path), "glyph is empty");
+        }
+        self.color(format!("{}.fg", path), &renderable.fg);
+        self.color(format!("{}.bg", path), &renderable.bg);
+    }
+
+    fn effects(&mut self, path: String, effects: &HashMap<String, String>) {
+        let mut names: Vec<&String> = effects.keys().collect();
+        names.sort();
+        for name in names {
+            let value = &effects[name];
+            let path = format!("{}.{}", path, name);
+            match name.as_str() {
+                "provides_healing" | "ranged" | "damage" | "area_of_effect" | "confusion"
+                | "provides_mana" | "damage_over_time" => {
+                    if value.parse::<i32>().is_err() {
+                        self.problem(path, format!("[{}] is not a whole number", value));
+                    }
+                }
+                "slow" => {
+                    if value.parse::<f32>().is_err() {
+                        self.problem(path, format!("[{}] is not a number", value));
+                    }
+                }
+                "particle" | "particle_line" => self.particle(path, value),
+                "dig" => match parse_tile_type(value) {
+                    Some(tile) => self.digs(path, tile),
+                    None => self.problem(path, format!("unknown tile [{}]", value)),
+                },
+                "teach_spell" => {
+                    if !self.spells.contains(value.as_str()) {
+                        self.problem(path, format!("unknown spell [{}]", value));
+                    }
+                }
+                "magic_mapping" | "town_portal" | "trapdoor" | "food" | "single_activation"
+                | "remove_curse" | "identify" | "target_self" => {}
+                _ => self.problem(path, format!("unknown effect [{}]", name)),
+            }
+        }
+    }
+
+    // Digging out rock to leave something that can't be walked on would trap
+    // whoever did it.
+    fn digs(&mut self, path: String, tile: TileType) {
+        if !tile.is_walkable() {
+            self.problem(path, format!("[{:?}] is not a walkable tile", tile));
+        }
+    }
+
+    // Particles are "glyph;#RRGGBB;lifetime in ms".
+    fn particle(&mut self, path: String, particle: &str) {
+        let tokens: Vec<&str> = particle.split(';').collect();
+        if tokens.len() != 3 {
+            self.problem(
+                path,
+                format!("[{}] should be \"glyph;#RRGGBB;lifetime\"", particle),
+            );
+            return;
+        }
+        if tokens[0].is_empty() {
+            self.problem(path.clone(), "particle glyph is empty");
+        }
+        self.color(path.clone(), tokens[1]);
+        if tokens[2].parse::<f32>().is_err() {
+            self.problem(path, format!("[{}] is not a lifetime", tokens[2]));
+        }
+    }
+
+    fn check_names(&mut self) {
+        let raws = self.raws;
+
+        // Items, mobs and props share one set of names.
+        let mut used = HashSet::new();
+        let entities = raws
+            .items
+            .iter()
+            .map(|i| ("items", i.name.as_str()))
+            .enumerate()
+            .chain(
+                raws.mobs
+                    .iter()
+                    .map(|m| ("mobs", m.name.as_str()))
+                    .enumerate(),
+            )
+            .chain(
+                raws.props
+                    .iter()
+                    .map(|p| ("props", p.name.as_str()))
+                    .enumerate(),
+            );
+        for (i, (section, name)) in entities {
+            let path = self.enter(section, name, i);
+            if !used.insert(name) {
+                self.problem(path, "name is used by more than one item, mob or prop");
+            }
+        }
+
+        let others: [(&'static str, Vec<&str>); 9] = [
+            (
+                "spells",
+                raws.spells.iter().map(|s| s.name.as_str()).collect(),
+            ),
+            (
+                "loot_tables",
+                raws.loot_tables.iter().map(|l| l.name.as_str()).collect(),
+            ),
+            (
+                "faction_table",
+                raws.faction_table.iter().map(|f| f.name.as_str()).collect(),
+            ),
+            (
+                "weapon_traits",
+                raws.weapon_traits.iter().map(|t| t.name.as_str()).collect(),
+            ),
+            (
+                "room_kinds",
+                raws.room_kinds.iter().map(|k| k.name.as_str()).collect(),
+            ),
+            (
+                "levels",
+                raws.levels.iter().map(|l| l.name.as_str()).collect(),
+            ),
+            (
+                "builder_fragments",
+                raws.builder_fragments
+                    .iter()
+                    .map(|f| f.name.as_str())
+                    .collect(),
+            ),
+            (
+                "branches",
+                raws.branches.iter().map(|b| b.name.as_str()).collect(),
+            ),
+            (
+                "prefabs",
+                raws.prefabs.iter().map(|p| p.name.as_str()).collect(),
+            ),
+        ];
+        for (section, names) in others {
+            let mut used = HashSet::new();
+            for (i, name) in names.into_iter().enumerate() {
+                let path = self.enter(section, name, i);
+                if !used.insert(name) {
+                    self.problem(path, "name is used more than once");
+                }
+            }
+        }
+    }
+
+    fn check_spawn_table(&mut self) {
+        for (i, spawn) in self.raws.spawn_table.iter().enumerate() {
+            let path = self.enter("spawn_table", &spawn.name, i);
+            if !self.entities.contains(&spawn.name) {
+                self.problem(path.clone(), "no item, mob or prop has this name");
+            }
+            if spawn.min_depth > spawn.max_depth {
+                self.problem(path, "min_depth is deeper than max_depth");
+            }
+        }
+    }
+
+    fn check_items(&mut self) {
+        for (i, item) in self.raws.items.iter().enumerate() {
+            let path = self.enter("items", &item.name, i);
+            if let Some(renderable) = &item.renderable {
+                self.renderable(format!("{}.renderable", path), renderable);
+            }
+            if let Some(consumable) = &item.consumable {
+                self.effects(format!("{}.consumable.effects", path), &consumable.effects);
+            }
+            if let Some(weapon) = &item.weapon {
+                let path = format!("{}.weapon", path);
+                if weapon.range != "melee" && weapon.range.parse::<i32>().is_err() {
+                    self.problem(
+                        format!("{}.range", path),
+                        format!("[{}] must be \"melee\" or a number", weapon.range),
+                    );
+                }
+                self.one_of(
+                    format!("{}.attribute", path),
+                    &weapon.attribute,
+                    &WEAPON_ATTRIBUTES,
+                );
+                self.dice(format!("{}.base_damage", path), &weapon.base_damage);
+                if let Some(effects) = &weapon.proc_effects {
+                    self.effects(format!("{}.proc_effects", path), effects);
+                }
+                match (&weapon.proc_chance, &weapon.proc_target) {
+                    (Some(_), None) => self.problem(
+                        format!("{}.proc_target", path),
+                        "proc_chance is set, so proc_target is required",
+                    ),
+                    (_, Some(target)) => {
+                        self.one_of(format!("{}.proc_target", path), target, &PROC_TARGETS)
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(wearable) = &item.wearable {
+                self.one_of(format!("{}.wearable.slot", path), &wearable.slot, &SLOTS);
+            }
+            if let Some(magic) = &item.magic {
+                self.one_of(
+                    format!("{}.magic.class", path),
+                    &magic.class,
+                    &MAGIC_CLASSES,
+                );
+            }
+            if let Some(tile) = item.digs {
+                let path = format!("{}.digs", path);
+                if item.weapon.is_none() && item.wearable.is_none() {
+                    self.problem(path.clone(), "only equipment can dig");
+                }
+                self.digs(path, tile);
+            }
+            if let Some(template) = &item.template_magic {
+                let path = format!("{}.template_magic", path);
+                if item.weapon.is_none() && item.wearable.is_none() {
+                    self.problem(path.clone(), "only weapons and armor can be templated");
+                }
+                if template.bonus_min > template.bonus_max {
+                    self.problem(path, "bonus_min is larger than bonus_max");
+                }
+            }
+        }
+    }
+
+    fn check_mobs(&mut self) {
+        for (i, mob) in self.raws.mobs.iter().enumerate() {
+            let path = self.enter("mobs", &mob.name, i);
+            if let Some(renderable) = &mob.renderable {
+                self.renderable(format!("{}.renderable", path), renderable);
+            }
+            self.one_of(format!("{}.movement", path), &mob.movement, &MOVEMENTS);
+            if let Some(tile) = mob.digs {
+                self.digs(format!("{}.digs", path), tile);
+            }
+            if let Some(skills) = &mob.skills {
+                let mut names: Vec<&String> = skills.keys().collect();
+                names.sort();
+                for skill in names {
+                    self.one_of(format!("{}.skills.{}", path, skill), skill, &SKILLS);
+                }
+            }
+            for (n, tag) in mob.equipped.iter().flatten().enumerate() {
+                let path = format!("{}.equipped[{}]", path, n);
+                match self.items.get(tag) {
+                    None => self.problem(path, format!("unknown item [{}]", tag)),
+                    Some(item) if item.weapon.is_none() && item.wearable.is_none() => {
+                        self.problem(path, format!("[{}] can't be equipped", tag))
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(natural) = &mob.natural {
+                for (n, attack) in natural.attacks.iter().flatten().enumerate() {
+                    self.dice(
+                        format!("{}.natural.attacks[{}].damage", path, n),
+                        &attack.damage,
+                    );
+                }
+            }
+            if let Some(loot) = &mob.loot_table {
+                if !self.loot_tables.contains(loot.as_str()) {
+                    self.problem(
+                        format!("{}.loot_table", path),
+                        format!("unknown loot table [{}]", loot),
+                    );
+                }
+            }
+            if let Some(light) = &mob.light {
+                self.color(format!("{}.light.color", path), &light.color);
+            }
+            if let Some(faction) = &mob.faction {
+                if !self.factions.contains(faction.as_str()) {
+                    self.problem(
+                        format!("{}.faction", path),
+                        format!("unknown faction [{}]", faction),
+                    );
+                }
+            }
+            if let Some(gold) = &mob.gold {
+                self.dice(format!("{}.gold", path), gold);
+            }
+            for (n, category) in mob.vendor.iter().flatten().enumerate() {
+                if !self.vendor_categories.contains(category.as_str()) {
+                    self.problem(
+                        format!("{}.vendor[{}]", path, n),
+                        format!("no item with a value is sold as [{}]", category),
+                    );
+                }
+            }
+            let abilities = [("abilities", &mob.abilities), ("on_death", &mob.on_death)];
+            for (list, abilities) in abilities {
+                for (n, ability) in abilities.iter().flatten().enumerate() {
+                    if !self.spells.contains(ability.spell.as_str()) {
+                        self.problem(
+                            format!("{}.{}[{}].spell", path, list, n),
+                            format!("unknown spell [{}]", ability.spell),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_props(&mut self) {
+        for (i, prop) in self.raws.props.iter().enumerate() {
+            let path = self.enter("props", &prop.name, i);
+            if let Some(renderable) = &prop.renderable {
+                self.renderable(format!("{}.renderable", path), renderable);
+            }
+            if let Some(trigger) = &prop.entry_trigger {
+                self.effects(format!("{}.entry_trigger.effects", path), &trigger.effects);
+            }
+            if let Some(light) = &prop.light {
+                self.color(format!("{}.light.color", path), &light.color);
+            }
+        }
+    }
+
+    fn check_spells(&mut self) {
+        for (i, spell) in self.raws.spells.iter().enumerate() {
+            let path = self.enter("spells", &spell.name, i);
+            self.effects(format!("{}.effects", path), &spell.effects);
+        }
+    }
+
+    fn check_loot_tables(&mut self) {
+        for (i, table) in self.raws.loot_tables.iter().enumerate() {
+            let path = self.enter("loot_tables", &table.name, i);
+            for (n, drop) in table.drops.iter().enumerate() {
+                if !self.entities.contains(&drop.name) {
+                    self.problem(
+                        format!("{}.drops[{}]", path, n),
+                        format!("no item, mob or prop is called [{}]", drop.name),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_factions(&mut self) {
+        for (i, faction) in self.raws.faction_table.iter().enumerate() {
+            let path = self.enter("faction_table", &faction.name, i);
+            let mut others: Vec<&String> = faction.responses.keys().collect();
+            others.sort();
+            for other in others {
+                let path = format!("{}.responses.{}", path, other);
+                if other != "Default" && !self.factions.contains(other.as_str()) {
+                    self.problem(path.clone(), format!("unknown faction [{}]", other));
+                }
+                self.one_of(path, &faction.responses[other], &REACTIONS);
+            }
+        }
+    }
+
+    fn check_weapon_traits(&mut self) {
+        for (i, weapon_trait) in self.raws.weapon_traits.iter().enumerate() {
+            let path = self.enter("weapon_traits", &weapon_trait.name, i);
+            if let Some(target) = &weapon_trait.target {
+                self.one_of(format!("{}.target", path), target, &PROC_TARGETS);
+            }
+            self.effects(format!("{}.effects", path), &weapon_trait.effects);
+        }
+    }
+
+    fn check_room_kinds(&mut self) {
+        for (i, kind) in self.raws.room_kinds.iter().enumerate() {
+            let path = self.enter("room_kinds", &kind.name, i);
+            if kind.min_depth > kind.max_depth {
+                self.problem(path.clone(), "min_depth is deeper than max_depth");
+            }
+            if let Some(floor) = kind.floor {
+                if !floor.is_walkable() {
+                    self.problem(
+                        format!("{}.floor", path),
+                        format!("[{:?}] is not a walkable tile", floor),
+                    );
+                }
+            }
+            if let Some(table) = &kind.from_depth_table {
+                self.one_of(format!("{}.from_depth_table", path), table, &DEPTH_TABLES);
+            }
+            if let Some(count) = &kind.spawn_count {
+                let path = format!("{}.spawn_count", path);
+                self.dice(path.clone(), count);
+                match (kind.spawns.is_empty(), &kind.from_depth_table) {
+                    (true, None) => self.problem(path, "needs either spawns or a from_depth_table"),
+                    (false, Some(_)) => {
+                        self.problem(path, "has both spawns and a from_depth_table")
+                    }
+                    _ => {}
+                }
+            }
+            for (n, spawn) in kind.spawns.iter().enumerate() {
+                if !self.entities.contains(&spawn.name) {
+                    self.problem(
+                        format!("{}.spawns[{}]", path, n),
+                        format!("no item, mob or prop is called [{}]", spawn.name),
+                    );
+                }
+            }
+            for (n, decoration) in kind.decorations.iter().enumerate() {
+                let path = format!("{}.decorations[{}]", path, n);
+                if !self.entities.contains(&decoration.name) {
+                    self.problem(
+                        path.clone(),
+                        format!("no item, mob or prop is called [{}]", decoration.name),
+                    );
+                }
+                self.dice(format!("{}.count", path), &decoration.count);
+            }
+        }
+    }
+
+    fn check_levels(&mut self) {
+        let raws = self.raws;
+        for (i, level) in raws.levels.iter().enumerate() {
+            let path = self.enter("levels", &level.name, i);
+            if let Some(max_depth) = level.max_depth {
+                if level.min_depth > max_depth {
+                    self.problem(path.clone(), "min_depth is deeper than max_depth");
+                }
+            }
+            if level.weight.is_some_and(|w| w <= 0) {
+                self.problem(format!("{}.weight", path), "weight must be above zero");
+            }
+            let width = level.width.unwrap_or(DEFAULT_MAP_WIDTH);
+            let height = level.height.unwrap_or(DEFAULT_MAP_HEIGHT);
+            let mut used = Vec::new();
+            self.builders_used(&level.steps, &mut HashSet::new(), &mut used);
+            for info in used.iter().filter_map(|name| builder_info(name)) {
+                let (min_width, min_height) = info.min_size;
+                if width < min_width || height < min_height {
+                    self.problem(
+                        path.clone(),
+                        format!(
+                            "{} needs a map of at least {}x{}",
+                            info.name, min_width, min_height
+                        ),
+                    );
+                }
+            }
+            if level.branch() != MAIN_BRANCH
+                && !raws.branches.iter().any(|b| b.name == level.branch())
+            {
+                self.problem(
+                    format!("{}.branch", path),
+                    format!("unknown branch [{}]", level.branch()),
+                );
+            }
+            self.steps(format!("{}.steps", path), &level.steps);
+        }
+
+        for (i, fragment) in raws.builder_fragments.iter().enumerate() {
+            let path = self.enter("builder_fragments", &fragment.name, i);
+            self.steps(format!("{}.steps", path), &fragment.steps);
+            if self.includes_fragment(&fragment.steps, &fragment.name, &mut HashSet::new()) {
+                self.problem(path, "fragment includes itself");
+            }
+        }
+
+        // Every depth needs a chain, or the game panics on arriving there.
+        self.section = "levels";
+        self.entity = "";
+        let main: Vec<&LevelChain> = raws
+            .levels
+            .iter()
+            .filter(|l| l.branch() == MAIN_BRANCH)
+            .collect();
+        let deepest = main.iter().map(|l| l.min_depth).max().unwrap_or(0);
+        for depth in 0..=deepest {
+            if !main.iter().any(|l| l.covers_depth(depth)) {
+                self.problem(
+                    "levels".to_string(),
+                    format!("no level covers depth {}", depth),
+                );
+            }
+        }
+        if !main.iter().any(|l| l.max_depth.is_none()) {
+            self.problem(
+                "levels".to_string(),
+                "no level has an open max_depth, so the deepest levels cannot be built",
+            );
+        }
+    }
+
+    fn check_branches(&mut self) {
+        let raws = self.raws;
+        let covered = |branch: &str, depth: i32| {
+            raws.levels
+                .iter()
+                .any(|l| l.branch() == branch && l.covers_depth(depth))
+        };
+        for (i, branch) in raws.branches.iter().enumerate() {
+            let path = self.enter("branches", &branch.name, i);
+            if branch.name == MAIN_BRANCH {
+                self.problem(path.clone(), format!("{} is the main dungeon", MAIN_BRANCH));
+            }
+            if branch.levels < 1 {
+                self.problem(
+                    format!("{}.levels", path),
+                    "a branch needs at least one level",
+                );
+            }
+
+            let parent = branch.parent();
+            if parent != MAIN_BRANCH && !raws.branches.iter().any(|b| b.name == parent) {
+                self.problem(
+                    format!("{}.parent", path),
+                    format!("unknown branch [{}]", parent),
+                );
+            } else if !covered(parent, branch.entrance_depth) {
+                self.problem(
+                    format!("{}.entrance_depth", path),
+                    format!("{} has no level at depth {}", parent, branch.entrance_depth),
+                );
+            }
+
+            // Following parents must end at the main dungeon.
+            let mut ancestor = branch;
+            for _ in 0..raws.branches.len() {
+                match raws.branches.iter().find(|b| b.name == ancestor.parent()) {
+                    Some(next) => ancestor = next,
+                    None => break,
+                }
+            }
+            if ancestor.parent() != MAIN_BRANCH
+                && raws.branches.iter().any(|b| b.name == ancestor.parent())
+            {
+                self.problem(
+                    format!("{}.parent", path),
+                    "following parents never leads back to the main dungeon",
+                );
+            }
+
+            for depth in branch.first_depth()..=branch.last_depth() {
+                if !covered(&branch.name, depth) {
+                    self.problem(path.clone(), format!("no level covers depth {}", depth));
+                }
+            }
+        }
+    }
+
+    fn check_prefabs(&mut self) {
+        let raws = self.raws;
+        self.section = "prefabs";
+        self.entity = "";
+        let mut shared: Vec<(&char, &PrefabGlyph)> = raws.prefab_legend.iter().collect();
+        shared.sort_by_key(|(ch, _)| **ch);
+        for (ch, glyph) in shared {
+            self.glyph(format!("legend.{}", ch), glyph, &raws.prefab_legend);
+        }
+
+        for (i, prefab) in raws.prefabs.iter().enumerate() {
+            let path = self.enter("prefabs", &prefab.name, i);
+            if let Some(tiled) = &prefab.tiled {
+                if prefab.kind != PrefabKind::Level {
+                    self.problem(path.clone(), "a Tiled map can only be a whole level");
+                }
+                for (pos, spawn) in tiled.spawns.iter() {
+                    if !self.entities.contains(spawn) {
+                        self.problem(
+                            format!("{}.file", path),
+                            format!(
+                                "no item, mob or prop is named [{}] (at {},{})",
+                                spawn, pos.x, pos.y
+                            ),
+                        );
+                    }
+                }
+            } else if prefab.template.is_empty() {
+                self.problem(path.clone(), "no template, or its file could not be read");
+            } else if prefab
+                .template
+                .iter()
+                .any(|row| row.chars().count() != prefab.width())
+            {
+                self.problem(
+                    format!("{}.template", path),
+                    "rows are not all the same width",
+                );
+            }
+
+            let mut unknown: Vec<char> = prefab
+                .template
+                .iter()
+                .flat_map(|row| row.chars())
+                .filter(|ch| {
+                    *ch != '\u{a0}'
+                        && !prefab.legend.contains_key(ch)
+                        && !raws.prefab_legend.contains_key(ch)
+                })
+                .collect();
+            unknown.sort();
+            unknown.dedup();
+            for ch in unknown {
+                self.problem(
+                    format!("{}.template", path),
+                    format!("[{}] is not in the legend", ch),
+                );
+            }
+            let mut legend: Vec<(&char, &PrefabGlyph)> = prefab.legend.iter().collect();
+            legend.sort_by_key(|(ch, _)| **ch);
+            for (ch, glyph) in legend {
+                self.glyph(format!("{}.legend.{}", path, ch), glyph, &prefab.legend);
+            }
+
+            if let (Some(min), Some(max)) = (prefab.min_depth, prefab.max_depth) {
+                if min > max {
+                    self.problem(path.clone(), "min_depth is deeper than max_depth");
+                }
+            }
+            if prefab.weight.is_some_and(|w| w <= 0) {
+                self.problem(format!("{}.weight", path), "weight must be above zero");
+            }
+            let placed = prefab.x.is_some() && prefab.y.is_some();
+            match prefab.kind {
+                PrefabKind::Section if !placed => {
+                    self.problem(path.clone(), "a section needs both x and y")
+                }
+                PrefabKind::Level | PrefabKind::Room
+                    if prefab.x.is_some() || prefab.y.is_some() =>
+                {
+                    self.problem(path.clone(), "only sections take x and y")
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn glyph(&mut self, path: String, glyph: &PrefabGlyph, legend: &HashMap<char, PrefabGlyph>) {
+        if let Some(spawn) = &glyph.spawn {
+            if !self.entities.contains(spawn) {
+                self.problem(
+                    format!("{}.spawn", path),
+                    format!("no item, mob or prop is named [{}]", spawn),
+                );
+            }
+        }
+        for (field, turned) in [("rotated", glyph.rotated), ("mirrored", glyph.mirrored)] {
+            if let Some(ch) = turned {
+                if !legend.contains_key(&ch) && !self.raws.prefab_legend.contains_key(&ch) {
+                    self.problem(
+                        format!("{}.{}", path, field),
+                        format!("[{}] is not in the legend", ch),
+                    );
+                }
+            }
+        }
+    }
+
+    fn steps(&mut self, path: String, steps: &[BuilderStep]) {
+        for (n, step) in steps.iter().enumerate() {
+            let path = format!("{}[{}]", path, n);
+            let kinds = [
+                step.builder.is_some(),
+                step.choose.is_some(),
+                step.include.is_some(),
+            ];
+            if kinds.iter().filter(|k| **k).count() != 1 {
+                self.problem(
+                    path,
+                    "a step needs exactly one of builder, choose or include",
+                );
+                continue;
+            }
+
+            if let Some(name) = &step.builder {
+                self.builder_step(path, name, step);
+            } else if let Some(choices) = &step.choose {
+                if choices.is_empty() {
+                    self.problem(format!("{}.choose", path), "nothing to choose from");
+                }
+                if choices.iter().any(|c| c.weight < 0) || choices.iter().all(|c| c.weight == 0) {
+                    self.problem(
+                        format!("{}.choose", path),
+                        "weights must not be negative, and at least one must be above zero",
+                    );
+                }
+                for (c, choice) in choices.iter().enumerate() {
+                    self.steps(format!("{}.choose[{}].steps", path, c), &choice.steps);
+                }
+            } else if let Some(name) = &step.include {
+                if !self.raws.builder_fragments.iter().any(|f| &f.name == name) {
+                    self.problem(
+                        format!("{}.include", path),
+                        format!("unknown builder fragment [{}]", name),
+                    );
+                }
+            }
+        }
+    }
+
+    fn builder_step(&mut self, path: String, name: &str, step: &BuilderStep) {
+        let info = match builder_info(name) {
+            Some(info) => info,
+            None => {
+                let names: Vec<&str> = BUILDERS.iter().map(|b| b.name).collect();
+                self.one_of(format!("{}.builder", path), name, &names);
+                return;
+            }
+        };
+
+        match (&step.variant, info.variants.is_empty()) {
+            (Some(variant), false) => {
+                self.one_of(format!("{}.variant", path), variant, info.variants)
+            }
+            (Some(_), true) => self.problem(
+                format!("{}.variant", path),
+                format!("{} has no variants", name),
+            ),
+            (None, false) => self.problem(
+                path.clone(),
+                format!("{} needs a variant: {}", name, info.variants.join(", ")),
+            ),
+            (None, true) => {}
+        }
+
+        let params = [
+            ("x", step.x.is_some()),
+            ("y", step.y.is_some()),
+            ("divisor", step.divisor.is_some()),
+            ("prefab", step.prefab.is_some()),
+            ("chunk_size", step.chunk_size.is_some()),
+            ("overlapping", step.overlapping.is_some()),
+        ];
+        for (param, given) in params {
+            let needs = info.params.contains(&param);
+            let takes = needs || info.optional.contains(&param);
+            if given && !takes {
+                self.problem(
+                    format!("{}.{}", path, param),
+                    format!("{} does not take {}", name, param),
+                );
+            } else if !given && needs {
+                self.problem(path.clone(), format!("{} needs {}", name, param));
+            }
+        }
+        if step.chunk_size.is_some_and(|size| size < 1) {
+            self.problem(
+                format!("{}.chunk_size", path),
+                "chunk_size must be above zero",
+            );
+        }
+
+        // Only starting positions can be picked at random.
+        let random = if name == "AreaStartingPosition" {
+            Some(RANDOM_POSITION)
+        } else {
+            None
+        };
+        if let Some(x) = &step.x {
+            let allowed: Vec<&str> = X_POSITIONS.iter().copied().chain(random).collect();
+            self.one_of(format!("{}.x", path), x, &allowed);
+        }
+        if let Some(y) = &step.y {
+            let allowed: Vec<&str> = Y_POSITIONS.iter().copied().chain(random).collect();
+            self.one_of(format!("{}.y", path), y, &allowed);
+        }
+        if let (Some(prefab), Some(kind)) = (&step.prefab, prefab_kind(name)) {
+            let names: Vec<&str> = self
+                .raws
+                .prefabs
+                .iter()
+                .filter(|p| p.kind == kind)
+                .map(|p| p.name.as_str())
+                .collect();
+            self.one_of(format!("{}.prefab", path), prefab, &names);
+        }
+    }
+
+    // Every builder the steps could run, through choices and fragments.
+    fn builders_used(
+        &self,
+        steps: &'a [BuilderStep],
+        seen: &mut HashSet<&'a str>,
+        used: &mut Vec<&'a str>,
+    ) {
+        for step in steps {
+            if let Some(name) = &step.builder {
+                if !used.contains(&name.as_str()) {
+                    used.push(name);
+                }
+            }
+            for choice in step.choose.iter().flatten() {
+                self.builders_used(&choice.steps, seen, used);
+            }
+            if let Some(name) = &step.include {
+                if seen.insert(name.as_str()) {
+                    if let Some(fragment) =
+                        self.raws.builder_fragments.iter().find(|f| &f.name == name)
+                    {
+                        self.builders_used(&fragment.steps, seen, used);
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether the steps include `target`, directly or through other fragments.
+    fn includes_fragment(
+        &self,
+        steps: &'a [BuilderStep],
+        target: &str,
+        seen: &mut HashSet<&'a str>,
+    ) -> bool {
+        steps.iter().any(|step| {
+            if step
+                .choose
+                .iter()
+                .flatten()
+                .any(|c| self.includes_fragment(&c.steps, target, seen))
+            {
+                return true;
+            }
+            match &step.include {
+                Some(name) if name == target => true,
+                Some(name) if seen.insert(name.as_str()) => {
+                    match self.raws.builder_fragments.iter().find(|f| &f.name == name) {
+                        Some(fragment) => self.includes_fragment(&fragment.steps, target, seen),
+                        None => false,
+                    }
+                }
+                _ => false,
+            }
+        })
+    }
+}
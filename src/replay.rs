@@ -0,0 +1,231 @@
+use crate::{prelude::*, KeyState};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+pub const REPLAY_PATH: &str = "./replay.jsonl";
+// Delay between inputs when a replay is played back in the window.
+pub const REPLAY_STEP_MS: f32 = 100.0;
+const REPLAY_VERSION: u32 = 1;
+
+// VirtualKeyCode has no serde support, so keys are stored by name. Only keys
+// the game actually responds to are listed; anything else is a no-op and is
+// simply not recorded.
+const RECORDABLE_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Backslash,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Space,
+];
+
+// Replays recorded before the mode and slot were stored are Permadeath games
+// in the first slot.
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    seed: GameSeed,
+    #[serde(default = "permadeath")]
+    mode: GameMode,
+    #[serde(default)]
+    slot: usize,
+}
+
+fn permadeath() -> GameMode {
+    GameMode::Permadeath
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedInput {
+    shift: bool,
+    control: bool,
+    alt: bool,
+    mouse_x: i32,
+    mouse_y: i32,
+    mouse_clicked: bool,
+    key: Option<String>,
+}
+
+impl RecordedInput {
+    fn from_key_state(key_state: &KeyState) -> Option<Self> {
+        let key = key_state
+            .key
+            .filter(|k| RECORDABLE_KEYS.contains(k))
+            .map(|k| format!("{:?}", k));
+        if key.is_none() && !key_state.mouse_clicked {
+            return None;
+        }
+        Some(Self {
+            shift: key_state.shift,
+            control: key_state.control,
+            alt: key_state.alt,
+            mouse_x: key_state.mouse_pos.x,
+            mouse_y: key_state.mouse_pos.y,
+            mouse_clicked: key_state.mouse_clicked,
+            key,
+        })
+    }
+
+    fn to_key_state(&self) -> KeyState {
+        KeyState {
+            shift: self.shift,
+            control: self.control,
+            alt: self.alt,
+            mouse_pos: Point::new(self.mouse_x, self.mouse_y),
+            mouse_clicked: self.mouse_clicked,
+            key: self.key.as_ref().and_then(|name| {
+                RECORDABLE_KEYS
+                    .iter()
+                    .find(|k| format!("{:?}", k) == *name)
+                    .copied()
+            }),
+        }
+    }
+}
+
+// Appends every accepted input to the replay file as it happens, so the file
+// is complete even if the game crashes.
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+}
+
+impl ReplayRecorder {
+    pub fn create(
+        path: &str,
+        seed: GameSeed,
+        slot: SaveSlot,
+        mode: GameMode,
+    ) -> std::io::Result<Self> {
+        let mut recorder = Self {
+            writer: BufWriter::new(File::create(path)?),
+        };
+        recorder.write_line(&ReplayHeader {
+            version: REPLAY_VERSION,
+            seed,
+            mode,
+            slot: slot.0,
+        })?;
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, key_state: &KeyState) {
+        if let Some(input) = RecordedInput::from_key_state(key_state) {
+            if let Err(e) = self.write_line(&input) {
+                log(format!("WARNING: unable to record replay input: {}", e));
+            }
+        }
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+pub struct Replay {
+    pub seed: GameSeed,
+    pub mode: GameMode,
+    pub slot: SaveSlot,
+    inputs: VecDeque<RecordedInput>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header_line = lines
+            .next()
+            .ok_or(format!("{}: empty replay file", path))?
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let header: ReplayHeader = serde_json::from_str(&header_line)
+            .map_err(|e| format!("{}: bad replay header: {}", path, e))?;
+        if header.version != REPLAY_VERSION {
+            return Err(format!(
+                "{}: replay version {} is not supported",
+                path, header.version
+            ));
+        }
+
+        let mut inputs = VecDeque::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A crash can leave a half-written final line; stop there.
+            match serde_json::from_str::<RecordedInput>(&line) {
+                Ok(input) => inputs.push_back(input),
+                Err(e) => {
+                    log(format!(
+                        "WARNING: {} line {}: {}; replay truncated",
+                        path,
+                        i + 2,
+                        e
+                    ));
+                    break;
+                }
+            }
+        }
+
+        Ok(Self {
+            seed: header.seed,
+            mode: header.mode,
+            slot: SaveSlot(header.slot),
+            inputs,
+        })
+    }
+
+    // Starts the game the way the recorded one was started.
+    pub fn new_game(&self) -> TurnState {
+        TurnState::NewGame {
+            slot: self.slot,
+            mode: self.mode,
+        }
+    }
+
+    pub fn next_input(&mut self) -> Option<KeyState> {
+        self.inputs.pop_front().map(|i| i.to_key_state())
+    }
+}
@@ -8,6 +8,12 @@ pub struct RexAssets {
     pub menu: XpFile,
 }
 
+impl Default for RexAssets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RexAssets {
     pub fn new() -> RexAssets {
         link_resource!(SMALL_DUNGEON, "../resources/SmallDungeon_80x50.xp");
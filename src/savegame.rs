@@ -0,0 +1,385 @@
+use crate::prelude::*;
+use serde_json::{json, Map as JsonMap, Value};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Bump this whenever a saved component changes shape, and add the matching
+// migration below.
+pub const SAVE_VERSION: u32 = 4;
+
+// MIGRATIONS[n] upgrades a version n save to version n + 1.
+type Migration = fn(&mut Value) -> Result<(), String>;
+const MIGRATIONS: [Migration; SAVE_VERSION as usize] = [
+    add_game_records,
+    add_game_mode,
+    move_resources_out_of_world,
+    add_branches,
+];
+
+pub const SAVE_SLOTS: usize = 5;
+const SAVE_DIR: &str = "./saves";
+// Where saves lived before there were slots.
+const LEGACY_SAVE_PATH: &str = "./savegame.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    // Loading a save deletes it, and death is final.
+    Permadeath,
+    // Saves are kept when loaded, and death returns you to the last one.
+    Explore,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaveSlot(pub usize);
+
+impl SaveSlot {
+    pub fn path(&self) -> String {
+        format!(
+            "{}/slot_{}.json",
+            SAVE_DIR,
+            self.letter().to_ascii_lowercase()
+        )
+    }
+
+    pub fn letter(&self) -> char {
+        (b'A' + self.0 as u8) as char
+    }
+
+    pub fn exists(&self) -> bool {
+        Path::new(&self.path()).exists()
+    }
+
+    pub fn delete(&self) -> Result<(), SaveError> {
+        if self.exists() {
+            let path = self.path();
+            fs::remove_file(&path)
+                .map_err(|e| SaveError(format!("Unable to delete {}: {}", path, e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveSummary {
+    pub level: i32,
+    pub depth: i32,
+    pub turns: i32,
+    pub saved_at: u64,
+    pub mode: GameMode,
+}
+
+impl SaveSummary {
+    pub fn new(level: i32, depth: i32, turns: i32, mode: GameMode) -> Self {
+        Self {
+            level,
+            depth,
+            turns,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            mode,
+        }
+    }
+
+    // UTC, as "YYYY-MM-DD HH:MM".
+    pub fn timestamp(&self) -> String {
+        let days = (self.saved_at / 86400) as i64;
+        let minutes = (self.saved_at % 86400) / 60;
+
+        // Days since the epoch to a civil date, after Howard Hinnant.
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year,
+            month,
+            day,
+            minutes / 60,
+            minutes % 60
+        )
+    }
+}
+
+pub enum SlotStatus {
+    Empty,
+    // Saves from before summaries were recorded, or that can't be read, have
+    // no details to show.
+    Saved(Option<SaveSummary>),
+}
+
+pub struct SaveSlots(pub Vec<SlotStatus>);
+
+impl SaveSlots {
+    pub fn any_saved(&self) -> bool {
+        self.0.iter().any(|s| matches!(s, SlotStatus::Saved(_)))
+    }
+}
+
+// Only the summary is decoded; the world is skipped over.
+#[derive(Deserialize)]
+struct SaveHeader {
+    summary: Option<SaveSummary>,
+}
+
+pub fn list_save_slots() -> SaveSlots {
+    adopt_legacy_save();
+    SaveSlots(
+        (0..SAVE_SLOTS)
+            .map(|slot| {
+                let slot = SaveSlot(slot);
+                if !slot.exists() {
+                    return SlotStatus::Empty;
+                }
+                let summary = fs::read_to_string(slot.path())
+                    .ok()
+                    .and_then(|text| serde_json::from_str::<SaveHeader>(&text).ok())
+                    .and_then(|header| header.summary);
+                SlotStatus::Saved(summary)
+            })
+            .collect(),
+    )
+}
+
+// Moves a save from before slots existed into the first free slot.
+fn adopt_legacy_save() {
+    if !Path::new(LEGACY_SAVE_PATH).exists() {
+        return;
+    }
+    if let Some(slot) = (0..SAVE_SLOTS).map(SaveSlot).find(|s| !s.exists()) {
+        if fs::create_dir_all(SAVE_DIR).is_ok() {
+            let _ = fs::rename(LEGACY_SAVE_PATH, slot.path());
+        }
+    }
+}
+
+// Game state that lives outside the world, stored alongside it.
+#[derive(Serialize, Deserialize)]
+pub struct SavedResources {
+    pub map: Map,
+    pub dungeon: MasterDungeonMap,
+    pub log: GameLog,
+    pub events: GameEvents,
+    pub seed: GameSeed,
+    pub rng: RandomNumberGenerator,
+    pub mode: GameMode,
+}
+
+pub struct SaveError(pub String);
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub fn encode_save(
+    summary: SaveSummary,
+    resources: &SavedResources,
+    world: Value,
+) -> Result<String, SaveError> {
+    let document = json!({
+        "version": SAVE_VERSION,
+        "summary": summary,
+        "resources": resources,
+        "world": world,
+    });
+    serde_json::to_string(&document)
+        .map_err(|e| SaveError(format!("Unable to encode the save: {}", e)))
+}
+
+pub fn write_save(path: &str, text: &str) -> Result<(), SaveError> {
+    fs::create_dir_all(SAVE_DIR)
+        .map_err(|e| SaveError(format!("Unable to create {}: {}", SAVE_DIR, e)))?;
+    fs::write(path, text).map_err(|e| SaveError(format!("Unable to write {}: {}", path, e)))
+}
+
+pub fn read_save(path: &str) -> Result<(SavedResources, Value), SaveError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| SaveError(format!("Unable to read {}: {}", path, e)))?;
+    decode_save(path, &text)
+}
+
+// Reads a save of any known version, upgrading it to the current one. `path`
+// only names the save in errors.
+pub fn decode_save(path: &str, text: &str) -> Result<(SavedResources, Value), SaveError> {
+    let document: Value = serde_json::from_str(text)
+        .map_err(|e| SaveError(format!("{} is not a valid save: {}", path, e)))?;
+
+    // Saves from before versioning are the bare world.
+    let (version, mut document) = match document.get("version") {
+        Some(version) => {
+            let version = version
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| SaveError(format!("{} has an invalid version", path)))?;
+            (version, document)
+        }
+        None => (0, json!({ "world": document })),
+    };
+
+    if version > SAVE_VERSION {
+        return Err(SaveError(format!(
+            "This save is from a newer version of the game (format {}, expected {}).",
+            version, SAVE_VERSION
+        )));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut document).map_err(|e| {
+            SaveError(format!(
+                "Unable to upgrade save from format {} to {}: {}",
+                from,
+                from + 1,
+                e
+            ))
+        })?;
+    }
+
+    let world = document
+        .get_mut("world")
+        .map(Value::take)
+        .ok_or_else(|| SaveError(format!("{} has no world", path)))?;
+    let resources = document
+        .get_mut("resources")
+        .map(Value::take)
+        .ok_or_else(|| SaveError(format!("{} has no game state", path)))?;
+    let resources = serde_json::from_value(resources)
+        .map_err(|e| SaveError(format!("Unable to restore the game state: {}", e)))?;
+
+    Ok((resources, world))
+}
+
+fn entities_mut(document: &mut Value) -> Result<&mut JsonMap<String, Value>, String> {
+    document
+        .get_mut("world")
+        .and_then(|w| w.get_mut("entities"))
+        .and_then(|e| e.as_object_mut())
+        .ok_or_else(|| "no entity list".to_string())
+}
+
+// Calls `upgrade` on every saved component with the given tag, for
+// migrations that change a single component's shape.
+fn migrate_component<F>(document: &mut Value, tag: &str, mut upgrade: F) -> Result<(), String>
+where
+    F: FnMut(&mut Value) -> Result<(), String>,
+{
+    for entity in entities_mut(document)?.values_mut() {
+        if let Some(component) = entity.get_mut(tag) {
+            upgrade(component)?;
+        }
+    }
+    Ok(())
+}
+
+fn has_component(document: &mut Value, tag: &str) -> Result<bool, String> {
+    Ok(entities_mut(document)?
+        .values()
+        .any(|entity| entity.get(tag).is_some()))
+}
+
+// Saved entities are keyed by random (version 4) UUIDs, so numbering new ones
+// from the nil UUID can never collide with them.
+fn push_entity(document: &mut Value, components: Value) -> Result<(), String> {
+    let entities = entities_mut(document)?;
+    let name = (0..)
+        .map(|n: u64| format!("00000000-0000-0000-0000-{:012x}", n))
+        .find(|name| !entities.contains_key(name))
+        .unwrap();
+    entities.insert(name, components);
+    Ok(())
+}
+
+// Format 0 saves never stored the game log or event counts (their types were
+// not registered), and the earliest ones have no game seed either.
+fn add_game_records(document: &mut Value) -> Result<(), String> {
+    if !has_component(document, "game_log")? {
+        push_entity(document, json!({ "game_log": [] }))?;
+    }
+    if !has_component(document, "game_events")? {
+        push_entity(document, json!({ "game_events": {} }))?;
+    }
+    if !has_component(document, "game_seed")? {
+        let seed = GameSeed::random();
+        let rng = serde_json::to_value(seed.game_rng()).map_err(|e| e.to_string())?;
+        push_entity(document, json!({ "game_seed": seed, "rng": rng }))?;
+    }
+    Ok(())
+}
+
+// Every save before game modes existed was a permadeath game.
+fn add_game_mode(document: &mut Value) -> Result<(), String> {
+    for entity in entities_mut(document)?.values_mut() {
+        if entity.get("game_seed").is_some() {
+            entity["game_mode"] = json!(GameMode::Permadeath);
+        }
+    }
+    Ok(())
+}
+
+// Format 2 saves stored the map, log and the like as extra entities in the
+// world; they now have a section of their own.
+fn move_resources_out_of_world(document: &mut Value) -> Result<(), String> {
+    const PARTS: [(&str, &str); 7] = [
+        ("map", "map"),
+        ("dungeon_master", "dungeon"),
+        ("game_log", "log"),
+        ("game_events", "events"),
+        ("game_seed", "seed"),
+        ("rng", "rng"),
+        ("game_mode", "mode"),
+    ];
+
+    let mut resources = JsonMap::new();
+    entities_mut(document)?.retain(|_, entity| {
+        let mut moved = false;
+        for (tag, name) in PARTS {
+            if let Some(value) = entity.get_mut(tag) {
+                resources.insert(name.to_string(), value.take());
+                moved = true;
+            }
+        }
+        !moved
+    });
+
+    if let Some((_, name)) = PARTS
+        .iter()
+        .find(|(_, name)| !resources.contains_key(*name))
+    {
+        return Err(format!("no {} was saved", name));
+    }
+    document["resources"] = Value::Object(resources);
+    Ok(())
+}
+
+// Format 3 saves predate branches: the dungeon kept its maps by depth alone,
+// and entities on other levels only recorded a depth. All of them are in the
+// main dungeon.
+fn add_branches(document: &mut Value) -> Result<(), String> {
+    let maps = document
+        .get_mut("resources")
+        .and_then(|r| r.get_mut("dungeon"))
+        .and_then(|d| d.get_mut("maps"))
+        .ok_or_else(|| "no dungeon maps".to_string())?;
+    if let Some(by_depth) = maps.as_object_mut() {
+        *maps = Value::Array(by_depth.values_mut().map(Value::take).collect());
+    }
+
+    migrate_component(document, "olpos", |olpos| {
+        let depth = olpos
+            .as_object_mut()
+            .and_then(|o| o.remove("depth"))
+            .ok_or_else(|| "other level position without a depth".to_string())?;
+        olpos["level"] = json!({ "branch": 0, "depth": depth });
+        Ok(())
+    })
+}
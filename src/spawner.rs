@@ -6,7 +6,13 @@ pub enum SpawnType {
     Carried { by: Entity },
 }
 
-pub fn spawn_player(ecs: &mut World, dm: &MasterDungeonMap, pos: Point) {
+pub fn spawn_player(
+    ecs: &mut World,
+    raws: &RawMaster,
+    dm: &MasterDungeonMap,
+    rng: &mut RandomNumberGenerator,
+    pos: Point,
+) {
     let player = ecs.push((
         Player { map_level: 0 },
         pos,
@@ -59,62 +65,70 @@ pub fn spawn_player(ecs: &mut World, dm: &MasterDungeonMap, pos: Point) {
     ));
 
     let mut commands = CommandBuffer::new(ecs);
-    spawn_all_spells(&mut commands);
+    spawn_all_spells(raws, &mut commands);
 
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Rusty Longsword",
         SpawnType::Equipped { by: player },
         dm,
+        rng,
         &mut commands,
     );
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Dried Sausage",
         SpawnType::Carried { by: player },
         dm,
+        rng,
         &mut commands,
     );
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Beer",
         SpawnType::Carried { by: player },
         dm,
+        rng,
         &mut commands,
     );
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Stained Tunic",
         SpawnType::Equipped { by: player },
         dm,
+        rng,
         &mut commands,
     );
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Torn Trousers",
         SpawnType::Equipped { by: player },
         dm,
+        rng,
         &mut commands,
     );
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Old Boots",
         SpawnType::Equipped { by: player },
         dm,
+        rng,
         &mut commands,
     );
     spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         "Shortbow",
         SpawnType::Carried { by: player },
         dm,
+        rng,
         &mut commands,
     );
     // spawn_named_entity(
-    //     &RAWS.lock().unwrap(),
+    //     raws,
     //     "Strength Potion",
     //     SpawnType::Carried { by: player },
     //     dm,
+    //     rng,
     //     &mut commands,
     // );
 
@@ -137,9 +151,9 @@ pub fn spawn_player(ecs: &mut World, dm: &MasterDungeonMap, pos: Point) {
 }
 
 pub fn spawn_town_portal(ecs: &mut World, resources: &mut Resources) {
-    // Get current position & depth
+    // Get current position & level
     let map = resources.get::<Map>().unwrap();
-    let depth = map.depth;
+    let level = map.level_id();
     let player_pos = <&Point>::query()
         .filter(component::<Player>())
         .iter(ecs)
@@ -150,7 +164,7 @@ pub fn spawn_town_portal(ecs: &mut World, resources: &mut Resources) {
 
     // Find part of the town for the portal
     let dm = resources.get::<MasterDungeonMap>().unwrap();
-    let town_map = dm.get_map(0).unwrap();
+    let town_map = dm.get_map(LevelId::town()).unwrap();
     let mut stairs_idx = 0;
     for (idx, tt) in town_map.tiles.iter().enumerate() {
         if *tt == TileType::DownStairs {
@@ -165,7 +179,7 @@ pub fn spawn_town_portal(ecs: &mut World, resources: &mut Resources) {
     ecs.push((
         OtherLevelPosition {
             position: portal_pos,
-            depth: 0,
+            level: LevelId::town(),
         },
         Render {
             color: ColorPair::new(CYAN, BLACK),
@@ -175,7 +189,7 @@ pub fn spawn_town_portal(ecs: &mut World, resources: &mut Resources) {
         EntryTrigger,
         TeleportTo {
             position: player_pos,
-            depth,
+            level,
             player_only: true,
         },
         Name("Town Portal".to_string()),
@@ -202,15 +216,22 @@ pub fn spawn_amulet_of_yala(ecs: &mut World, pos: Point) {
     ));
 }
 
-pub fn spawn_entity(ecs: &mut World, dm: &MasterDungeonMap, spawn: &(&Point, &String)) {
+pub fn spawn_entity(
+    ecs: &mut World,
+    raws: &RawMaster,
+    dm: &MasterDungeonMap,
+    rng: &mut RandomNumberGenerator,
+    spawn: &(&Point, &String),
+) {
     let pos = *spawn.0;
 
     let mut command_buffer = CommandBuffer::new(ecs);
     if spawn_named_entity(
-        &RAWS.lock().unwrap(),
+        raws,
         &spawn.1,
         SpawnType::AtPosition { point: pos },
         dm,
+        rng,
         &mut command_buffer,
     )
     .is_some()
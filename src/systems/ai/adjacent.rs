@@ -22,6 +22,7 @@ pub fn adjacent(
     size: Option<&TileSize>,
     _name: &Name,
     #[resource] map: &Map,
+    #[resource] raws: &RawMaster,
     commands: &mut CommandBuffer,
 ) {
     // Add possible reactions to adjacent entities for each direction.
@@ -40,10 +41,7 @@ pub fn adjacent(
             adjacent_pts.intersection(&points).count() != 0
         })
         .for_each(|(e, _, f, _)| {
-            reactions.push((
-                *e,
-                faction_reaction(&faction.name, &f.name, &RAWS.lock().unwrap()),
-            ));
+            reactions.push((*e, faction_reaction(&faction.name, &f.name, raws)));
         });
 
     // Cache available weaponry
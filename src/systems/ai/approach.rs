@@ -7,6 +7,7 @@ use crate::prelude::*;
 #[write_component(FieldOfView)]
 #[write_component(EntityMoved)]
 #[read_component(TileSize)]
+#[read_component(Digs)]
 #[filter(component::<MyTurn>())]
 pub fn approach(
     _ecs: &SubWorld,
@@ -15,7 +16,9 @@ pub fn approach(
     _fov: &mut FieldOfView,
     pos: &mut Point,
     size: Option<&TileSize>,
+    digs: Option<&Digs>,
     #[resource] map: &mut Map,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     let approach_pos = map.index_to_point2d(wants_approach.idx);
@@ -43,6 +46,19 @@ pub fn approach(
         let new_idx = path.steps[1];
         let destination = map.index_to_point2d(new_idx) + offset;
         commands.add_component(*entity, WantsToMove { destination });
+    } else if let Some(digs) = digs {
+        // No way round, so a burrower goes through.
+        let line = line2d(LineAlg::Bresenham, closest_point, approach_pos);
+        if line.len() > 1 {
+            let dig_idx = map.point2d_to_index(line[1]);
+            if can_dig(map, dig_idx) {
+                effect_queue.add_effect(
+                    Some(*entity),
+                    EffectType::Dig { tile: digs.0 },
+                    Targets::Tile { tile_idx: dig_idx },
+                );
+            }
+        }
     }
 
     commands.remove_component::<WantsToApproach>(*entity);
@@ -14,6 +14,7 @@ pub fn chasing(
     fov: &mut FieldOfView,
     chasing: &Chasing,
     #[resource] map: &mut Map,
+    #[resource] spatial: &mut SpatialMap,
     commands: &mut CommandBuffer,
 ) {
     // Is the target still valid?
@@ -43,7 +44,7 @@ pub fn chasing(
         if path.success && path.steps.len() < 15 {
             let old_idx = map.point2d_to_index(*pos);
             let new_idx = path.steps[1];
-            crate::spatial::move_entity(*entity, old_idx, new_idx);
+            spatial.move_entity(*entity, old_idx, new_idx);
             *pos = map.index_to_point2d(new_idx) + offset;
             fov.is_dirty = true;
             commands.add_component(*entity, EntityMoved);
@@ -6,6 +6,7 @@ use crate::prelude::*;
 #[write_component(Point)]
 #[write_component(FieldOfView)]
 #[write_component(EntityMoved)]
+#[read_component(Digs)]
 #[filter(component::<MyTurn>()&!component::<Player>())]
 pub fn default_movement(
     _ecs: &SubWorld,
@@ -13,8 +14,11 @@ pub fn default_movement(
     pos: &mut Point,
     _fov: &mut FieldOfView,
     mode: &mut MoveMode,
+    digs: Option<&Digs>,
     #[resource] map: &mut Map,
     #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] spatial: &SpatialMap,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     commands.remove_component::<MyTurn>(*entity);
@@ -29,7 +33,13 @@ pub fn default_movement(
                 _ => Point::zero(),
             };
             let new_pos = *pos + delta;
-            if map.can_enter_tile(new_pos) {
+            if let (Some(digs), Some(new_idx)) = (digs, map.try_idx(new_pos)) {
+                if can_dig(map, new_idx) {
+                    burrow(*entity, new_idx, digs, effect_queue);
+                    return;
+                }
+            }
+            if map.can_enter_tile(new_pos, spatial) {
                 commands.add_component(
                     *entity,
                     WantsToMove {
@@ -44,6 +54,16 @@ pub fn default_movement(
                 if path.len() > 1 {
                     let new_idx = path[1];
                     let destination = map.index_to_point2d(new_idx);
+                    if let Some(digs) = digs {
+                        if can_dig(map, new_idx) {
+                            burrow(*entity, new_idx, digs, effect_queue);
+                            return;
+                        }
+                        if !map.can_enter_tile(destination, spatial) {
+                            mode.0 = Movement::RandomWaypoint { path: None };
+                            return;
+                        }
+                    }
                     commands.add_component(*entity, WantsToMove { destination });
                     path.remove(0);
                 } else {
@@ -53,7 +73,15 @@ pub fn default_movement(
                 let target_x = rng.range(1, map.width as i32 - 2);
                 let target_y = rng.range(1, map.height as i32 - 2);
                 let target = map.point2d_to_index(Point::new(target_x, target_y));
-                if map.tiles[target].is_walkable() {
+                if digs.is_some() {
+                    // Burrowers head straight there, through whatever is in
+                    // the way.
+                    let path = line2d(LineAlg::Bresenham, *pos, Point::new(target_x, target_y))
+                        .iter()
+                        .map(|p| map.point2d_to_index(*p))
+                        .collect();
+                    mode.0 = Movement::RandomWaypoint { path: Some(path) }
+                } else if map.tiles[target].is_walkable() {
                     let path = a_star_search(idx, target, &mut *map);
                     if path.success && path.steps.len() > 1 {
                         mode.0 = Movement::RandomWaypoint {
@@ -65,3 +93,11 @@ pub fn default_movement(
         }
     }
 }
+
+fn burrow(entity: Entity, idx: usize, digs: &Digs, effect_queue: &mut EffectQueue) {
+    effect_queue.add_effect(
+        Some(entity),
+        EffectType::Dig { tile: digs.0 },
+        Targets::Tile { tile_idx: idx },
+    );
+}
@@ -16,6 +16,7 @@ pub fn initiative(
     ecs: &mut SubWorld,
     #[resource] rng: &mut RandomNumberGenerator,
     #[resource] turn_state: &mut TurnState,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     if *turn_state != TurnState::Ticking {
@@ -95,7 +96,7 @@ pub fn initiative(
         .for_each_mut(ecs, |(ent, duration, effect, maybe_dot)| {
             duration.0 -= 1;
             if let Some(dot) = maybe_dot {
-                add_effect(
+                effect_queue.add_effect(
                     None,
                     EffectType::Damage { amount: dot.damage },
                     Targets::Single {
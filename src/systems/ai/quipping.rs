@@ -10,6 +10,7 @@ pub fn quipping(
     name: &Name,
     quips: &mut Quips,
     #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] game_log: &mut GameLog,
 ) {
     let player_pos = <&Point>::query()
         .filter(component::<Player>())
@@ -25,7 +26,7 @@ pub fn quipping(
             .append("says")
             .color(CYAN)
             .append(&quips.0[quip_idx])
-            .log();
+            .log(game_log);
         quips.0.remove(quip_idx);
     }
 }
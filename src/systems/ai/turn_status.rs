@@ -9,6 +9,7 @@ use crate::prelude::*;
 pub fn turn_status(
     ecs: &mut SubWorld,
     #[resource] turn_state: &mut TurnState,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     if *turn_state != TurnState::Ticking {
@@ -31,7 +32,7 @@ pub fn turn_status(
         }
         if entry.get_component::<Confusion>().is_ok() {
             commands.remove_component::<MyTurn>(effect.target);
-            add_effect(
+            effect_queue.add_effect(
                 None,
                 EffectType::Particle {
                     glyph: to_cp437('?'),
@@ -27,6 +27,7 @@ pub fn visible(
     abilities: Option<&SpecialAbilities>,
     #[resource] map: &Map,
     #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] raws: &RawMaster,
     commands: &mut CommandBuffer,
 ) {
     let mut reactions: Vec<(usize, Reaction, Entity)> = Vec::new();
@@ -36,7 +37,7 @@ pub fn visible(
         .for_each(|(e, p, f)| {
             reactions.push((
                 map.point2d_to_index(*p),
-                faction_reaction(&faction.name, &f.name, &RAWS.lock().unwrap()),
+                faction_reaction(&faction.name, &f.name, raws),
                 *e,
             ));
         });
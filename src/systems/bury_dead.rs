@@ -19,6 +19,9 @@ pub fn bury_dead(
     #[resource] rng: &mut RandomNumberGenerator,
     #[resource] dm: &MasterDungeonMap,
     #[resource] map: &Map,
+    #[resource] raws: &RawMaster,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     let player_pools = <&Pools>::query()
@@ -41,7 +44,7 @@ pub fn bury_dead(
                 .npc_name(&name.0)
                 .color(RED)
                 .append("is dead!")
-                .log();
+                .log(game_log);
             dead_list.insert(*entity, *pos);
         });
 
@@ -69,7 +72,6 @@ pub fn bury_dead(
         .iter(ecs)
         .filter_map(|(e, t)| dead_list.get(e).map(|p| (t, p)))
         .for_each(|(table, pos)| {
-            let raws = &RAWS.lock().unwrap();
             if let Some(drop) = get_drop_item(raws, rng, &table.0) {
                 spawn_named_item(
                     raws,
@@ -111,7 +113,7 @@ pub fn bury_dead(
                         Targets::Tile { tile_idx }
                     };
 
-                    add_effect(
+                    effect_queue.add_effect(
                         None,
                         EffectType::CastSpell {
                             spell: spell_entity,
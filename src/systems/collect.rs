@@ -12,6 +12,7 @@ pub fn collect(
     ecs: &mut SubWorld,
     commands: &mut CommandBuffer,
     #[resource] dm: &MasterDungeonMap,
+    #[resource] game_log: &mut GameLog,
 ) {
     commands.remove_component::<Point>(wants_collect.what);
     commands.add_component(wants_collect.what, Carried(wants_collect.who));
@@ -39,7 +40,7 @@ pub fn collect(
         .append("picked up")
         .color(CYAN)
         .append(what)
-        .log();
+        .log(game_log);
 
     commands.remove(*entity);
 }
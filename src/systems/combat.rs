@@ -21,6 +21,8 @@ pub fn melee_combat(
     wants_attack: &WantsToAttack,
     ecs: &mut SubWorld,
     #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     let (attacker, victim) = (wants_attack.attacker, wants_attack.victim);
@@ -106,7 +108,7 @@ pub fn melee_combat(
         //     "Damage: {} + {}attr + {}skill + {}weapon = {}",
         //     base_damage, attr_damage_bonus, skill_damage_bonus, &weapon_info.damage_die, amount,
         // );
-        add_effect(
+        effect_queue.add_effect(
             Some(attacker),
             EffectType::Damage { amount },
             Targets::Single { target: victim },
@@ -119,7 +121,7 @@ pub fn melee_combat(
                 } else {
                     Targets::Single { target: victim }
                 };
-                add_effect(
+                effect_queue.add_effect(
                     Some(attacker),
                     EffectType::ItemUse {
                         item: weapon_entity.unwrap(),
@@ -135,8 +137,8 @@ pub fn melee_combat(
             .append("considers attacking")
             .npc_name(&victim_name)
             .append("but misjudges the timing!")
-            .log();
-        add_effect(
+            .log(game_log);
+        effect_queue.add_effect(
             None,
             EffectType::Particle {
                 glyph: to_cp437('‼'),
@@ -152,8 +154,8 @@ pub fn melee_combat(
             .append("attacks")
             .npc_name(&victim_name)
             .append("but can't connect.")
-            .log();
-        add_effect(
+            .log(game_log);
+        effect_queue.add_effect(
             None,
             EffectType::Particle {
                 glyph: to_cp437('‼'),
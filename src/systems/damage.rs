@@ -16,6 +16,8 @@ pub fn damage(
     commands: &mut CommandBuffer,
     #[resource] map: &mut Map,
     #[resource] particle_builder: &mut ParticleBuilder,
+    #[resource] spatial: &mut SpatialMap,
+    #[resource] game_log: &mut GameLog,
 ) {
     let user_name = name_for(&command.user_entity, ecs);
     let target_name = name_for(&command.target, ecs);
@@ -40,15 +42,15 @@ pub fn damage(
                 let amount = i32::min(command.damage, stats.hit_points.current);
                 stats.hit_points.current -= amount;
                 if stats.hit_points.current < 1 {
-                    crate::spatial::remove_entity(command.target, target_idx);
+                    spatial.remove_entity(command.target, target_idx);
                 }
 
                 if let Some(item_name) = item_name {
-                    log_for_item_damage(&user_name, &target_name, &item_name, amount)
+                    log_for_item_damage(game_log, &user_name, &target_name, &item_name, amount)
                 } else if command.user_entity == command.target {
-                    log_for_self_damage(&user_name, amount)
+                    log_for_self_damage(game_log, &user_name, amount)
                 } else {
-                    log_for_damage(&user_name, &target_name, amount)
+                    log_for_damage(game_log, &user_name, &target_name, amount)
                 }
             }
 
@@ -58,9 +60,9 @@ pub fn damage(
             }
         } else if target.get_component::<Item>().is_ok() {
             // destroy the item outright
-            crate::spatial::remove_entity(command.target, target_idx);
+            spatial.remove_entity(command.target, target_idx);
             commands.remove(command.target);
-            log_for_destroyed_item(&user_name, &target_name.0);
+            log_for_destroyed_item(game_log, &user_name, &target_name.0);
         }
     };
 
@@ -75,6 +77,7 @@ pub fn damage(
         award_xp_and_gold(
             ecs,
             particle_builder,
+            game_log,
             &command.user_entity,
             xp_gain,
             gold_gain,
@@ -87,6 +90,7 @@ pub fn damage(
 fn award_xp_and_gold(
     ecs: &mut SubWorld,
     particle_builder: &mut ParticleBuilder,
+    game_log: &mut GameLog,
     entity: &Entity,
     xp_gain: i32,
     gold_gain: f32,
@@ -105,7 +109,7 @@ fn award_xp_and_gold(
                 crate::gamelog::Logger::new()
                     .append("Congratulations, you are now level")
                     .append(format!("{}", stats.level))
-                    .log();
+                    .log(game_log);
 
                 stats.hit_points.max =
                     player_hp_at_level(attrs.fitness.base + attrs.fitness.modifiers, stats.level);
@@ -130,7 +134,12 @@ fn award_xp_and_gold(
         });
 }
 
-fn log_for_damage(user_name: &(String, bool), target_name: &(String, bool), amount: i32) {
+fn log_for_damage(
+    game_log: &mut GameLog,
+    user_name: &(String, bool),
+    target_name: &(String, bool),
+    amount: i32,
+) {
     if user_name.1 {
         crate::gamelog::Logger::new()
             .append("You hit")
@@ -138,14 +147,14 @@ fn log_for_damage(user_name: &(String, bool), target_name: &(String, bool), amou
             .append("causing")
             .damage(amount)
             .append("hp damage.")
-            .log();
+            .log(game_log);
     } else if target_name.1 {
         crate::gamelog::Logger::new()
             .npc_name(&user_name.0)
             .append("hits you, causing")
             .damage(amount)
             .append("hp damage.")
-            .log();
+            .log(game_log);
     } else {
         crate::gamelog::Logger::new()
             .npc_name(&user_name.0)
@@ -154,28 +163,29 @@ fn log_for_damage(user_name: &(String, bool), target_name: &(String, bool), amou
             .append("causing")
             .damage(amount)
             .append("hp damage.")
-            .log();
+            .log(game_log);
     }
 }
 
-fn log_for_self_damage(user_name: &(String, bool), amount: i32) {
+fn log_for_self_damage(game_log: &mut GameLog, user_name: &(String, bool), amount: i32) {
     if user_name.1 {
         crate::gamelog::Logger::new()
             .append("You take")
             .damage(amount)
             .append("hp damage.")
-            .log();
+            .log(game_log);
     } else {
         crate::gamelog::Logger::new()
             .npc_name(&user_name.0)
             .append("takes")
             .damage(amount)
             .append("hp damage.")
-            .log();
+            .log(game_log);
     }
 }
 
 fn log_for_item_damage(
+    game_log: &mut GameLog,
     user_name: &(String, bool),
     target_name: &(String, bool),
     item_name: &String,
@@ -188,7 +198,7 @@ fn log_for_item_damage(
                 .damage(amount)
                 .append("hp damage on yourself with")
                 .item_name(item_name)
-                .log();
+                .log(game_log);
         } else {
             crate::gamelog::Logger::new()
                 .append("You used")
@@ -198,7 +208,7 @@ fn log_for_item_damage(
                 .append("inflicting")
                 .damage(amount)
                 .append("hp damage.")
-                .log();
+                .log(game_log);
         }
     } else if target_name.1 {
         crate::gamelog::Logger::new()
@@ -208,7 +218,7 @@ fn log_for_item_damage(
             .append("inflicting")
             .damage(amount)
             .append("hp damage on you!")
-            .log();
+            .log(game_log);
     } else {
         crate::gamelog::Logger::new()
             .npc_name(&user_name.0)
@@ -219,21 +229,21 @@ fn log_for_item_damage(
             .append("inflicting")
             .damage(amount)
             .append("damage.")
-            .log();
+            .log(game_log);
     }
 }
 
-fn log_for_destroyed_item(user_name: &(String, bool), item_name: &String) {
+fn log_for_destroyed_item(game_log: &mut GameLog, user_name: &(String, bool), item_name: &String) {
     if user_name.1 {
         crate::gamelog::Logger::new()
             .append("You destroyed")
             .item_name(item_name)
-            .log();
+            .log(game_log);
     } else {
         crate::gamelog::Logger::new()
             .npc_name(&user_name.0)
             .append("destroyed")
             .item_name(item_name)
-            .log();
+            .log(game_log);
     }
 }
@@ -11,6 +11,7 @@ pub fn drop_item(
     want_drop: &WantsToDrop,
     ecs: &SubWorld,
     #[resource] dm: &MasterDungeonMap,
+    #[resource] game_log: &mut GameLog,
     commands: &mut CommandBuffer,
 ) {
     if let Ok(who) = ecs.entry_ref(want_drop.who) {
@@ -25,7 +26,7 @@ pub fn drop_item(
             crate::gamelog::Logger::new()
                 .append("You dropped the")
                 .item_name(&item_name)
-                .log();
+                .log(game_log);
         }
     }
     commands.remove(*entity);
@@ -5,13 +5,14 @@ use crate::prelude::*;
 #[read_component(Point)]
 #[write_component(Pools)]
 #[write_component(Attributes)]
-#[read_component(FieldOfView)]
+#[write_component(FieldOfView)]
 #[read_component(Item)]
 #[read_component(Name)]
 #[write_component(HungerClock)]
 #[read_component(ProvidesFood)]
 #[read_component(ProvidesDungeonMap)]
 #[read_component(TownPortal)]
+#[read_component(Trapdoor)]
 #[read_component(Damage)]
 #[read_component(AreaOfEffect)]
 #[read_component(ProvidesHealing)]
@@ -39,13 +40,34 @@ use crate::prelude::*;
 #[read_component(TileSize)]
 #[write_component(Skills)]
 #[read_component(AlwaysTargetsSelf)]
+#[read_component(Digs)]
+#[read_component(SecretPassage)]
 pub fn effects(
     ecs: &mut SubWorld,
     #[resource] map: &mut Map,
     #[resource] particle_builder: &mut ParticleBuilder,
     #[resource] turn_state: &mut TurnState,
     #[resource] dm: &mut MasterDungeonMap,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] raws: &RawMaster,
+    #[resource] spatial: &mut SpatialMap,
+    #[resource] game_log: &mut GameLog,
+    #[resource] events: &mut GameEvents,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
-    run_effects_queue(ecs, map, particle_builder, turn_state, dm, commands);
+    let mut ctx = EffectContext {
+        map,
+        particle_builder,
+        turn_state,
+        dm,
+        rng,
+        raws,
+        spatial,
+        game_log,
+        events,
+        effect_queue,
+        commands,
+    };
+    run_effects_queue(ecs, &mut ctx);
 }
@@ -16,6 +16,7 @@ pub fn encumbrance(
     entity: &Entity,
     stats: &mut Pools,
     attrs: &mut Attributes,
+    #[resource] game_log: &mut GameLog,
     commands: &mut CommandBuffer,
 ) {
     commands.remove_component::<EquipmentChanged>(*entity);
@@ -83,7 +84,7 @@ pub fn encumbrance(
             crate::gamelog::Logger::new()
                 .color(ORANGE)
                 .append("You are overburdened, and suffering from fatigue.")
-                .log();
+                .log(game_log);
         }
     }
 }
@@ -32,6 +32,13 @@ pub fn fov(ecs: &mut SubWorld, #[resource] map: &mut Map) {
             } else {
                 fov.visible_tiles = field_of_view_set(*pos, fov.radius, map);
             }
+            if !map.dark_tiles.is_empty() {
+                let viewer = *pos;
+                fov.visible_tiles.retain(|p| {
+                    !map.dark_tiles.contains(&map.point2d_to_index(*p))
+                        || DistanceAlg::Pythagoras.distance2d(viewer, *p) < 1.5
+                });
+            }
             fov.is_dirty = false;
             updated_locations.push(*pos);
         });
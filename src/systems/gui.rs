@@ -21,7 +21,7 @@ use crate::prelude::*;
 #[read_component(Weapon)]
 pub fn gui(
     ecs: &SubWorld,
-    // #[resource] gamelog: &Gamelog,
+    #[resource] game_log: &GameLog,
     #[resource] map: &Map,
     #[resource] dm: &MasterDungeonMap,
 ) {
@@ -241,7 +241,7 @@ pub fn gui(
     // Draw the log
     let mut block = TextBlock::new(1, 46 / 2, 79, 58 / 2);
     block
-        .print(&crate::gamelog::log_display())
+        .print(&game_log.log_display())
         .expect("Failed to get log contents");
     block.render(&mut BACKEND_INTERNAL.lock().consoles[3].console);
 
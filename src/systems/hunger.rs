@@ -3,15 +3,26 @@ use crate::prelude::*;
 #[system]
 #[write_component(HungerClock)]
 #[read_component(Player)]
-pub fn hunger(ecs: &mut SubWorld, commands: &mut CommandBuffer) {
+pub fn hunger(
+    ecs: &mut SubWorld,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
+    commands: &mut CommandBuffer,
+) {
     <(&mut HungerClock, Entity)>::query()
         .filter(component::<Player>() & component::<MyTurn>())
         .for_each_mut(ecs, |(clock, entity)| {
-            update_hunger(clock, entity, commands);
+            update_hunger(clock, entity, game_log, effect_queue, commands);
         });
 }
 
-fn update_hunger(clock: &mut HungerClock, entity: &Entity, _commands: &mut CommandBuffer) {
+fn update_hunger(
+    clock: &mut HungerClock,
+    entity: &Entity,
+    game_log: &mut GameLog,
+    effect_queue: &mut EffectQueue,
+    _commands: &mut CommandBuffer,
+) {
     clock.duration -= 1;
     if clock.duration < 1 {
         match clock.state {
@@ -21,7 +32,7 @@ fn update_hunger(clock: &mut HungerClock, entity: &Entity, _commands: &mut Comma
                 crate::gamelog::Logger::new()
                     .color(ORANGE)
                     .append("You are no longer well-fed.")
-                    .log();
+                    .log(game_log);
             }
             HungerState::Normal => {
                 clock.state = HungerState::Hungry;
@@ -29,7 +40,7 @@ fn update_hunger(clock: &mut HungerClock, entity: &Entity, _commands: &mut Comma
                 crate::gamelog::Logger::new()
                     .color(ORANGE)
                     .append("You are hungry.")
-                    .log();
+                    .log(game_log);
             }
             HungerState::Hungry => {
                 clock.state = HungerState::Starving;
@@ -37,15 +48,15 @@ fn update_hunger(clock: &mut HungerClock, entity: &Entity, _commands: &mut Comma
                 crate::gamelog::Logger::new()
                     .color(RED)
                     .append("You are starving!")
-                    .log();
+                    .log(game_log);
             }
             HungerState::Starving => {
                 // Inflict damage from hunger.
                 crate::gamelog::Logger::new()
                     .color(ORANGE)
                     .append("Your hunger pangs are getting painful! You suffer 1 hp damage.")
-                    .log();
-                add_effect(
+                    .log(game_log);
+                effect_queue.add_effect(
                     None,
                     EffectType::Damage { amount: 1 },
                     Targets::Single { target: *entity },
@@ -19,6 +19,7 @@ pub fn inventory(
     #[resource] key_state: &mut KeyState,
     #[resource] turn_state: &mut TurnState,
     #[resource] dm: &MasterDungeonMap,
+    #[resource] effect_queue: &mut EffectQueue,
 ) {
     match *turn_state {
         TurnState::ShowingInventory
@@ -165,7 +166,7 @@ pub fn inventory(
                     commands.remove_component::<CursedItem>(item);
                 }
                 TurnState::ShowingIdentify => {
-                    add_effect(
+                    effect_queue.add_effect(
                         Some(player),
                         EffectType::Identify,
                         Targets::Single { target: item },
@@ -192,6 +193,7 @@ pub fn identification(
     carried: &Carried,
     name: &Name,
     #[resource] dm: &mut MasterDungeonMap,
+    #[resource] raws: &RawMaster,
     ecs: &SubWorld,
     commands: &mut CommandBuffer,
 ) {
@@ -204,7 +206,7 @@ pub fn identification(
         return;
     }
 
-    if !dm.identified_items.contains(&name.0) && is_tag_magic(&name.0) {
+    if !dm.identified_items.contains(&name.0) && is_tag_magic(raws, &name.0) {
         dm.identified_items.insert(name.0.clone());
     }
 
@@ -1,13 +1,18 @@
-use crate::{prelude::*, spatial};
+use crate::prelude::*;
 
 #[system]
 #[read_component(BlocksTile)]
 #[read_component(Point)]
 #[read_component(Pools)]
 #[read_component(TileSize)]
-pub fn map_indexing(ecs: &SubWorld, #[resource] map: &mut Map) {
-    spatial::clear();
-    spatial::populate_blocked_from_map(map);
+pub fn map_indexing(
+    ecs: &SubWorld,
+    #[resource] map: &mut Map,
+    #[resource] spatial: &mut SpatialMap,
+) {
+    spatial.clear();
+    map.populate_blocked();
+    spatial.populate_blocked_from_map(map);
 
     <(
         &Point,
@@ -25,6 +30,6 @@ pub fn map_indexing(ecs: &SubWorld, #[resource] map: &mut Map) {
         }
         let size = maybe_size.map(|s| *s).unwrap_or_default();
         let idx = map.point2d_to_index(*pos);
-        spatial::index_entity(*entity, idx, blocks.is_some(), size.x, size.y, map.width);
+        spatial.index_entity(*entity, idx, blocks.is_some(), size.x, size.y, map.width);
     });
 }
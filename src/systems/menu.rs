@@ -1,5 +1,4 @@
 use crate::{prelude::*, KeyState};
-use std::path::Path;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum MainMenuSelection {
@@ -14,13 +13,19 @@ impl Default for MainMenuSelection {
     }
 }
 
-fn save_exists() -> bool {
-    Path::new("./savegame.json").exists()
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SlotMenuPurpose {
+    Load,
+    NewGame { mode: GameMode },
 }
 
 #[system]
 #[read_component(Player)]
-pub fn main_menu(#[resource] turn_state: &mut TurnState, #[resource] key_state: &KeyState) {
+pub fn main_menu(
+    #[resource] turn_state: &mut TurnState,
+    #[resource] key_state: &mut KeyState,
+    #[resource] slots: &SaveSlots,
+) {
     let selection = if let TurnState::MainMenu { selection } = *turn_state {
         selection
     } else {
@@ -46,7 +51,7 @@ pub fn main_menu(#[resource] turn_state: &mut TurnState, #[resource] key_state:
     let unselected = ColorPair::new(WHITE, BLACK);
 
     let mut y_idx = 24;
-    if save_exists() {
+    if slots.any_saved() {
         draw_batch.print_color_centered(
             y_idx,
             "Continue Game",
@@ -80,7 +85,8 @@ pub fn main_menu(#[resource] turn_state: &mut TurnState, #[resource] key_state:
 
     draw_batch.submit(10000).expect("Batch render error");
 
-    if let Some(key) = key_state.key {
+    // The other menu systems run after this one; don't let them see the key.
+    if let Some(key) = key_state.key.take() {
         match key {
             VirtualKeyCode::Escape => *turn_state = TurnState::AwaitingInput,
             VirtualKeyCode::Up => {
@@ -104,8 +110,12 @@ pub fn main_menu(#[resource] turn_state: &mut TurnState, #[resource] key_state:
                 }
             }
             VirtualKeyCode::Return => match selection {
-                MainMenuSelection::NewGame => *turn_state = TurnState::NewGame,
-                MainMenuSelection::LoadGame => *turn_state = TurnState::LoadGame,
+                MainMenuSelection::NewGame => *turn_state = TurnState::ShowingGameModes,
+                MainMenuSelection::LoadGame => {
+                    *turn_state = TurnState::ShowingSaveSlots {
+                        purpose: SlotMenuPurpose::Load,
+                    }
+                }
                 MainMenuSelection::Quit => ::std::process::exit(0),
             },
             _ => {}
@@ -113,6 +123,138 @@ pub fn main_menu(#[resource] turn_state: &mut TurnState, #[resource] key_state:
     }
 }
 
+#[system]
+pub fn game_mode_menu(
+    #[resource] turn_state: &mut TurnState,
+    #[resource] key_state: &mut KeyState,
+) {
+    if *turn_state != TurnState::ShowingGameModes {
+        return;
+    }
+
+    let mut batch = DrawBatch::new();
+    batch.target(2);
+
+    let menu_items = vec![
+        ('P', "Permadeath: loading deletes the save"),
+        ('E', "Explore: death returns to your last save"),
+    ];
+    render_menu(
+        &mut batch,
+        15,
+        24,
+        50,
+        "Choose a game mode",
+        Some("ESCAPE to cancel"),
+        &menu_items,
+    );
+    batch.submit(10000).expect("Batch render error");
+
+    let mode = match key_state.key.take() {
+        Some(VirtualKeyCode::P) => GameMode::Permadeath,
+        Some(VirtualKeyCode::E) => GameMode::Explore,
+        Some(VirtualKeyCode::Escape) => {
+            *turn_state = TurnState::MainMenu {
+                selection: MainMenuSelection::NewGame,
+            };
+            return;
+        }
+        _ => return,
+    };
+    *turn_state = TurnState::ShowingSaveSlots {
+        purpose: SlotMenuPurpose::NewGame { mode },
+    };
+}
+
+#[system]
+pub fn save_slot_menu(
+    #[resource] turn_state: &mut TurnState,
+    #[resource] key_state: &mut KeyState,
+    #[resource] slots: &SaveSlots,
+) {
+    let purpose = if let TurnState::ShowingSaveSlots { purpose } = *turn_state {
+        purpose
+    } else {
+        return;
+    };
+
+    let mut batch = DrawBatch::new();
+    batch.target(2);
+
+    let (title, footer) = match purpose {
+        SlotMenuPurpose::Load => ("Load which game?", "ESCAPE to cancel"),
+        SlotMenuPurpose::NewGame { mode: _ } => (
+            "Save the new game where?",
+            "A used slot is replaced. ESCAPE to cancel",
+        ),
+    };
+    let y = 22;
+    menu_box(
+        &mut batch,
+        5,
+        y,
+        70,
+        slots.0.len() as i32 + 3,
+        title,
+        Some(footer),
+    );
+    for (i, status) in slots.0.iter().enumerate() {
+        let description = match status {
+            SlotStatus::Empty => "Empty".to_string(),
+            SlotStatus::Saved(None) => "Saved game".to_string(),
+            SlotStatus::Saved(Some(summary)) => format!(
+                "Level {}, depth {}, {} turns, {}{}",
+                summary.level,
+                summary.depth,
+                summary.turns,
+                summary.timestamp(),
+                if summary.mode == GameMode::Explore {
+                    " (explore)"
+                } else {
+                    ""
+                }
+            ),
+        };
+        menu_option(
+            &mut batch,
+            7,
+            y + i as i32,
+            to_cp437(SaveSlot(i).letter()),
+            description,
+        );
+    }
+    batch.submit(10000).expect("Batch render error");
+
+    match key_state.key.take() {
+        Some(VirtualKeyCode::Escape) => {
+            *turn_state = TurnState::MainMenu {
+                selection: match purpose {
+                    SlotMenuPurpose::Load => MainMenuSelection::LoadGame,
+                    SlotMenuPurpose::NewGame { mode: _ } => MainMenuSelection::NewGame,
+                },
+            }
+        }
+        Some(key) => {
+            let selection = letter_to_option(key);
+            if selection < 0 || selection as usize >= slots.0.len() {
+                return;
+            }
+            let slot = SaveSlot(selection as usize);
+            match purpose {
+                SlotMenuPurpose::Load => {
+                    if let SlotStatus::Saved(_) = slots.0[slot.0] {
+                        *turn_state = TurnState::LoadGame { slot };
+                    }
+                }
+                SlotMenuPurpose::NewGame { mode } => {
+                    *turn_state = TurnState::NewGame { slot, mode };
+                }
+            }
+        }
+        None => {}
+    }
+}
+
 #[system]
 #[read_component(Player)]
 #[write_component(Point)]
@@ -124,6 +266,9 @@ pub fn cheat_menu(
     #[resource] turn_state: &mut TurnState,
     #[resource] key_state: &mut KeyState,
     #[resource] map: &mut Map,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
 ) {
     let mut batch = DrawBatch::new();
     batch.target(2);
@@ -134,6 +279,8 @@ pub fn cheat_menu(
         ('R', "Reveal the map"),
         ('G', "God mode (no death)"),
         ('L', "Level up"),
+        ('D', "Reload raw data"),
+        ('V', "Visualize map generation"),
     ];
 
     let y = (25 - (menu_items.len() / 2)) as i32;
@@ -169,16 +316,24 @@ pub fn cheat_menu(
                 *turn_state = TurnState::AwaitingInput;
             }
             VirtualKeyCode::L => {
-                level_up(ecs, map);
+                level_up(ecs, map, rng, game_log, effect_queue);
                 *turn_state = TurnState::AwaitingInput;
             }
+            VirtualKeyCode::D => *turn_state = TurnState::ReloadRaws,
+            VirtualKeyCode::V => *turn_state = TurnState::ToggleMapgenVisualizer,
             VirtualKeyCode::Escape => *turn_state = TurnState::AwaitingInput,
             _ => {}
         }
     }
 }
 
-fn level_up(ecs: &mut SubWorld, map: &Map) {
+fn level_up(
+    ecs: &mut SubWorld,
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+    game_log: &mut GameLog,
+    effect_queue: &mut EffectQueue,
+) {
     <(&Point, &mut Pools, &mut Attributes, &mut Skills)>::query()
         .filter(component::<Player>())
         .for_each_mut(ecs, |(pos, stats, attrs, skills)| {
@@ -189,38 +344,37 @@ fn level_up(ecs: &mut SubWorld, map: &Map) {
                 .color(MAGENTA)
                 .append("Congratulations, you are now level")
                 .append(format!("{}", stats.level))
-                .log();
+                .log(game_log);
 
             // Improve a random attribute
-            let mut rng = RandomNumberGenerator::new();
             match rng.roll_dice(1, 4) {
                 1 => {
                     attrs.might.base += 1;
                     crate::gamelog::Logger::new()
                         .color(GREEN)
                         .append("You feel stronger!")
-                        .log();
+                        .log(game_log);
                 }
                 2 => {
                     attrs.fitness.base += 1;
                     crate::gamelog::Logger::new()
                         .color(GREEN)
                         .append("You feel healthier!")
-                        .log();
+                        .log(game_log);
                 }
                 3 => {
                     attrs.quickness.base += 1;
                     crate::gamelog::Logger::new()
                         .color(GREEN)
                         .append("You feel quicker!")
-                        .log();
+                        .log(game_log);
                 }
                 _ => {
                     attrs.intelligence.base += 1;
                     crate::gamelog::Logger::new()
                         .color(GREEN)
                         .append("You feel smarter!")
-                        .log();
+                        .log(game_log);
                 }
             }
 
@@ -240,7 +394,7 @@ fn level_up(ecs: &mut SubWorld, map: &Map) {
 
             for i in 0..10 {
                 if pos.y - i > 1 {
-                    add_effect(
+                    effect_queue.add_effect(
                         None,
                         EffectType::Particle {
                             glyph: to_cp437('░'),
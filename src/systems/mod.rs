@@ -27,9 +27,10 @@ mod use_items;
 mod vendor;
 
 use crate::prelude::*;
+use legion::systems::{Builder, ParallelRunnable};
 
 pub use ai::*;
-pub use menu::MainMenuSelection;
+pub use menu::{MainMenuSelection, SlotMenuPurpose};
 pub use particles::ParticleBuilder;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -38,9 +39,38 @@ pub enum SystemCondition {
     RequiresShiftKey,
 }
 
-pub fn build_input_scheduler() -> Schedule {
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    Window,
+    Headless,
+}
+
+// Systems that only draw (or animate particles) are left out of headless
+// schedules; the simulation must never depend on them.
+trait RenderSystems {
+    fn add_render_system<T: ParallelRunnable + 'static>(
+        &mut self,
+        mode: RenderMode,
+        system: T,
+    ) -> &mut Self;
+}
+
+impl RenderSystems for Builder {
+    fn add_render_system<T: ParallelRunnable + 'static>(
+        &mut self,
+        mode: RenderMode,
+        system: T,
+    ) -> &mut Self {
+        match mode {
+            RenderMode::Window => self.add_system(system),
+            RenderMode::Headless => self,
+        }
+    }
+}
+
+pub fn build_input_scheduler(mode: RenderMode) -> Schedule {
     Schedule::builder()
-        .add_system(particles::update_system())
+        .add_render_system(mode, particles::update_system())
         .add_system(encumbrance::encumbrance_system())
         .flush()
         .add_system(player_input::player_input_system())
@@ -49,14 +79,14 @@ pub fn build_input_scheduler() -> Schedule {
         .flush()
         .add_system(encumbrance::encumbrance_system())
         .add_system(fov::fov_system())
-        .add_system(particles::spawn_system())
+        .add_render_system(mode, particles::spawn_system())
         .flush()
-        .add_system(lighting::lighting_system())
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
+        .add_render_system(mode, lighting::lighting_system())
+        .add_render_system(mode, map_render::map_render_system())
+        .add_render_system(mode, entity_render::entity_render_system())
         .add_system(map_indexing::map_indexing_system())
-        .add_system(gui::gui_system())
-        .add_system(tooltips::tooltips_system(SystemCondition::None))
+        .add_render_system(mode, gui::gui_system())
+        .add_render_system(mode, tooltips::tooltips_system(SystemCondition::None))
         .add_system(inventory::inventory_system())
         .add_system(vendor::vendor_system())
         .build()
@@ -68,9 +98,9 @@ pub fn update_targeting_scheduler() -> Schedule {
         .build()
 }
 
-pub fn build_ticking_scheduler() -> Schedule {
+pub fn build_ticking_scheduler(mode: RenderMode) -> Schedule {
     Schedule::builder()
-        .add_system(particles::update_system())
+        .add_render_system(mode, particles::update_system())
         .add_system(map_indexing::map_indexing_system())
         .add_system(fov::fov_system())
         .add_system(encumbrance::encumbrance_system())
@@ -109,68 +139,73 @@ pub fn build_ticking_scheduler() -> Schedule {
         .flush()
         .add_system(inventory::identification_system())
         .add_system(effects::effects_system())
-        .add_system(particles::spawn_system())
+        .add_render_system(mode, particles::spawn_system())
         .flush()
-        .add_system(lighting::lighting_system())
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(gui::gui_system())
+        .add_render_system(mode, lighting::lighting_system())
+        .add_render_system(mode, map_render::map_render_system())
+        .add_render_system(mode, entity_render::entity_render_system())
+        .add_render_system(mode, gui::gui_system())
         .add_system(bury_dead::bury_dead_system())
         // .add_system(end_turn::end_turn_system())
         .build()
 }
 
-pub fn build_ranged_scheduler() -> Schedule {
+pub fn build_ranged_scheduler(mode: RenderMode) -> Schedule {
     Schedule::builder()
-        .add_system(particles::update_system())
+        .add_render_system(mode, particles::update_system())
         .add_system(ranged_target::ranged_target_system())
         .add_system(fov::fov_system())
-        .add_system(particles::spawn_system())
+        .add_render_system(mode, particles::spawn_system())
         .flush()
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
+        .add_render_system(mode, map_render::map_render_system())
+        .add_render_system(mode, entity_render::entity_render_system())
         .add_system(map_indexing::map_indexing_system())
-        .add_system(gui::gui_system())
-        .add_system(tooltips::tooltips_system(SystemCondition::RequiresShiftKey))
+        .add_render_system(mode, gui::gui_system())
+        .add_render_system(
+            mode,
+            tooltips::tooltips_system(SystemCondition::RequiresShiftKey),
+        )
         .build()
 }
 
 pub fn build_menu_scheduler() -> Schedule {
     Schedule::builder()
         .add_system(menu::main_menu_system())
+        .add_system(menu::game_mode_menu_system())
+        .add_system(menu::save_slot_menu_system())
         .build()
 }
 
-pub fn build_cheat_menu_scheduler() -> Schedule {
+pub fn build_cheat_menu_scheduler(mode: RenderMode) -> Schedule {
     Schedule::builder()
-        .add_system(particles::update_system())
-        .add_system(lighting::lighting_system())
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(gui::gui_system())
+        .add_render_system(mode, particles::update_system())
+        .add_render_system(mode, lighting::lighting_system())
+        .add_render_system(mode, map_render::map_render_system())
+        .add_render_system(mode, entity_render::entity_render_system())
+        .add_render_system(mode, gui::gui_system())
         .add_system(menu::cheat_menu_system())
         .build()
 }
 
-pub fn build_popup_scheduler() -> Schedule {
+pub fn build_popup_scheduler(mode: RenderMode) -> Schedule {
     Schedule::builder()
-        .add_system(particles::update_system())
-        .add_system(particles::spawn_system())
+        .add_render_system(mode, particles::update_system())
+        .add_render_system(mode, particles::spawn_system())
         .flush()
-        .add_system(lighting::lighting_system())
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(gui::gui_system())
+        .add_render_system(mode, lighting::lighting_system())
+        .add_render_system(mode, map_render::map_render_system())
+        .add_render_system(mode, entity_render::entity_render_system())
+        .add_render_system(mode, gui::gui_system())
         .add_system(inventory::inventory_system())
         .build()
 }
 
-pub fn map_reveal_scheduler() -> Schedule {
+pub fn map_reveal_scheduler(mode: RenderMode) -> Schedule {
     Schedule::builder()
-        .add_system(lighting::lighting_system())
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(gui::gui_system())
+        .add_render_system(mode, lighting::lighting_system())
+        .add_render_system(mode, map_render::map_render_system())
+        .add_render_system(mode, entity_render::entity_render_system())
+        .add_render_system(mode, gui::gui_system())
         .build()
 }
 
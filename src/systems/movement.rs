@@ -16,6 +16,7 @@ pub fn movement(
     player: Option<&Player>,
     #[resource] map: &mut Map,
     #[resource] camera: &mut Camera,
+    #[resource] spatial: &mut SpatialMap,
     commands: &mut CommandBuffer,
 ) {
     let to_idx = map.point2d_to_index(want_move.destination);
@@ -35,12 +36,12 @@ pub fn movement(
         }
     }
     let tiles: Vec<_> = itiles.iter().map(|i| *i as usize).collect();
-    if !crate::spatial::tiles_blocked_ignoring_entity(tiles, *entity) {
+    if !spatial.tiles_blocked_ignoring_entity(tiles, *entity) {
         fov.is_dirty = true;
         commands.add_component(*entity, EntityMoved);
 
         let from_idx = map.point2d_to_index(*pos);
-        crate::spatial::move_entity(*entity, from_idx, to_idx);
+        spatial.move_entity(*entity, from_idx, to_idx);
         *pos = want_move.destination;
 
         if player.is_some() {
@@ -63,9 +64,10 @@ pub fn teleport(
     player: Option<&Player>,
     #[resource] map: &Map,
     #[resource] turn_state: &mut TurnState,
+    #[resource] spatial: &mut SpatialMap,
     commands: &mut CommandBuffer,
 ) {
-    if teleport.depth == map.depth {
+    if teleport.level == map.level_id() {
         // Just move around the map
         commands.add_component(
             *entity,
@@ -76,17 +78,17 @@ pub fn teleport(
     } else if player.is_some() {
         *turn_state = TurnState::LevelTeleport {
             destination: teleport.destination,
-            depth: teleport.depth,
+            level: teleport.level,
         };
         return;
     } else {
         let from_idx = map.point2d_to_index(*pos);
-        crate::spatial::remove_entity(*entity, from_idx);
+        spatial.remove_entity(*entity, from_idx);
         commands.add_component(
             *entity,
             OtherLevelPosition {
                 position: teleport.destination,
-                depth: teleport.depth,
+                level: teleport.level,
             },
         );
     }
@@ -2,6 +2,12 @@ use std::cmp::Ordering;
 
 use crate::{prelude::*, KeyState};
 
+// What a d20 plus intelligence bonus has to beat to find something hidden
+// nearby: looking on purpose, or noticing it while waiting.
+const SEARCH_DIFFICULTY: i32 = 12;
+const NOTICE_DIFFICULTY: i32 = 18;
+const SEARCH_RANGE: f32 = 2.0;
+
 #[system]
 #[write_component(Point)]
 #[read_component(Player)]
@@ -18,6 +24,8 @@ use crate::{prelude::*, KeyState};
 #[read_component(BlocksTile)]
 #[write_component(Render)]
 #[read_component(Hidden)]
+#[read_component(SecretPassage)]
+#[read_component(Attributes)]
 #[read_component(Name)]
 #[read_component(Vendor)]
 #[read_component(KnownSpells)]
@@ -26,6 +34,7 @@ use crate::{prelude::*, KeyState};
 #[read_component(Confusion)]
 #[read_component(Target)]
 #[read_component(Equipped)]
+#[read_component(Digs)]
 pub fn player_input(
     ecs: &mut SubWorld,
     commands: &mut CommandBuffer,
@@ -34,6 +43,10 @@ pub fn player_input(
     #[resource] turn_state: &mut TurnState,
     #[resource] camera: &mut Camera,
     #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] raws: &RawMaster,
+    #[resource] spatial: &mut SpatialMap,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
 ) {
     // don't process input here if we're in inventory mode.
     if *turn_state != TurnState::AwaitingInput {
@@ -46,7 +59,7 @@ pub fn player_input(
                 if key_state.shift {
                     *turn_state = use_consumable_hotkey(ecs, commands, hotkey);
                 } else if key_state.control {
-                    *turn_state = use_spell_hotkey(ecs, commands, hotkey);
+                    *turn_state = use_spell_hotkey(ecs, game_log, commands, hotkey);
                 }
                 key_state.key = None;
                 return;
@@ -68,7 +81,7 @@ pub fn player_input(
             != 0;
         if confused {
             // eat the key-press, spawn particle, miss turn.
-            add_effect(
+            effect_queue.add_effect(
                 None,
                 EffectType::Particle {
                     glyph: to_cp437('?'),
@@ -94,10 +107,24 @@ pub fn player_input(
             KeyInputResponse::ShowCheatMenu => *turn_state = TurnState::ShowCheatMenu,
             KeyInputResponse::ShowDropMenu => *turn_state = TurnState::ShowingDropItems,
             KeyInputResponse::ShowInventory => *turn_state = TurnState::ShowingInventory,
-            KeyInputResponse::UpStairs => try_climb_stairs(map, turn_state, player_pos),
-            KeyInputResponse::DownStairs => try_descend_stairs(map, turn_state, player_pos),
+            KeyInputResponse::UpStairs => try_climb_stairs(map, turn_state, player_pos, game_log),
+            KeyInputResponse::DownStairs => {
+                try_descend_stairs(map, turn_state, player_pos, game_log)
+            }
             KeyInputResponse::StandStill => {
-                try_wait_player(ecs, rng);
+                try_wait_player(ecs, raws, rng, map, game_log, commands, player_pos);
+                *turn_state = TurnState::Ticking;
+            }
+            KeyInputResponse::Search => {
+                search(
+                    ecs,
+                    map,
+                    rng,
+                    game_log,
+                    commands,
+                    player_pos,
+                    SEARCH_DIFFICULTY,
+                );
                 *turn_state = TurnState::Ticking;
             }
             KeyInputResponse::SaveGame => *turn_state = TurnState::SaveGame,
@@ -129,6 +156,8 @@ pub fn player_input(
                 destination,
                 map,
                 camera,
+                raws,
+                spatial,
                 ecs,
                 commands,
             ) {
@@ -154,6 +183,19 @@ pub fn player_input(
                         mode: VendorMode::Buy { page: 0 },
                     }
                 }
+                MoveResult::Dig { tile } => {
+                    effect_queue.add_effect(
+                        Some(player_entity),
+                        EffectType::Dig { tile },
+                        Targets::Tile {
+                            tile_idx: map.point2d_to_index(destination),
+                        },
+                    );
+                    crate::gamelog::Logger::new()
+                        .append("You dig through the rock.")
+                        .log(game_log);
+                    *turn_state = TurnState::Ticking;
+                }
             }
         }
         key_state.key = None;
@@ -338,7 +380,12 @@ fn use_consumable_hotkey(
     TurnState::Ticking
 }
 
-fn use_spell_hotkey(ecs: &mut SubWorld, commands: &mut CommandBuffer, hotkey: i32) -> TurnState {
+fn use_spell_hotkey(
+    ecs: &mut SubWorld,
+    game_log: &mut GameLog,
+    commands: &mut CommandBuffer,
+    hotkey: i32,
+) -> TurnState {
     let player_entity = <Entity>::query()
         .filter(component::<Player>())
         .iter(ecs)
@@ -377,7 +424,7 @@ fn use_spell_hotkey(ecs: &mut SubWorld, commands: &mut CommandBuffer, hotkey: i3
                 crate::gamelog::Logger::new()
                     .append("You don't have enough mana to cast")
                     .append(&spell.display_name)
-                    .log();
+                    .log(game_log);
             }
         }
     }
@@ -393,6 +440,7 @@ fn swap_entity(
     to: Point,
     map: &Map,
     camera: &mut Camera,
+    spatial: &mut SpatialMap,
     commands: &mut CommandBuffer,
 ) {
     // Swap positions.
@@ -418,8 +466,8 @@ fn swap_entity(
 
     let from_idx = map.point2d_to_index(from);
     let to_idx = map.point2d_to_index(to);
-    crate::spatial::move_entity(player_entity, from_idx, to_idx);
-    crate::spatial::move_entity(other_entity, to_idx, from_idx);
+    spatial.move_entity(player_entity, from_idx, to_idx);
+    spatial.move_entity(other_entity, to_idx, from_idx);
 
     commands.add_component(player_entity, EntityMoved);
     commands.add_component(other_entity, EntityMoved);
@@ -448,6 +496,7 @@ enum MoveResult {
     OpenedDoor,
     Attack { entity: Entity },
     OpenShop { entity: Entity },
+    Dig { tile: TileType },
 }
 
 fn try_move_player(
@@ -456,6 +505,8 @@ fn try_move_player(
     destination: Point,
     map: &mut Map,
     camera: &mut Camera,
+    raws: &RawMaster,
+    spatial: &mut SpatialMap,
     ecs: &mut SubWorld,
     commands: &mut CommandBuffer,
 ) -> MoveResult {
@@ -466,7 +517,7 @@ fn try_move_player(
 
         let reaction: Reaction;
         if let Ok(faction) = entry.get_component::<Faction>() {
-            reaction = faction_reaction(&faction.name, "Player", &RAWS.lock().unwrap());
+            reaction = faction_reaction(&faction.name, "Player", raws);
             if reaction == Reaction::Attack {
                 return MoveResult::Attack { entity };
             } else if entry.get_component::<Vendor>().is_ok() {
@@ -480,20 +531,38 @@ fn try_move_player(
                     destination,
                     map,
                     camera,
+                    spatial,
                     commands,
                 );
                 return MoveResult::Moved;
             }
-        } else if entry.get_component::<Door>().is_ok() {
-            if try_open_door(ecs, entity, destination, commands) {
-                return MoveResult::OpenedDoor;
-            }
+        } else if entry.get_component::<Door>().is_ok()
+            && entry.get_component::<Hidden>().is_err()
+            && try_open_door(ecs, entity, destination, commands)
+        {
+            return MoveResult::OpenedDoor;
+        }
+    }
+
+    // Walking into rock with a digging tool in hand tunnels into it.
+    if can_dig(map, destination_idx) {
+        let digging = <(&Digs, &Equipped)>::query()
+            .iter(ecs)
+            .find_map(|(digs, equipped)| {
+                if equipped.owner == player_entity {
+                    Some(digs.0)
+                } else {
+                    None
+                }
+            });
+        if let Some(tile) = digging {
+            return MoveResult::Dig { tile };
         }
     }
 
     // If destination isn't walkable, don't eat the turn.
     let destination_idx = map.point2d_to_index(destination);
-    if crate::spatial::is_blocked(destination_idx) {
+    if spatial.is_blocked(destination_idx) {
         return MoveResult::Stood;
     }
 
@@ -537,7 +606,15 @@ fn try_open_door(
     opened
 }
 
-fn try_wait_player(ecs: &mut SubWorld, rng: &mut RandomNumberGenerator) {
+fn try_wait_player(
+    ecs: &mut SubWorld,
+    raws: &RawMaster,
+    rng: &mut RandomNumberGenerator,
+    map: &mut Map,
+    game_log: &mut GameLog,
+    commands: &mut CommandBuffer,
+    player_pos: Point,
+) {
     // Player is standing still.
     // If well fed, we may heal.
     let hunger_state = <&HungerClock>::query()
@@ -557,8 +634,7 @@ fn try_wait_player(ecs: &mut SubWorld, rng: &mut RandomNumberGenerator) {
         .iter(ecs)
         .filter(|(pos, faction)| {
             fov.visible_tiles.contains(pos)
-                && faction_reaction(&faction.name, "Player", &RAWS.lock().unwrap())
-                    == Reaction::Attack
+                && faction_reaction(&faction.name, "Player", raws) == Reaction::Attack
         })
         .count();
 
@@ -580,6 +656,66 @@ fn try_wait_player(ecs: &mut SubWorld, rng: &mut RandomNumberGenerator) {
                 }
             });
     }
+
+    // They may notice something hidden nearby.
+    search(
+        ecs,
+        map,
+        rng,
+        game_log,
+        commands,
+        player_pos,
+        NOTICE_DIFFICULTY,
+    );
+}
+
+// Rolls for each hidden thing near the player. Found secret passages open up
+// the wall they were standing in for.
+fn search(
+    ecs: &mut SubWorld,
+    map: &mut Map,
+    rng: &mut RandomNumberGenerator,
+    game_log: &mut GameLog,
+    commands: &mut CommandBuffer,
+    player_pos: Point,
+    difficulty: i32,
+) {
+    let bonus = <&Attributes>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+        .map_or(0, |attributes| attributes.intelligence.bonus);
+
+    let hidden: Vec<(Entity, Point, String, Option<SecretPassage>)> =
+        <(Entity, &Point, &Name, Option<&SecretPassage>)>::query()
+            .filter(component::<Hidden>())
+            .iter(ecs)
+            .filter(|(_, pos, _, _)| {
+                DistanceAlg::Pythagoras.distance2d(player_pos, **pos) <= SEARCH_RANGE
+            })
+            .map(|(entity, pos, name, secret)| (*entity, *pos, name.0.clone(), secret.copied()))
+            .collect();
+
+    for (entity, pos, name, secret) in hidden {
+        if rng.roll_dice(1, 20) + bonus < difficulty {
+            continue;
+        }
+        commands.remove_component::<Hidden>(entity);
+
+        if let Some(secret) = secret {
+            let idx = map.point2d_to_index(pos);
+            map.tiles[idx] = secret.opens_to;
+            <&mut FieldOfView>::query()
+                .iter_mut(ecs)
+                .filter(|fov| fov.visible_tiles.contains(&pos))
+                .for_each(|fov| fov.is_dirty = true);
+        }
+
+        crate::gamelog::Logger::new()
+            .append("You found a")
+            .item_name(&name)
+            .log(game_log);
+    }
 }
 
 enum KeyInputResponse {
@@ -595,6 +731,7 @@ enum KeyInputResponse {
     SaveGame,
     CycleTargets,
     FireRangedWeapon,
+    Search,
 }
 
 fn process_key_input(key: VirtualKeyCode, key_state: &mut KeyState) -> KeyInputResponse {
@@ -635,6 +772,7 @@ fn process_key_input(key: VirtualKeyCode, key_state: &mut KeyState) -> KeyInputR
             delta: Point::new(1, 1),
         },
         VirtualKeyCode::V => KeyInputResponse::CycleTargets,
+        VirtualKeyCode::S => KeyInputResponse::Search,
         VirtualKeyCode::Backslash => KeyInputResponse::ShowCheatMenu,
         VirtualKeyCode::Period => {
             if key_state.shift {
@@ -658,28 +796,42 @@ fn process_key_input(key: VirtualKeyCode, key_state: &mut KeyState) -> KeyInputR
     }
 }
 
-fn try_climb_stairs(map: &Map, turn_state: &mut TurnState, player_pos: Point) {
+fn try_climb_stairs(
+    map: &Map,
+    turn_state: &mut TurnState,
+    player_pos: Point,
+    game_log: &mut GameLog,
+) {
     let player_idx = map.point2d_to_index(player_pos);
     if map.tiles[player_idx] == TileType::UpStairs {
-        *turn_state = TurnState::PreviousLevel;
-        return;
+        if let Some(level) = map.exit_from(player_idx) {
+            *turn_state = TurnState::ChangeLevel { level };
+            return;
+        }
     }
 
     crate::gamelog::Logger::new()
         .append("There is no way up from here.")
-        .log();
+        .log(game_log);
 }
 
-fn try_descend_stairs(map: &Map, turn_state: &mut TurnState, player_pos: Point) {
+fn try_descend_stairs(
+    map: &Map,
+    turn_state: &mut TurnState,
+    player_pos: Point,
+    game_log: &mut GameLog,
+) {
     let player_idx = map.point2d_to_index(player_pos);
     if map.tiles[player_idx] == TileType::DownStairs {
-        *turn_state = TurnState::NextLevel;
-        return;
+        if let Some(level) = map.exit_from(player_idx) {
+            *turn_state = TurnState::ChangeLevel { level };
+            return;
+        }
     }
 
     crate::gamelog::Logger::new()
         .append("There is no way down from here.")
-        .log();
+        .log(game_log);
 }
 
 fn try_collect_items(
@@ -30,6 +30,8 @@ pub fn ranged_combat(
     ecs: &mut SubWorld,
     #[resource] rng: &mut RandomNumberGenerator,
     #[resource] map: &Map,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     let victim = wants_attack.target;
@@ -54,7 +56,7 @@ pub fn ranged_combat(
     }
 
     let victim_pos = victim_entry.get_component::<Point>().unwrap();
-    add_effect(
+    effect_queue.add_effect(
         None,
         EffectType::ParticleProjectile {
             glyph: to_cp437('*'),
@@ -131,7 +133,7 @@ pub fn ranged_combat(
         //     "Damage: {} + {}attr + {}skill + {}weapon = {}",
         //     base_damage, attr_damage_bonus, skill_damage_bonus, &weapon_info.damage_die, amount,
         // );
-        add_effect(
+        effect_queue.add_effect(
             Some(*attacker),
             EffectType::Damage { amount },
             Targets::Single { target: victim },
@@ -144,7 +146,7 @@ pub fn ranged_combat(
                 } else {
                     Targets::Single { target: victim }
                 };
-                add_effect(
+                effect_queue.add_effect(
                     Some(*attacker),
                     EffectType::ItemUse {
                         item: weapon_entity.unwrap(),
@@ -160,8 +162,8 @@ pub fn ranged_combat(
             .append("considers attacking")
             .npc_name(&victim_name)
             .append("but misjudges the timing!")
-            .log();
-        add_effect(
+            .log(game_log);
+        effect_queue.add_effect(
             None,
             EffectType::Particle {
                 glyph: to_cp437('‼'),
@@ -177,8 +179,8 @@ pub fn ranged_combat(
             .append("attacks")
             .npc_name(&victim_name)
             .append("but can't connect.")
-            .log();
-        add_effect(
+            .log(game_log);
+        effect_queue.add_effect(
             None,
             EffectType::Particle {
                 glyph: to_cp437('‼'),
@@ -10,7 +10,13 @@ use crate::prelude::*;
 #[read_component(TeleportTo)]
 #[read_component(SingleActivation)]
 #[read_component(Player)]
-pub fn trigger(ecs: &SubWorld, commands: &mut CommandBuffer, #[resource] map: &Map) {
+pub fn trigger(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &mut Map,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
+) {
     let moved_entities: Vec<(Entity, Point)> = <(Entity, &Point)>::query()
         .filter(component::<EntityMoved>())
         .iter(ecs)
@@ -21,6 +27,27 @@ pub fn trigger(ecs: &SubWorld, commands: &mut CommandBuffer, #[resource] map: &M
         // Remove the movement marker
         commands.remove_component::<EntityMoved>(entity);
 
+        // The first time the player walks into a special room, say what it is.
+        let is_player = ecs
+            .entry_ref(entity)
+            .is_ok_and(|e| e.get_component::<Player>().is_ok());
+        if is_player {
+            map.special_rooms
+                .iter_mut()
+                .filter(|room| !room.entered && room.area.point_in_rect(pos))
+                .for_each(|room| {
+                    room.entered = true;
+                    crate::gamelog::Logger::new()
+                        .color(CYAN)
+                        .append(&room.message)
+                        .log(game_log);
+                });
+        }
+
+        if map.tiles[map.point2d_to_index(pos)] == TileType::Pit {
+            effect_queue.add_effect(None, EffectType::Fall, Targets::Single { target: entity });
+        }
+
         <(Entity, &Point, &Name)>::query()
             .filter(component::<EntryTrigger>())
             .iter(ecs)
@@ -31,11 +58,11 @@ pub fn trigger(ecs: &SubWorld, commands: &mut CommandBuffer, #[resource] map: &M
                     .append(&trigger_name.0)
                     .color(WHITE)
                     .append("triggers!")
-                    .log();
+                    .log(game_log);
 
                 // add into the effects system
                 let tile_idx = map.point2d_to_index(pos);
-                add_effect(
+                effect_queue.add_effect(
                     Some(entity),
                     EffectType::TriggerFire {
                         trigger: *trigger_entity,
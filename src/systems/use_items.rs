@@ -30,6 +30,7 @@ pub fn use_items(
     aoe: Option<&AreaOfEffect>,
     ecs: &mut SubWorld,
     #[resource] map: &mut Map,
+    #[resource] effect_queue: &mut EffectQueue,
     commands: &mut CommandBuffer,
 ) {
     commands.remove_component::<UseItem>(*entity);
@@ -41,7 +42,7 @@ pub fn use_items(
         .unwrap();
 
     if use_item.user == *player_entity {
-        add_effect(
+        effect_queue.add_effect(
             Some(*player_entity),
             EffectType::Identify,
             Targets::Single { target: *entity },
@@ -49,7 +50,7 @@ pub fn use_items(
     }
 
     // Call into the effects system
-    add_effect(
+    effect_queue.add_effect(
         Some(use_item.user),
         EffectType::ItemUse { item: *entity },
         match use_item.target {
@@ -81,6 +82,7 @@ pub fn spellcasting(
     entity: &Entity,
     wants_cast: &WantsToCastSpell,
     #[resource] map: &Map,
+    #[resource] effect_queue: &mut EffectQueue,
     ecs: &SubWorld,
     commands: &mut CommandBuffer,
 ) {
@@ -89,7 +91,7 @@ pub fn spellcasting(
     let spell = ecs.entry_ref(wants_cast.spell).unwrap();
 
     // Call into the effects system
-    add_effect(
+    effect_queue.add_effect(
         Some(*entity),
         EffectType::CastSpell {
             spell: wants_cast.spell,
@@ -130,6 +132,8 @@ pub fn equip(
     magic: Option<&MagicItem>,
     cursed: Option<&CursedItem>,
     #[resource] dm: &MasterDungeonMap,
+    #[resource] game_log: &mut GameLog,
+    #[resource] effect_queue: &mut EffectQueue,
     ecs: &SubWorld,
     commands: &mut CommandBuffer,
 ) {
@@ -151,7 +155,7 @@ pub fn equip(
                 .append("unequipped")
                 .color(CYAN)
                 .append(&name.0)
-                .log();
+                .log(game_log);
         } else {
             crate::gamelog::Logger::new()
                 .append("You cannot unequip")
@@ -159,7 +163,7 @@ pub fn equip(
                 .append(&name.0)
                 .color(WHITE)
                 .append(" - it is cursed!")
-                .log();
+                .log(game_log);
         }
         return;
     }
@@ -181,7 +185,7 @@ pub fn equip(
                     .append("unequipped")
                     .color(CYAN)
                     .append(&n.0)
-                    .log();
+                    .log(game_log);
             } else {
                 crate::gamelog::Logger::new()
                     .append("You cannot unequip")
@@ -189,7 +193,7 @@ pub fn equip(
                     .append(&name.0)
                     .color(WHITE)
                     .append(" - it is cursed!")
-                    .log();
+                    .log(game_log);
                 equip_blocked = true;
             }
         });
@@ -211,12 +215,12 @@ pub fn equip(
         .append("equipped")
         .color(CYAN)
         .append(&name.0)
-        .log();
+        .log(game_log);
     commands.add_component(use_item.user, EquipmentChanged);
 
     // auto-identify if it's magic
     if magic.is_some() && !dm.identified_items.contains(&name.0) {
-        add_effect(
+        effect_queue.add_effect(
             Some(use_item.user),
             EffectType::Identify,
             Targets::Single { target: *entity },
@@ -12,6 +12,7 @@ pub fn vendor(
     #[resource] turn_state: &mut TurnState,
     #[resource] key_state: &mut KeyState,
     #[resource] dm: &MasterDungeonMap,
+    #[resource] raws: &RawMaster,
     commands: &mut CommandBuffer,
 ) {
     let player_entity = <Entity>::query()
@@ -23,9 +24,16 @@ pub fn vendor(
     match *turn_state {
         TurnState::ShowingVendor { vendor, mode } => {
             let new_state = match mode {
-                VendorMode::Buy { page } => {
-                    vendor_buy_menu(ecs, vendor, page, player_entity, key_state, dm, commands)
-                }
+                VendorMode::Buy { page } => vendor_buy_menu(
+                    ecs,
+                    vendor,
+                    page,
+                    player_entity,
+                    key_state,
+                    raws,
+                    dm,
+                    commands,
+                ),
                 VendorMode::Sell { page } => {
                     vendor_sell_menu(ecs, vendor, page, player_entity, key_state, commands)
                 }
@@ -110,6 +118,7 @@ fn vendor_buy_menu(
     page: i32,
     player: Entity,
     key_state: &mut KeyState,
+    raws: &RawMaster,
     dm: &MasterDungeonMap,
     commands: &mut CommandBuffer,
 ) -> Option<TurnState> {
@@ -121,8 +130,6 @@ fn vendor_buy_menu(
         .clone();
     std::mem::drop(vendor_entry);
 
-    let raws = &RAWS.lock().unwrap();
-
     let inventory = get_vendor_items(&categories, raws);
     let mut batch = DrawBatch::new();
     batch.target(2);
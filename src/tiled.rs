@@ -0,0 +1,551 @@
+use std::collections::HashMap;
+
+use crate::prelude::*;
+
+// Levels go to and from Tiled's JSON map format (.tmj), so generated floors
+// can be polished by hand and brought back as prefab levels. Tiles are drawn
+// with the game's 8x8 font; which terrain a tile stands for is named by its
+// "tile" property, so the tileset can be redrawn freely.
+pub const TILESET_IMAGE: &str = "terminal8x8.png";
+const TILE_SIZE: i32 = 8;
+const FONT_COLUMNS: i32 = 16;
+
+// The terrain the tileset offers; a new TileType needs adding here as well as
+// to terrain_glyph.
+const TERRAIN: [TileType; 15] = [
+    TileType::Wall,
+    TileType::Stalactite,
+    TileType::Stalagmite,
+    TileType::Floor,
+    TileType::DownStairs,
+    TileType::Road,
+    TileType::Grass,
+    TileType::ShallowWater,
+    TileType::DeepWater,
+    TileType::WoodFloor,
+    TileType::Bridge,
+    TileType::Gravel,
+    TileType::UpStairs,
+    TileType::Chasm,
+    TileType::Pit,
+];
+
+// Tiled keeps flips and rotations in the top bits of a gid.
+const GID_FLAGS: u32 = 0xF000_0000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TiledMap {
+    #[serde(rename = "type", default)]
+    pub map_type: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub orientation: String,
+    #[serde(default)]
+    pub renderorder: String,
+    pub width: usize,
+    pub height: usize,
+    pub tilewidth: i32,
+    pub tileheight: i32,
+    #[serde(default)]
+    pub infinite: bool,
+    #[serde(default)]
+    pub nextlayerid: u32,
+    #[serde(default)]
+    pub nextobjectid: u32,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+    #[serde(default)]
+    pub tilesets: Vec<TiledTileset>,
+    #[serde(default)]
+    pub layers: Vec<TiledLayer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TiledProperty {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub value_type: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TiledTileset {
+    pub firstgid: u32,
+    // Set when the tileset lives in its own file, which we don't read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub imagewidth: i32,
+    #[serde(default)]
+    pub imageheight: i32,
+    #[serde(default)]
+    pub tilewidth: i32,
+    #[serde(default)]
+    pub tileheight: i32,
+    #[serde(default)]
+    pub columns: i32,
+    #[serde(default)]
+    pub tilecount: u32,
+    #[serde(default)]
+    pub margin: i32,
+    #[serde(default)]
+    pub spacing: i32,
+    #[serde(default)]
+    pub tiles: Vec<TiledTile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TiledTile {
+    pub id: u32,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TiledLayer {
+    pub id: u32,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub layer_type: String,
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<usize>,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    // Tile layers saved as base64 or compressed have a string here instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draworder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objects: Option<Vec<TiledObject>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TiledObject {
+    pub id: u32,
+    #[serde(default)]
+    pub name: String,
+    // Tiled 1.9 renamed this "class", but still saves it as "type".
+    #[serde(rename = "type", default)]
+    pub object_type: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub point: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polyline: Option<Vec<TiledPoint>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TiledPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+// What a Tiled map brings back into a level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledLevel {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<TileType>,
+    pub spawns: Vec<(Point, String)>,
+    pub start: Option<Point>,
+    pub rooms: Vec<Rect>,
+    pub corridors: Vec<Vec<usize>>,
+    pub name: Option<String>,
+    pub depth: Option<i32>,
+    pub theme: Option<MapTheme>,
+}
+
+impl TiledMap {
+    pub fn from_level(level: &BuilderMap) -> TiledMap {
+        TiledMap::new(
+            &level.map,
+            &level.spawn_list,
+            level.starting_position,
+            level.rooms.as_deref().unwrap_or(&[]),
+            level.corridors.as_deref().unwrap_or(&[]),
+        )
+    }
+
+    pub fn from_snapshot(snapshot: &MapSnapshot) -> TiledMap {
+        TiledMap::new(
+            &snapshot.map,
+            &snapshot.spawn_list,
+            snapshot.starting_position,
+            snapshot.rooms.as_deref().unwrap_or(&[]),
+            snapshot.corridors.as_deref().unwrap_or(&[]),
+        )
+    }
+
+    // Terrain on a tile layer, then object layers for spawns, the start,
+    // rooms and corridors.
+    pub fn new(
+        map: &Map,
+        spawn_list: &[(Point, String)],
+        starting_position: Option<Point>,
+        rooms: &[Rect],
+        corridors: &[Vec<usize>],
+    ) -> TiledMap {
+        let mut tiled = TiledMap {
+            map_type: "map".to_string(),
+            version: "1.10".to_string(),
+            orientation: "orthogonal".to_string(),
+            renderorder: "right-down".to_string(),
+            width: map.width,
+            height: map.height,
+            tilewidth: TILE_SIZE,
+            tileheight: TILE_SIZE,
+            infinite: false,
+            nextlayerid: 1,
+            nextobjectid: 1,
+            properties: vec![
+                TiledProperty::string("name", &map.name),
+                TiledProperty {
+                    name: "depth".to_string(),
+                    value_type: "int".to_string(),
+                    value: map.depth.into(),
+                },
+                TiledProperty::string(
+                    "theme",
+                    &serde_json::to_string(&map.theme).unwrap_or_default(),
+                ),
+            ],
+            tilesets: vec![terrain_tileset()],
+            layers: Vec::new(),
+        };
+
+        let data: Vec<u32> = map.tiles.iter().map(|t| terrain_gid(*t)).collect();
+        tiled.add_layer("tiles", "tilelayer", |layer| {
+            layer.width = Some(map.width);
+            layer.height = Some(map.height);
+            layer.data = Some(data.into());
+        });
+
+        // Spawn tables roll "None" for nothing at all.
+        let spawns: Vec<TiledObject> = spawn_list
+            .iter()
+            .filter(|(_, name)| name != "None")
+            .map(|(pos, name)| tiled.tile_object(name, "spawn", *pos))
+            .collect();
+        tiled.add_objects("spawns", spawns);
+
+        let markers: Vec<TiledObject> = starting_position
+            .iter()
+            .map(|start| tiled.tile_object("Start", "start", *start))
+            .collect();
+        tiled.add_objects("markers", markers);
+
+        let rooms: Vec<TiledObject> = rooms
+            .iter()
+            .map(|room| {
+                let mut object = tiled.tile_object("", "room", Point::new(room.x1, room.y1));
+                object.width = (room.width() * TILE_SIZE) as f32;
+                object.height = (room.height() * TILE_SIZE) as f32;
+                object
+            })
+            .collect();
+        tiled.add_objects("rooms", rooms);
+
+        // Corridors run through the middle of their tiles.
+        let corridors: Vec<TiledObject> = corridors
+            .iter()
+            .filter(|corridor| !corridor.is_empty())
+            .map(|corridor| {
+                let first = map.index_to_point2d(corridor[0]);
+                let mut object = tiled.tile_object("", "corridor", first);
+                object.x += (TILE_SIZE / 2) as f32;
+                object.y += (TILE_SIZE / 2) as f32;
+                object.width = 0.0;
+                object.height = 0.0;
+                object.polyline = Some(
+                    corridor
+                        .iter()
+                        .map(|idx| {
+                            let pos = map.index_to_point2d(*idx) - first;
+                            TiledPoint {
+                                x: (pos.x * TILE_SIZE) as f32,
+                                y: (pos.y * TILE_SIZE) as f32,
+                            }
+                        })
+                        .collect(),
+                );
+                object
+            })
+            .collect();
+        tiled.add_objects("corridors", corridors);
+
+        tiled
+    }
+
+    // Reads the layers `from_level` writes back out. Terrain comes from the
+    // first tile layer; objects are recognised by their type wherever they
+    // are, so designers may move them between layers.
+    pub fn to_level(&self) -> Result<TiledLevel, String> {
+        if self.infinite {
+            return Err("infinite maps are not supported".to_string());
+        }
+        let terrain = self.terrain_by_gid()?;
+        let layer = self
+            .layers
+            .iter()
+            .find(|l| l.layer_type == "tilelayer")
+            .ok_or("there is no tile layer")?;
+        let data: Vec<u32> = match &layer.data {
+            Some(serde_json::Value::Array(data)) => data
+                .iter()
+                .map(|gid| gid.as_u64().unwrap_or(0) as u32)
+                .collect(),
+            _ => return Err(format!("layer {} must be saved as CSV", layer.name)),
+        };
+        let (width, height) = (
+            layer.width.unwrap_or(self.width),
+            layer.height.unwrap_or(self.height),
+        );
+        if data.len() != width * height {
+            return Err(format!(
+                "layer {} has {} tiles for a {}x{} map",
+                layer.name,
+                data.len(),
+                width,
+                height
+            ));
+        }
+        let tiles = data
+            .iter()
+            .enumerate()
+            .map(|(idx, gid)| match gid & !GID_FLAGS {
+                // Nothing painted: leave it solid.
+                0 => Ok(TileType::Wall),
+                gid => terrain.get(&gid).copied().ok_or(format!(
+                    "tile {},{} has no \"tile\" property",
+                    idx % width,
+                    idx / width
+                )),
+            })
+            .collect::<Result<Vec<TileType>, String>>()?;
+
+        let mut level = TiledLevel {
+            width,
+            height,
+            tiles,
+            spawns: Vec::new(),
+            start: None,
+            rooms: Vec::new(),
+            corridors: Vec::new(),
+            name: self
+                .property("name")
+                .and_then(|v| v.as_str().map(String::from)),
+            depth: self
+                .property("depth")
+                .and_then(|v| v.as_i64())
+                .map(|d| d as i32),
+            theme: match self.property("theme").and_then(|v| v.as_str()) {
+                Some(theme) => {
+                    Some(serde_json::from_str(theme).map_err(|e| format!("bad theme: {}", e))?)
+                }
+                None => None,
+            },
+        };
+
+        let objects = self.layers.iter().flat_map(|l| l.objects.iter().flatten());
+        for object in objects {
+            let pos = self.tile_at(object.x, object.y);
+            match object.object_type.as_str() {
+                "spawn" => level.spawns.push((pos, object.name.clone())),
+                "start" => level.start = Some(pos),
+                "room" => {
+                    let end = self.tile_at(object.x + object.width, object.y + object.height);
+                    level
+                        .rooms
+                        .push(Rect::with_exact(pos.x, pos.y, end.x, end.y));
+                }
+                "corridor" => level.corridors.push(
+                    object
+                        .polyline
+                        .iter()
+                        .flatten()
+                        .map(|p| self.tile_at(object.x + p.x, object.y + p.y))
+                        .filter(|p| {
+                            p.x >= 0 && p.y >= 0 && p.x < width as i32 && p.y < height as i32
+                        })
+                        .map(|p| p.y as usize * width + p.x as usize)
+                        .collect(),
+                ),
+                _ => {}
+            }
+        }
+        Ok(level)
+    }
+
+    fn terrain_by_gid(&self) -> Result<HashMap<u32, TileType>, String> {
+        let mut terrain = HashMap::new();
+        for tileset in self.tilesets.iter() {
+            if let Some(source) = &tileset.source {
+                return Err(format!("tileset {} must be embedded in the map", source));
+            }
+            for tile in tileset.tiles.iter() {
+                let name = tile
+                    .properties
+                    .iter()
+                    .find(|p| p.name == "tile")
+                    .and_then(|p| p.value.as_str());
+                if let Some(name) = name {
+                    let tile_type = serde_json::from_value(name.into())
+                        .map_err(|_| format!("unknown tile [{}]", name))?;
+                    terrain.insert(tileset.firstgid + tile.id, tile_type);
+                }
+            }
+        }
+        Ok(terrain)
+    }
+
+    fn property(&self, name: &str) -> Option<&serde_json::Value> {
+        self.properties
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| &p.value)
+    }
+
+    fn tile_at(&self, x: f32, y: f32) -> Point {
+        Point::new(
+            (x / self.tilewidth.max(1) as f32).floor() as i32,
+            (y / self.tileheight.max(1) as f32).floor() as i32,
+        )
+    }
+
+    fn tile_object(&mut self, name: &str, object_type: &str, pos: Point) -> TiledObject {
+        let id = self.nextobjectid;
+        self.nextobjectid += 1;
+        TiledObject {
+            id,
+            name: name.to_string(),
+            object_type: object_type.to_string(),
+            x: (pos.x * self.tilewidth) as f32,
+            y: (pos.y * self.tileheight) as f32,
+            width: self.tilewidth as f32,
+            height: self.tileheight as f32,
+            rotation: 0.0,
+            visible: true,
+            point: false,
+            polyline: None,
+        }
+    }
+
+    fn add_objects(&mut self, name: &str, objects: Vec<TiledObject>) {
+        self.add_layer(name, "objectgroup", |layer| {
+            layer.draworder = Some("topdown".to_string());
+            layer.objects = Some(objects);
+        });
+    }
+
+    fn add_layer<F>(&mut self, name: &str, layer_type: &str, fill: F)
+    where
+        F: FnOnce(&mut TiledLayer),
+    {
+        let mut layer = TiledLayer {
+            id: self.nextlayerid,
+            name: name.to_string(),
+            layer_type: layer_type.to_string(),
+            x: 0,
+            y: 0,
+            width: None,
+            height: None,
+            opacity: 1.0,
+            visible: true,
+            data: None,
+            draworder: None,
+            objects: None,
+        };
+        fill(&mut layer);
+        self.nextlayerid += 1;
+        self.layers.push(layer);
+    }
+}
+
+impl TiledProperty {
+    fn string(name: &str, value: &str) -> TiledProperty {
+        TiledProperty {
+            name: name.to_string(),
+            value_type: "string".to_string(),
+            value: value.into(),
+        }
+    }
+}
+
+fn terrain_tileset() -> TiledTileset {
+    TiledTileset {
+        firstgid: 1,
+        source: None,
+        name: "terrain".to_string(),
+        image: TILESET_IMAGE.to_string(),
+        imagewidth: FONT_COLUMNS * TILE_SIZE,
+        imageheight: FONT_COLUMNS * TILE_SIZE,
+        tilewidth: TILE_SIZE,
+        tileheight: TILE_SIZE,
+        columns: FONT_COLUMNS,
+        tilecount: (FONT_COLUMNS * FONT_COLUMNS) as u32,
+        margin: 0,
+        spacing: 0,
+        tiles: TERRAIN
+            .iter()
+            .map(|tile| TiledTile {
+                id: to_cp437(terrain_glyph(*tile)) as u32,
+                properties: vec![TiledProperty::string("tile", &format!("{:?}", tile))],
+            })
+            .collect(),
+    }
+}
+
+fn terrain_glyph(tile: TileType) -> char {
+    match tile {
+        TileType::Wall => '#',
+        TileType::Stalactite => '╨',
+        TileType::Stalagmite => '╥',
+        TileType::Floor => '.',
+        TileType::DownStairs => '>',
+        TileType::Road => '≡',
+        TileType::Grass => '"',
+        TileType::ShallowWater => '~',
+        TileType::DeepWater => '≈',
+        TileType::WoodFloor => '=',
+        TileType::Bridge => '▒',
+        TileType::Gravel => ';',
+        TileType::UpStairs => '<',
+        TileType::Chasm => '▓',
+        TileType::Pit => '○',
+    }
+}
+
+fn terrain_gid(tile: TileType) -> u32 {
+    to_cp437(terrain_glyph(tile)) as u32 + 1
+}
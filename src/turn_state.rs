@@ -21,17 +21,54 @@ pub enum TurnState {
     RangedTargeting { range: i32, item: Entity },
 
     MainMenu { selection: MainMenuSelection },
+    ShowingGameModes,
+    ShowingSaveSlots { purpose: SlotMenuPurpose },
 
-    NewGame,
+    NewGame { slot: SaveSlot, mode: GameMode },
     SaveGame,
-    LoadGame,
+    LoadGame { slot: SaveSlot },
     GameOver,
+    ShowingSaveError { in_game: bool },
 
     NextLevel,
-    PreviousLevel,
+    ChangeLevel { level: LevelId },
     TownPortal,
-    LevelTeleport { destination: Point, depth: i32 },
+    LevelTeleport { destination: Point, level: LevelId },
 
     MapBuilding { step: usize },
     ShowCheatMenu,
+    ReloadRaws,
+    ToggleMapgenVisualizer,
+}
+
+impl TurnState {
+    // States that sit still until a key is pressed; everything else advances
+    // on its own from one frame to the next.
+    pub fn awaits_input(&self) -> bool {
+        matches!(
+            self,
+            TurnState::AwaitingInput
+                | TurnState::ShowingInventory
+                | TurnState::ShowingDropItems
+                | TurnState::ShowingVendor { .. }
+                | TurnState::ShowingRemoveCurse
+                | TurnState::ShowingIdentify
+                | TurnState::RangedTargeting { .. }
+                | TurnState::MainMenu { .. }
+                | TurnState::ShowingGameModes
+                | TurnState::ShowingSaveSlots { .. }
+                | TurnState::GameOver
+                | TurnState::ShowingSaveError { .. }
+                | TurnState::ShowCheatMenu
+        )
+    }
+
+    pub fn in_main_menu(&self) -> bool {
+        matches!(
+            self,
+            TurnState::MainMenu { .. }
+                | TurnState::ShowingGameModes
+                | TurnState::ShowingSaveSlots { .. }
+        )
+    }
 }
@@ -0,0 +1,161 @@
+use rrlt::headless::{random_walk, HeadlessGame};
+use rrlt::prelude::*;
+use rrlt::KeyState;
+
+// Short enough that the wandering player is still alive at the end.
+const TURNS: i32 = 50;
+
+fn press(game: &mut HeadlessGame, key: VirtualKeyCode) -> TurnState {
+    game.send(KeyState::with_key(key))
+}
+
+// Takes the cheat menu's stairs to the next level.
+fn descend(game: &mut HeadlessGame) -> TurnState {
+    press(game, VirtualKeyCode::Backslash);
+    press(game, VirtualKeyCode::T)
+}
+
+fn player_pos(game: &HeadlessGame) -> Point {
+    *<&Point>::query()
+        .filter(component::<Player>())
+        .iter(game.world())
+        .next()
+        .unwrap()
+}
+
+fn level_id(game: &HeadlessGame) -> LevelId {
+    game.resources().get::<Map>().unwrap().level_id()
+}
+
+// Digs a pit on open ground next to the player and walks into it.
+fn step_into_pit(game: &mut HeadlessGame) -> TurnState {
+    let pos = player_pos(game);
+    let taken: Vec<Point> = <&Point>::query().iter(game.world()).copied().collect();
+    let (target, key) = {
+        let map = game.resources().get::<Map>().unwrap();
+        [
+            (Point::new(-1, 0), VirtualKeyCode::Left),
+            (Point::new(1, 0), VirtualKeyCode::Right),
+            (Point::new(0, -1), VirtualKeyCode::Up),
+            (Point::new(0, 1), VirtualKeyCode::Down),
+        ]
+        .into_iter()
+        .map(|(delta, key)| (pos + delta, key))
+        .find(|(target, _)| {
+            map.try_idx(*target)
+                .is_some_and(|idx| map.tiles[idx].is_walkable() && map.exit_from(idx).is_none())
+                && !taken.contains(target)
+        })
+        .expect("No open ground next to the player")
+    };
+    {
+        let mut map = game.resources_mut().get_mut::<Map>().unwrap();
+        let idx = map.point2d_to_index(target);
+        map.tiles[idx] = TileType::Pit;
+    }
+    press(game, key)
+}
+
+#[test]
+fn a_new_game_waits_for_input_in_town() {
+    let game = HeadlessGame::new(GameSeed(7));
+    assert_eq!(game.turn_state(), TurnState::AwaitingInput);
+
+    let report = game.report();
+    assert_eq!(report.depth, 0);
+    assert_eq!(report.level, 1);
+    assert!(report.hit_points > 0);
+    assert!(!report.died);
+}
+
+#[test]
+fn a_short_walk_leaves_the_player_alive() {
+    let report = random_walk(GameSeed(42), TURNS);
+    assert!(report.turns > 0);
+    assert_eq!(report.depth, 0);
+    assert!(report.hit_points > 0);
+    assert!(!report.died);
+}
+
+#[test]
+fn the_same_seed_plays_the_same_game() {
+    assert_eq!(
+        random_walk(GameSeed(1), TURNS),
+        random_walk(GameSeed(1), TURNS)
+    );
+}
+
+#[test]
+fn the_same_seed_builds_the_same_levels() {
+    let mut levels = Vec::new();
+    for _ in 0..2 {
+        let mut game = HeadlessGame::new(GameSeed(3));
+        let mut tiles = Vec::new();
+        for depth in 1..=3 {
+            assert_eq!(descend(&mut game), TurnState::AwaitingInput);
+            assert_eq!(game.report().depth, depth);
+            assert!(!game.report().died);
+            tiles.push(game.resources().get::<Map>().unwrap().tiles.clone());
+        }
+        levels.push(tiles);
+    }
+    assert!(levels[0] == levels[1]);
+}
+
+#[test]
+fn falling_into_a_pit_lands_on_the_level_below() {
+    let mut game = HeadlessGame::new(GameSeed(3));
+    descend(&mut game);
+    descend(&mut game);
+    assert_eq!(game.report().depth, 2);
+
+    assert_eq!(step_into_pit(&mut game), TurnState::AwaitingInput);
+    assert_eq!(level_id(&game), LevelId::main(3));
+    assert!(!game.report().died);
+
+    let map = game.resources().get::<Map>().unwrap();
+    let idx = map.point2d_to_index(player_pos(&game));
+    assert!(map.tiles[idx].is_walkable());
+}
+
+#[test]
+fn a_pit_at_the_bottom_of_a_branch_goes_nowhere() {
+    let mut game = HeadlessGame::new(GameSeed(3));
+    let crypt = game
+        .resources()
+        .get::<MasterDungeonMap>()
+        .unwrap()
+        .branch_id("Crypt")
+        .unwrap();
+    let bottom = LevelId::new(crypt, 2);
+    game.resources_mut()
+        .insert(TurnState::ChangeLevel { level: bottom });
+    game.run_until_input();
+    assert_eq!(level_id(&game), bottom);
+
+    assert_eq!(step_into_pit(&mut game), TurnState::AwaitingInput);
+    assert_eq!(level_id(&game), bottom);
+    assert!(!game.report().died);
+}
+
+#[test]
+fn a_monster_killed_by_a_fall_is_buried_where_it_fell() {
+    let mut game = HeadlessGame::new(GameSeed(3));
+    descend(&mut game);
+    let monster = <(Entity, &mut Pools)>::query()
+        .filter(component::<Point>() & !component::<Player>())
+        .iter_mut(game.world_mut())
+        .map(|(entity, stats)| {
+            stats.hit_points.current = 1;
+            *entity
+        })
+        .next()
+        .expect("No monsters on the level");
+    game.resources_mut()
+        .get_mut::<EffectQueue>()
+        .unwrap()
+        .add_effect(None, EffectType::Fall, Targets::Single { target: monster });
+    press(&mut game, VirtualKeyCode::Period);
+
+    assert!(game.world().entry_ref(monster).is_err());
+}
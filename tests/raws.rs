@@ -0,0 +1,10 @@
+use rrlt::raws::{read_raws, validate_raws};
+
+#[test]
+fn the_bundled_raws_are_valid() {
+    let problems: Vec<String> = validate_raws(&read_raws())
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
@@ -0,0 +1,195 @@
+use rrlt::headless::{play_replay, HeadlessGame};
+use rrlt::prelude::*;
+use rrlt::replay::{Replay, ReplayRecorder};
+use rrlt::KeyState;
+use std::fs;
+
+fn replay_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("rrlt_{}_{}.jsonl", name, std::process::id()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn press(game: &mut HeadlessGame, key: VirtualKeyCode) -> TurnState {
+    game.send(KeyState::with_key(key))
+}
+
+// Standing still heals a point, so this has to be more than a killing blow.
+fn kill_player(game: &mut HeadlessGame) {
+    <&mut Pools>::query()
+        .filter(component::<Player>())
+        .for_each_mut(game.world_mut(), |stats| stats.hit_points.current = -10);
+    press(game, VirtualKeyCode::Period);
+}
+
+#[test]
+fn a_replay_keeps_its_seed_and_inputs() {
+    let path = replay_path("header");
+    let mut recorder =
+        ReplayRecorder::create(&path, GameSeed(9), SaveSlot(0), GameMode::Permadeath).unwrap();
+    recorder.record(&KeyState::with_key(VirtualKeyCode::Left));
+    // Keys the game ignores aren't worth recording.
+    recorder.record(&KeyState::with_key(VirtualKeyCode::F12));
+    drop(recorder);
+
+    let mut replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.seed, GameSeed(9));
+    let input = replay.next_input().unwrap();
+    assert_eq!(input.key, Some(VirtualKeyCode::Left));
+    assert!(replay.next_input().is_none());
+}
+
+#[test]
+fn a_replay_remembers_how_the_game_was_started() {
+    let path = replay_path("mode");
+    let recorder =
+        ReplayRecorder::create(&path, GameSeed(9), SaveSlot(2), GameMode::Explore).unwrap();
+    drop(recorder);
+
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.mode, GameMode::Explore);
+    assert_eq!(replay.slot, SaveSlot(2));
+}
+
+#[test]
+fn older_replays_are_permadeath_games() {
+    let path = replay_path("legacy");
+    fs::write(&path, "{\"version\":1,\"seed\":9}\n").unwrap();
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.mode, GameMode::Permadeath);
+    assert_eq!(replay.slot, SaveSlot(0));
+}
+
+#[test]
+fn a_half_written_line_ends_the_replay() {
+    let path = replay_path("truncated");
+    let mut recorder =
+        ReplayRecorder::create(&path, GameSeed(9), SaveSlot(0), GameMode::Permadeath).unwrap();
+    recorder.record(&KeyState::with_key(VirtualKeyCode::Up));
+    drop(recorder);
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str("{\"shift\":fal");
+    fs::write(&path, text).unwrap();
+
+    let mut replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(replay.next_input().is_some());
+    assert!(replay.next_input().is_none());
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let path = replay_path("version");
+    fs::write(&path, "{\"version\":99,\"seed\":9}\n").unwrap();
+    let replay = Replay::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(replay.is_err());
+}
+
+#[test]
+fn a_replay_plays_the_recorded_game_again() {
+    let keys = [
+        VirtualKeyCode::Left,
+        VirtualKeyCode::Up,
+        VirtualKeyCode::Period,
+        VirtualKeyCode::Right,
+        VirtualKeyCode::Down,
+    ];
+    let path = replay_path("playback");
+    let mut recorder =
+        ReplayRecorder::create(&path, GameSeed(5), SaveSlot(0), GameMode::Permadeath).unwrap();
+    let mut game = HeadlessGame::new(GameSeed(5));
+    for key in keys.iter() {
+        recorder.record(&KeyState::with_key(*key));
+        press(&mut game, *key);
+    }
+    drop(recorder);
+
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(play_replay(replay), game.report());
+}
+
+#[test]
+fn a_replay_steps_through_level_builds_as_they_were_watched() {
+    let path = replay_path("mapgen");
+    let mut recorder =
+        ReplayRecorder::create(&path, GameSeed(5), SaveSlot(0), GameMode::Permadeath).unwrap();
+    let mut game = HeadlessGame::new(GameSeed(5));
+    let mut play = |game: &mut HeadlessGame, key: VirtualKeyCode| {
+        recorder.record(&KeyState::with_key(key));
+        press(game, key)
+    };
+    play(&mut game, VirtualKeyCode::Backslash);
+    play(&mut game, VirtualKeyCode::V);
+    play(&mut game, VirtualKeyCode::Backslash);
+    let state = play(&mut game, VirtualKeyCode::T);
+    assert!(matches!(state, TurnState::MapBuilding { .. }));
+    play(&mut game, VirtualKeyCode::Right);
+    play(&mut game, VirtualKeyCode::Right);
+    assert_eq!(
+        play(&mut game, VirtualKeyCode::Return),
+        TurnState::AwaitingInput
+    );
+    play(&mut game, VirtualKeyCode::Left);
+    play(&mut game, VirtualKeyCode::Up);
+    drop(recorder);
+
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let report = play_replay(replay);
+    assert_eq!(report.depth, 1);
+    assert_eq!(report, game.report());
+}
+
+#[test]
+fn an_explore_replay_plays_on_after_a_death() {
+    let path = replay_path("explore");
+    let mut recorder =
+        ReplayRecorder::create(&path, GameSeed(4), SaveSlot(1), GameMode::Explore).unwrap();
+    for _ in 0..5 {
+        recorder.record(&KeyState::with_key(VirtualKeyCode::Period));
+    }
+    drop(recorder);
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let report = play_replay(replay);
+    assert!(!report.died);
+    assert!(report.turns > 0);
+}
+
+#[test]
+fn explore_mode_returns_to_the_last_save_on_death() {
+    let mut game = HeadlessGame::starting(
+        GameSeed(4),
+        TurnState::NewGame {
+            slot: SaveSlot(1),
+            mode: GameMode::Explore,
+        },
+    );
+    kill_player(&mut game);
+    assert_eq!(game.turn_state(), TurnState::GameOver);
+
+    let state = press(&mut game, VirtualKeyCode::Space);
+    assert_eq!(state, TurnState::AwaitingInput);
+    assert_eq!(
+        *game.resources().get::<GameMode>().unwrap(),
+        GameMode::Explore
+    );
+    assert!(game.report().hit_points > 0);
+}
+
+#[test]
+fn permadeath_returns_to_the_menu_on_death() {
+    let mut game = HeadlessGame::new(GameSeed(4));
+    kill_player(&mut game);
+    assert_eq!(game.turn_state(), TurnState::GameOver);
+
+    let state = press(&mut game, VirtualKeyCode::Space);
+    assert!(state.in_main_menu());
+}